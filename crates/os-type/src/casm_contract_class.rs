@@ -3,7 +3,7 @@ use crate::hash::GenericClassHash;
 use std::cell::OnceCell;
 use std::sync::Arc;
 
-pub type CairoLangCasmClass = cairo_lang_starknet_classes::casm_contract_class::CasmContractClass;
+pub type CairoLangCasmClass = cairo_lang_starknet::casm_contract_class::CasmContractClass;
 pub type BlockifierCasmClass = blockifier::execution::contract_class::ContractClassV1;
 
 #[derive(Debug, Clone)]
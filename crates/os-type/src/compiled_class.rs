@@ -1,8 +1,11 @@
+use blockifier::execution::contract_class::ContractClass as BlockifierContractClass;
+
 use crate::casm_contract_class::GenericCasmContractClass;
 use crate::deprecated_compiled_class::GenericDeprecatedCompiledClass;
 use crate::error::ContractClassError;
 use crate::hash::GenericClassHash;
 
+#[derive(Debug, Clone)]
 pub enum GenericCompiledClass {
     Cairo0(GenericDeprecatedCompiledClass),
     Cairo1(GenericCasmContractClass),
@@ -15,4 +18,19 @@ impl GenericCompiledClass {
             GenericCompiledClass::Cairo1(casm_class) => casm_class.class_hash(),
         }
     }
+
+    pub fn get_blockifier_contract_class(
+        &self,
+    ) -> Result<BlockifierContractClass, ContractClassError> {
+        let blockifier_class = match self {
+            GenericCompiledClass::Cairo0(deprecated_class) => BlockifierContractClass::V0(
+                deprecated_class.get_blockifier_contract_class()?.clone(),
+            ),
+            GenericCompiledClass::Cairo1(casm_class) => {
+                BlockifierContractClass::V1(casm_class.get_blockifier_contract_class()?.clone())
+            }
+        };
+
+        Ok(blockifier_class)
+    }
 }
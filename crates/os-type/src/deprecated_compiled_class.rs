@@ -4,7 +4,6 @@ use crate::arcane_core_addons::{
 use crate::error::{ContractClassError, ConversionError};
 use crate::hash::GenericClassHash;
 use serde::{Deserialize, Deserializer, Serialize, Serializer};
-use starknet_gateway_types::class_hash::compute_class_hash;
 use std::cell::OnceCell;
 use std::sync::Arc;
 
@@ -35,6 +34,18 @@ impl From<BlockifierDeprecatedClass> for GenericDeprecatedCompiledClass {
     }
 }
 
+impl From<StarknetCoreDeprecatedClass> for GenericDeprecatedCompiledClass {
+    fn from(starknet_core_class: StarknetCoreDeprecatedClass) -> Self {
+        Self {
+            blockifier_contract_class: Default::default(),
+            starknet_api_contract_class: Default::default(),
+            starknet_core_contract_class: OnceCell::from(Arc::new(starknet_core_class)),
+            serialized_class: Default::default(),
+            class_hash: Default::default(),
+        }
+    }
+}
+
 impl TryFrom<CompressedStarknetCoreDeprecatedClass> for GenericDeprecatedCompiledClass {
     type Error = LegacyContractDecompressionError;
 
@@ -68,6 +79,12 @@ impl GenericDeprecatedCompiledClass {
         ))
     }
 
+    fn build_starknet_core_class(&self) -> Result<StarknetCoreDeprecatedClass, ContractClassError> {
+        let serialized_class = self.get_serialized_contract_class()?;
+        let contract_class = serde_json::from_slice(serialized_class)?;
+        Ok(contract_class)
+    }
+
     fn build_blockifier_class(&self) -> Result<BlockifierDeprecatedClass, ContractClassError> {
         let serialized_class = self
             .serialized_class
@@ -85,6 +102,14 @@ impl GenericDeprecatedCompiledClass {
             .map(|boxed| boxed.as_ref())
     }
 
+    pub fn get_starknet_core_contract_class(
+        &self,
+    ) -> Result<&StarknetCoreDeprecatedClass, ContractClassError> {
+        self.starknet_core_contract_class
+            .get_or_try_init(|| self.build_starknet_core_class().map(Arc::new))
+            .map(|boxed| boxed.as_ref())
+    }
+
     pub fn get_blockifier_contract_class(
         &self,
     ) -> Result<&BlockifierDeprecatedClass, ContractClassError> {
@@ -114,13 +139,12 @@ impl GenericDeprecatedCompiledClass {
     }
 
     fn compute_class_hash(&self) -> Result<GenericClassHash, ContractClassError> {
-        let serialized_class = self.get_serialized_contract_class()?;
-        let class_hash = compute_class_hash(serialized_class)
+        let starknet_core_contract_class = self.get_starknet_core_contract_class()?;
+        let class_hash = starknet_core_contract_class
+            .class_hash()
             .map_err(|e| ContractClassError::HashError(e.to_string()))?;
 
-        Ok(GenericClassHash::from_bytes_be(
-            class_hash.hash().0.to_be_bytes(),
-        ))
+        Ok(GenericClassHash::from_bytes_be(class_hash.to_bytes_be()))
     }
 
     pub fn class_hash(&self) -> Result<GenericClassHash, ContractClassError> {
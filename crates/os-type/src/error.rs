@@ -1,4 +1,4 @@
-use cairo_lang_starknet_classes::casm_contract_class::StarknetSierraCompilationError;
+use cairo_lang_starknet::casm_contract_class::StarknetSierraCompilationError;
 use std::error::Error;
 
 #[derive(thiserror::Error, Debug)]
@@ -9,7 +9,7 @@ use starknet_types_core::felt::Felt;
 use std::cell::OnceCell;
 use std::sync::Arc;
 
-pub type CairoLangSierraContractClass = cairo_lang_starknet_classes::contract_class::ContractClass;
+pub type CairoLangSierraContractClass = cairo_lang_starknet::contract_class::ContractClass;
 pub type StarknetCoreSierraContractClass = starknet_core::types::FlattenedSierraClass;
 
 #[derive(Debug, Clone)]
@@ -26,7 +26,7 @@ pub struct FlattenedSierraClassWithAbi {
     pub sierra_program: Vec<Felt>,
     pub contract_class_version: String,
     pub entry_points_by_type: EntryPointsByType,
-    pub abi: Option<cairo_lang_starknet_classes::abi::Contract>,
+    pub abi: Option<cairo_lang_starknet::abi::Contract>,
 }
 
 impl From<StarknetCoreSierraContractClass> for GenericSierraContractClass {
@@ -137,12 +137,8 @@ impl GenericSierraContractClass {
     pub fn compile(&self) -> Result<GenericCasmContractClass, ContractClassError> {
         let cairo_lang_class = self.get_cairo_lang_contract_class()?.clone();
         let add_pythonic_hints = false;
-        let max_bytecode_size = 180000;
-        let casm_contract_class = CairoLangCasmClass::from_contract_class(
-            cairo_lang_class,
-            add_pythonic_hints,
-            max_bytecode_size,
-        )?;
+        let casm_contract_class =
+            CairoLangCasmClass::from_contract_class(cairo_lang_class, add_pythonic_hints)?;
 
         Ok(GenericCasmContractClass::from(casm_contract_class))
     }
@@ -0,0 +1,49 @@
+//! With the `embedded-os` feature, copies the compiled OS to `OUT_DIR` so that it can be embedded
+//! in the crate, after checking its program hash against the pinned one.
+
+use std::env;
+use std::path::PathBuf;
+
+use starknet_crypto::FieldElement;
+
+#[path = "src/program_hash.rs"]
+mod program_hash;
+
+const COMPILED_OS_VAR: &str = "ARCANE_OS_COMPILED_OS";
+const PROGRAM_HASH_VAR: &str = "ARCANE_OS_PROGRAM_HASH";
+const DEFAULT_COMPILED_OS: &str = "../../build/os_latest.json";
+
+fn main() {
+    if env::var_os("CARGO_FEATURE_EMBEDDED_OS").is_none() {
+        return;
+    }
+    println!("cargo:rerun-if-env-changed={COMPILED_OS_VAR}");
+    println!("cargo:rerun-if-env-changed={PROGRAM_HASH_VAR}");
+
+    let manifest_dir = PathBuf::from(env::var("CARGO_MANIFEST_DIR").unwrap());
+    let compiled_os_path =
+        manifest_dir.join(env::var(COMPILED_OS_VAR).unwrap_or_else(|_| DEFAULT_COMPILED_OS.to_string()));
+    println!("cargo:rerun-if-changed={}", compiled_os_path.display());
+
+    let expected_hash = env::var(PROGRAM_HASH_VAR)
+        .unwrap_or_else(|_| panic!("{PROGRAM_HASH_VAR} must pin the program hash of the embedded OS"));
+    let expected_hash = FieldElement::from_hex_be(&expected_hash)
+        .unwrap_or_else(|e| panic!("{PROGRAM_HASH_VAR} is not a valid hash: {e}"));
+
+    let compiled_os = std::fs::read(&compiled_os_path)
+        .unwrap_or_else(|e| panic!("could not read the compiled OS {}: {e}", compiled_os_path.display()));
+    let program_hash = program_hash::compute_program_hash(&compiled_os)
+        .unwrap_or_else(|e| panic!("could not hash the compiled OS {}: {e}", compiled_os_path.display()));
+    if program_hash != expected_hash {
+        panic!(
+            "the compiled OS {} has program hash {:#x}, expected {:#x}",
+            compiled_os_path.display(),
+            program_hash,
+            expected_hash
+        );
+    }
+
+    let out_dir = PathBuf::from(env::var("OUT_DIR").unwrap());
+    std::fs::write(out_dir.join("compiled_os.json"), compiled_os).unwrap();
+    println!("cargo:rustc-env={PROGRAM_HASH_VAR}={program_hash:#x}");
+}
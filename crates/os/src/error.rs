@@ -1,6 +1,8 @@
 use cairo_vm::vm::errors::cairo_run_errors::CairoRunError;
 use cairo_vm::Felt252;
 
+use crate::layout::RejectedLayout;
+
 #[derive(thiserror::Error, Debug)]
 pub enum ArcaneError {
     #[error("SnOs Error: {0}")]
@@ -15,6 +17,8 @@ pub enum ArcaneError {
     SharpRequest(String),
     #[error("Starknet Os Runner Error: {0}")]
     Runner(CairoRunError),
+    #[error("Prover Input Error: {0}")]
+    ProverInput(String),
     #[error("SnOs Output Error: {0}")]
     Output(String),
     #[error(transparent)]
@@ -23,6 +27,8 @@ pub enum ArcaneError {
     SerdeJson(#[from] serde_json::Error),
     #[error("SnOs Deprecated Syscall Error: {0}")]
     InvalidDeprecatedSyscallSelector(Felt252),
+    #[error("No layout fits the OS run: {}", .0.iter().map(ToString::to_string).collect::<Vec<_>>().join("; "))]
+    NoSuitableLayout(Vec<RejectedLayout>),
 }
 
 #[derive(thiserror::Error, Clone, Debug)]
@@ -14,15 +14,18 @@ use starknet_api::deprecated_contract_class::EntryPointType;
 
 use crate::config::STORED_BLOCK_HASH_BUFFER;
 use crate::crypto::pedersen::PedersenHash;
+use crate::execution::os_logger::{OsLogger, ResourceSnapshot};
+use crate::execution::resource_report::ResourceProfiler;
 use crate::starknet::starknet_storage::{CommitmentInfo, CommitmentInfoError, OsSingleStarknetStorage};
 use crate::storage::dict_storage::DictStorage;
 use crate::storage::storage::StorageError;
+use crate::utils::felt_api2vm;
 
 // TODO: make the execution helper generic over the storage and hash function types.
 pub type ContractStorageMap = HashMap<Felt252, OsSingleStarknetStorage<DictStorage, PedersenHash>>;
 
 /// Maintains the info for executing txns in the OS
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub struct ExecutionHelper {
     pub _prev_block_context: Option<BlockContext>,
     // Pointer tx execution info
@@ -51,6 +54,10 @@ pub struct ExecutionHelper {
     pub execute_code_read_iter: IntoIter<Felt252>,
     // Per-contract storage
     pub storage_by_address: ContractStorageMap,
+    // Traces the transactions and syscalls executed by the OS
+    pub os_logger: OsLogger,
+    // Attributes the resources used by the OS to transactions and calls
+    pub resource_profiler: ResourceProfiler,
 }
 
 /// ExecutionHelper is wrapped in Rc<RefCell<_>> in order
@@ -86,10 +93,18 @@ impl ExecutionHelperWrapper {
                 deployed_contracts_iter: vec![].into_iter(),
                 execute_code_read_iter: vec![].into_iter(),
                 storage_by_address: contract_storage_map,
+                os_logger: OsLogger::default(),
+                resource_profiler: ResourceProfiler::default(),
             })),
         }
     }
 
+    /// Returns a helper with its own copy of the execution state, unlike `clone` which shares it.
+    /// The OS consumes the helper as it runs, so each run needs a fresh one.
+    pub fn deep_clone(&self) -> Self {
+        Self { execution_helper: Rc::new(RefCell::new(self.execution_helper.borrow().clone())) }
+    }
+
     pub fn get_old_block_number_and_hash(&self) -> Result<(Felt252, Felt252), HintError> {
         let eh_ref = self.execution_helper.as_ref().borrow();
         eh_ref.old_block_number_and_hash.ok_or(HintError::AssertionFailed(
@@ -174,6 +189,34 @@ impl ExecutionHelperWrapper {
         self.exit_call();
     }
 
+    /// Logs the start of a syscall issued by the current call.
+    pub fn os_logger_enter_syscall(
+        &self,
+        syscall: String,
+        deprecated: bool,
+        resources: ResourceSnapshot,
+    ) -> Result<(), HintError> {
+        let mut eh_ref = self.execution_helper.as_ref().borrow_mut();
+        let (contract_address, selector) = match &eh_ref.call_info {
+            Some(call_info) => (
+                Some(felt_api2vm(*call_info.call.storage_address.0.key())),
+                Some(felt_api2vm(call_info.call.entry_point_selector.0)),
+            ),
+            None => (None, None),
+        };
+        eh_ref.os_logger.enter_syscall(syscall, deprecated, contract_address, selector, resources)
+    }
+
+    /// Starts profiling the call entered with `enter_call`.
+    pub fn resource_profiler_enter_call(&self, resources: ResourceSnapshot) -> Result<(), HintError> {
+        let mut eh_ref = self.execution_helper.as_ref().borrow_mut();
+        let call = &eh_ref.call_info.as_ref().ok_or(HintError::CustomHint("No call to profile".into()))?.call;
+        let contract_address = felt_api2vm(*call.storage_address.0.key());
+        let class_hash = call.class_hash.map(|class_hash| felt_api2vm(class_hash.0));
+        let selector = felt_api2vm(call.entry_point_selector.0);
+        eh_ref.resource_profiler.enter_call(contract_address, class_hash, selector, resources)
+    }
+
     pub fn read_storage_for_address(&mut self, address: Felt252, key: Felt252) -> Result<Felt252, StorageError> {
         let storage_by_address = &mut self.execution_helper.as_ref().borrow_mut().storage_by_address;
         if let Some(storage) = storage_by_address.get_mut(&address) {
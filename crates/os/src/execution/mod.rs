@@ -2,6 +2,8 @@ mod constants;
 pub mod deprecated_syscall_handler;
 pub mod execute_syscalls;
 pub mod helper;
+pub mod os_logger;
+pub mod resource_report;
 pub mod syscall_handler;
 pub mod syscall_utils;
 mod syscalls;
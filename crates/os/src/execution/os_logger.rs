@@ -0,0 +1,328 @@
+use std::collections::BTreeMap;
+
+use cairo_vm::types::relocatable::Relocatable;
+use cairo_vm::vm::errors::hint_errors::HintError;
+use cairo_vm::vm::vm_core::VirtualMachine;
+use cairo_vm::Felt252;
+use serde::Serialize;
+
+/// VM step and builtin instances used since the start of the run, at some point of the run.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct ResourceSnapshot {
+    pub n_steps: usize,
+    /// Builtin instances by builtin name.
+    pub builtin_instances: BTreeMap<String, usize>,
+}
+
+impl ResourceSnapshot {
+    /// Takes a snapshot from the builtin pointers of the OS.
+    ///
+    /// `builtin_ptrs` points to a `BuiltinPointers` struct, whose members are pointers to the
+    /// next free cell of each builtin segment. The range check pointer of the struct is not kept
+    /// up to date by the OS, the current one is passed separately.
+    pub fn take(
+        vm: &VirtualMachine,
+        builtin_ptrs: Relocatable,
+        range_check_ptr: Relocatable,
+    ) -> Result<Self, HintError> {
+        let builtin_runners = vm.get_builtin_runners();
+        let mut builtin_instances = BTreeMap::new();
+
+        // The members of the struct are read until one is not a builtin pointer, which does not
+        // depend on the layout of the struct in a given OS version
+        let mut add_builtin_ptr = |ptr: Relocatable| {
+            let runner = builtin_runners.iter().find(|runner| runner.base() as isize == ptr.segment_index)?;
+            let instances = ptr.offset / runner.cells_per_instance() as usize;
+            builtin_instances.insert(runner.name().to_str().to_string(), instances);
+            Some(())
+        };
+        for i in 0..builtin_runners.len() {
+            let Ok(ptr) = vm.get_relocatable((builtin_ptrs + i)?) else {
+                break;
+            };
+            if add_builtin_ptr(ptr).is_none() {
+                break;
+            }
+        }
+        add_builtin_ptr(range_check_ptr);
+
+        Ok(Self { n_steps: vm.get_current_step(), builtin_instances })
+    }
+
+    /// Resources used between `start` and this snapshot.
+    pub fn since(&self, start: &ResourceSnapshot) -> ResourceSnapshot {
+        let builtin_instances = self
+            .builtin_instances
+            .iter()
+            .map(|(builtin, instances)| {
+                let start_instances = start.builtin_instances.get(builtin).copied().unwrap_or_default();
+                (builtin.clone(), instances.saturating_sub(start_instances))
+            })
+            .collect();
+
+        ResourceSnapshot { n_steps: self.n_steps.saturating_sub(start.n_steps), builtin_instances }
+    }
+}
+
+/// An event of the OS logger, equivalent to the logs of `OsLogger` in cairo-lang.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum OsLogEvent {
+    EnterTx {
+        tx_index: usize,
+        tx_type: String,
+        tx_hash: Felt252,
+        resources: ResourceSnapshot,
+    },
+    ExitTx {
+        tx_index: usize,
+        resources: ResourceSnapshot,
+        /// Resources used by the transaction.
+        used: ResourceSnapshot,
+    },
+    EnterSyscall {
+        tx_index: usize,
+        /// Number of syscalls being executed, including this one.
+        depth: usize,
+        syscall: String,
+        deprecated: bool,
+        /// Address of the contract issuing the syscall, if known.
+        contract_address: Option<Felt252>,
+        /// Entry point of the contract issuing the syscall, if known.
+        selector: Option<Felt252>,
+        resources: ResourceSnapshot,
+    },
+    ExitSyscall {
+        tx_index: usize,
+        depth: usize,
+        syscall: String,
+        resources: ResourceSnapshot,
+        /// Resources used by the syscall, including its inner calls.
+        used: ResourceSnapshot,
+    },
+}
+
+/// Tracks the resources used by the current transaction and by the frames nested in it:
+/// syscalls for the [`OsLogger`], calls for the
+/// [`ResourceProfiler`](crate::execution::resource_report::ResourceProfiler).
+#[derive(Debug, Clone)]
+pub struct ResourceTracker<Tx, Frame> {
+    current_tx: Option<(Tx, ResourceSnapshot)>,
+    stack: Vec<(Frame, ResourceSnapshot)>,
+}
+
+impl<Tx, Frame> Default for ResourceTracker<Tx, Frame> {
+    fn default() -> Self {
+        Self { current_tx: None, stack: vec![] }
+    }
+}
+
+impl<Tx, Frame> ResourceTracker<Tx, Frame> {
+    pub fn enter_tx(&mut self, tx: Tx, resources: ResourceSnapshot) -> Result<(), &'static str> {
+        if self.current_tx.is_some() {
+            return Err("entering a transaction before exiting the previous one");
+        }
+        self.current_tx = Some((tx, resources));
+        Ok(())
+    }
+
+    /// Returns the exited transaction and the resources it used.
+    pub fn exit_tx(&mut self, resources: &ResourceSnapshot) -> Result<(Tx, ResourceSnapshot), &'static str> {
+        if !self.stack.is_empty() {
+            return Err("exiting a transaction before exiting its syscalls or calls");
+        }
+        let (tx, start) = self.current_tx.take().ok_or("no transaction to exit")?;
+        Ok((tx, resources.since(&start)))
+    }
+
+    /// Returns the depth of the entered frame, including itself.
+    pub fn enter(&mut self, frame: Frame, resources: ResourceSnapshot) -> Result<usize, &'static str> {
+        if self.current_tx.is_none() {
+            return Err("entering a syscall or call outside of a transaction");
+        }
+        self.stack.push((frame, resources));
+        Ok(self.stack.len())
+    }
+
+    /// Returns the exited frame, its depth and the resources it used, including its inner frames.
+    pub fn exit(&mut self, resources: &ResourceSnapshot) -> Result<(Frame, usize, ResourceSnapshot), &'static str> {
+        let depth = self.stack.len();
+        let (frame, start) = self.stack.pop().ok_or("no syscall or call to exit")?;
+        Ok((frame, depth, resources.since(&start)))
+    }
+
+    pub fn current_tx(&self) -> Option<&Tx> {
+        self.current_tx.as_ref().map(|(tx, _)| tx)
+    }
+
+    pub fn current_tx_mut(&mut self) -> Option<&mut Tx> {
+        self.current_tx.as_mut().map(|(tx, _)| tx)
+    }
+
+    /// The innermost frame being executed.
+    pub fn current_frame_mut(&mut self) -> Option<&mut Frame> {
+        self.stack.last_mut().map(|(frame, _)| frame)
+    }
+}
+
+/// Tracks the transactions and syscalls executed by the OS.
+///
+/// Each event is logged at debug level with the `os_logger` target, and collected in the
+/// trace returned with the result of the run.
+#[derive(Debug, Clone, Default)]
+pub struct OsLogger {
+    /// Index of the next transaction.
+    n_txs: usize,
+    /// Index of the current transaction, and the syscalls being executed.
+    tracker: ResourceTracker<usize, String>,
+    events: Vec<OsLogEvent>,
+}
+
+impl OsLogger {
+    pub fn enter_tx(
+        &mut self,
+        tx_type: String,
+        tx_hash: Felt252,
+        resources: ResourceSnapshot,
+    ) -> Result<(), HintError> {
+        let tx_index = self.n_txs;
+        self.tracker.enter_tx(tx_index, resources.clone()).map_err(logger_error)?;
+        self.n_txs += 1;
+        log::debug!(target: "os_logger", "Entering transaction {tx_index}: {tx_type} {}", tx_hash.to_hex_string());
+
+        self.events.push(OsLogEvent::EnterTx { tx_index, tx_type, tx_hash, resources });
+        Ok(())
+    }
+
+    pub fn exit_tx(&mut self, resources: ResourceSnapshot) -> Result<(), HintError> {
+        let (tx_index, used) = self.tracker.exit_tx(&resources).map_err(logger_error)?;
+        log::debug!(
+            target: "os_logger",
+            "Exiting transaction {tx_index}: {} steps, {:?}",
+            used.n_steps,
+            used.builtin_instances
+        );
+
+        self.events.push(OsLogEvent::ExitTx { tx_index, resources, used });
+        Ok(())
+    }
+
+    pub fn enter_syscall(
+        &mut self,
+        syscall: String,
+        deprecated: bool,
+        contract_address: Option<Felt252>,
+        selector: Option<Felt252>,
+        resources: ResourceSnapshot,
+    ) -> Result<(), HintError> {
+        let depth = self.tracker.enter(syscall.clone(), resources.clone()).map_err(logger_error)?;
+        let tx_index = self.current_tx_index()?;
+        log::debug!(target: "os_logger", "{}Entering {syscall}", indent(depth));
+
+        self.events.push(OsLogEvent::EnterSyscall {
+            tx_index,
+            depth,
+            syscall,
+            deprecated,
+            contract_address,
+            selector,
+            resources,
+        });
+        Ok(())
+    }
+
+    pub fn exit_syscall(&mut self, resources: ResourceSnapshot) -> Result<(), HintError> {
+        let (syscall, depth, used) = self.tracker.exit(&resources).map_err(logger_error)?;
+        let tx_index = self.current_tx_index()?;
+        log::debug!(
+            target: "os_logger",
+            "{}Exiting {}: {} steps, {:?}",
+            indent(depth),
+            syscall,
+            used.n_steps,
+            used.builtin_instances
+        );
+
+        self.events.push(OsLogEvent::ExitSyscall { tx_index, depth, syscall, resources, used });
+        Ok(())
+    }
+
+    pub fn events(&self) -> &[OsLogEvent] {
+        &self.events
+    }
+
+    /// Returns the events logged so far and clears them.
+    pub fn take_events(&mut self) -> Vec<OsLogEvent> {
+        std::mem::take(&mut self.events)
+    }
+
+    fn current_tx_index(&self) -> Result<usize, HintError> {
+        self.tracker.current_tx().copied().ok_or_else(|| logger_error("no current transaction"))
+    }
+}
+
+fn indent(depth: usize) -> String {
+    "  ".repeat(depth)
+}
+
+fn logger_error(message: &str) -> HintError {
+    HintError::CustomHint(format!("OS logger: {message}").into_boxed_str())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn snapshot(n_steps: usize, pedersen: usize) -> ResourceSnapshot {
+        ResourceSnapshot { n_steps, builtin_instances: BTreeMap::from([("pedersen".to_string(), pedersen)]) }
+    }
+
+    #[test]
+    fn test_resource_snapshot_since() {
+        assert_eq!(snapshot(150, 7).since(&snapshot(100, 2)), snapshot(50, 5));
+    }
+
+    #[test]
+    fn test_os_logger_events() {
+        let mut logger = OsLogger::default();
+        logger.enter_tx("INVOKE_FUNCTION".to_string(), Felt252::ONE, snapshot(0, 0)).unwrap();
+        logger.enter_syscall("StorageRead".to_string(), false, None, None, snapshot(10, 1)).unwrap();
+        logger.exit_syscall(snapshot(30, 2)).unwrap();
+        logger.exit_tx(snapshot(100, 4)).unwrap();
+
+        let events = logger.take_events();
+        assert_eq!(events.len(), 4);
+        assert_eq!(
+            events[2],
+            OsLogEvent::ExitSyscall {
+                tx_index: 0,
+                depth: 1,
+                syscall: "StorageRead".to_string(),
+                resources: snapshot(30, 2),
+                used: snapshot(20, 1),
+            }
+        );
+        assert_eq!(events[3], OsLogEvent::ExitTx { tx_index: 0, resources: snapshot(100, 4), used: snapshot(100, 4) });
+        assert!(logger.events().is_empty());
+    }
+
+    #[test]
+    fn test_resource_tracker() {
+        let mut tracker = ResourceTracker::<usize, &str>::default();
+        tracker.enter_tx(0, snapshot(0, 0)).unwrap();
+        assert_eq!(tracker.enter("outer", snapshot(10, 1)).unwrap(), 1);
+        assert_eq!(tracker.enter("inner", snapshot(20, 1)).unwrap(), 2);
+        assert_eq!(tracker.exit(&snapshot(25, 2)).unwrap(), ("inner", 2, snapshot(5, 1)));
+        assert!(tracker.exit_tx(&snapshot(30, 2)).is_err());
+        assert_eq!(tracker.exit(&snapshot(30, 2)).unwrap(), ("outer", 1, snapshot(20, 1)));
+        assert_eq!(tracker.exit_tx(&snapshot(40, 2)).unwrap(), (0, snapshot(40, 2)));
+        assert!(tracker.exit(&snapshot(50, 2)).is_err());
+    }
+
+    #[test]
+    fn test_os_logger_syscall_outside_of_tx() {
+        let mut logger = OsLogger::default();
+        assert!(logger.enter_syscall("StorageRead".to_string(), false, None, None, snapshot(0, 0)).is_err());
+        assert!(logger.exit_tx(snapshot(0, 0)).is_err());
+    }
+}
@@ -0,0 +1,251 @@
+use std::fmt::Write;
+
+use cairo_vm::vm::errors::hint_errors::HintError;
+use cairo_vm::Felt252;
+use serde::Serialize;
+
+use crate::execution::os_logger::{ResourceSnapshot, ResourceTracker};
+
+/// Resources used by a call of the OS, including its inner calls.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct CallResources {
+    pub contract_address: Felt252,
+    pub class_hash: Option<Felt252>,
+    pub selector: Felt252,
+    pub used: ResourceSnapshot,
+    pub inner_calls: Vec<CallResources>,
+}
+
+/// Resources used by a transaction, from `START_TX` to `END_TX`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct TxResources {
+    /// Hash of the transaction, unknown for transactions the OS starts without loading them.
+    pub tx_hash: Option<Felt252>,
+    pub used: ResourceSnapshot,
+    /// The calls of the transaction: validation, execution and fee transfer.
+    pub calls: Vec<CallResources>,
+}
+
+/// Steps and builtins used by each transaction of an OS run and by each of their calls.
+///
+/// Meant to find what does not fit a layout, see [`crate::layout::LayoutSelection`].
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct ResourceReport {
+    pub transactions: Vec<TxResources>,
+}
+
+/// The resource used as the weight of the folded stacks of a [`ResourceReport`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ResourceWeight<'a> {
+    Steps,
+    /// Instances of the builtin with this name.
+    Builtin(&'a str),
+}
+
+impl ResourceWeight<'_> {
+    fn of(&self, resources: &ResourceSnapshot) -> usize {
+        match self {
+            ResourceWeight::Steps => resources.n_steps,
+            ResourceWeight::Builtin(builtin) => resources.builtin_instances.get(*builtin).copied().unwrap_or_default(),
+        }
+    }
+}
+
+impl ResourceReport {
+    pub fn to_json(&self) -> Result<String, serde_json::Error> {
+        serde_json::to_string_pretty(self)
+    }
+
+    /// Formats the report as folded stacks, one `frame;frame;... weight` line per transaction
+    /// and call, as expected by flamegraph tools.
+    ///
+    /// The weight of each line excludes the inner calls, which have their own lines. Frames
+    /// are named `tx <hash>` for transactions and `<contract address>:<class hash>:<selector>`
+    /// for calls.
+    pub fn folded_stacks(&self, weight: ResourceWeight<'_>) -> String {
+        let mut folded = String::new();
+        for (tx_index, tx) in self.transactions.iter().enumerate() {
+            let frame = match &tx.tx_hash {
+                Some(tx_hash) => format!("tx {}", tx_hash.to_hex_string()),
+                None => format!("tx #{tx_index}"),
+            };
+            let inner_weight: usize = tx.calls.iter().map(|call| weight.of(&call.used)).sum();
+            write_folded_line(&mut folded, &frame, weight.of(&tx.used).saturating_sub(inner_weight));
+            for call in &tx.calls {
+                write_folded_call(&mut folded, &frame, call, weight);
+            }
+        }
+
+        folded
+    }
+}
+
+fn write_folded_call(folded: &mut String, stack: &str, call: &CallResources, weight: ResourceWeight<'_>) {
+    let class_hash = call.class_hash.map(|class_hash| class_hash.to_hex_string()).unwrap_or_else(|| "?".to_string());
+    let stack =
+        format!("{stack};{}:{class_hash}:{}", call.contract_address.to_hex_string(), call.selector.to_hex_string());
+
+    let inner_weight: usize = call.inner_calls.iter().map(|call| weight.of(&call.used)).sum();
+    write_folded_line(folded, &stack, weight.of(&call.used).saturating_sub(inner_weight));
+    for inner_call in &call.inner_calls {
+        write_folded_call(folded, &stack, inner_call, weight);
+    }
+}
+
+fn write_folded_line(folded: &mut String, stack: &str, weight: usize) {
+    if weight > 0 {
+        writeln!(folded, "{stack} {weight}").unwrap();
+    }
+}
+
+/// A transaction being executed.
+#[derive(Debug, Clone)]
+struct TxFrame {
+    tx_hash: Option<Felt252>,
+    calls: Vec<CallResources>,
+}
+
+/// A call being executed.
+#[derive(Debug, Clone)]
+struct CallFrame {
+    contract_address: Felt252,
+    class_hash: Option<Felt252>,
+    selector: Felt252,
+    inner_calls: Vec<CallResources>,
+}
+
+/// Builds a [`ResourceReport`] from the transaction and call boundaries of the OS.
+#[derive(Debug, Clone, Default)]
+pub struct ResourceProfiler {
+    tracker: ResourceTracker<TxFrame, CallFrame>,
+    report: ResourceReport,
+}
+
+impl ResourceProfiler {
+    pub fn start_tx(&mut self, tx_hash: Option<Felt252>, resources: ResourceSnapshot) -> Result<(), HintError> {
+        self.tracker.enter_tx(TxFrame { tx_hash, calls: vec![] }, resources).map_err(profiler_error)
+    }
+
+    pub fn end_tx(&mut self, resources: ResourceSnapshot) -> Result<(), HintError> {
+        let (tx, used) = self.tracker.exit_tx(&resources).map_err(profiler_error)?;
+
+        self.report.transactions.push(TxResources { tx_hash: tx.tx_hash, used, calls: tx.calls });
+        Ok(())
+    }
+
+    pub fn enter_call(
+        &mut self,
+        contract_address: Felt252,
+        class_hash: Option<Felt252>,
+        selector: Felt252,
+        resources: ResourceSnapshot,
+    ) -> Result<(), HintError> {
+        let call = CallFrame { contract_address, class_hash, selector, inner_calls: vec![] };
+        self.tracker.enter(call, resources).map_err(profiler_error)?;
+        Ok(())
+    }
+
+    pub fn exit_call(&mut self, resources: ResourceSnapshot) -> Result<(), HintError> {
+        let (call, _depth, used) = self.tracker.exit(&resources).map_err(profiler_error)?;
+        let call = CallResources {
+            contract_address: call.contract_address,
+            class_hash: call.class_hash,
+            selector: call.selector,
+            used,
+            inner_calls: call.inner_calls,
+        };
+
+        match self.tracker.current_frame_mut() {
+            Some(caller) => caller.inner_calls.push(call),
+            None => self
+                .tracker
+                .current_tx_mut()
+                .ok_or_else(|| profiler_error("exiting a call outside of a transaction"))?
+                .calls
+                .push(call),
+        }
+        Ok(())
+    }
+
+    /// Returns the report of the transactions ended so far and clears it.
+    pub fn take_report(&mut self) -> ResourceReport {
+        std::mem::take(&mut self.report)
+    }
+}
+
+fn profiler_error(message: &str) -> HintError {
+    HintError::CustomHint(format!("Resource profiler: {message}").into_boxed_str())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use super::*;
+
+    fn snapshot(n_steps: usize, pedersen: usize) -> ResourceSnapshot {
+        ResourceSnapshot { n_steps, builtin_instances: BTreeMap::from([("pedersen".to_string(), pedersen)]) }
+    }
+
+    fn profile_tx() -> ResourceReport {
+        let mut profiler = ResourceProfiler::default();
+        profiler.start_tx(Some(Felt252::ONE), snapshot(0, 0)).unwrap();
+        profiler.enter_call(Felt252::TWO, Some(Felt252::THREE), Felt252::ZERO, snapshot(10, 0)).unwrap();
+        profiler.enter_call(Felt252::THREE, None, Felt252::ONE, snapshot(20, 1)).unwrap();
+        profiler.exit_call(snapshot(50, 3)).unwrap();
+        profiler.exit_call(snapshot(80, 4)).unwrap();
+        profiler.end_tx(snapshot(100, 4)).unwrap();
+        profiler.take_report()
+    }
+
+    #[test]
+    fn test_resource_profiler() {
+        let report = profile_tx();
+
+        let inner_call = CallResources {
+            contract_address: Felt252::THREE,
+            class_hash: None,
+            selector: Felt252::ONE,
+            used: snapshot(30, 2),
+            inner_calls: vec![],
+        };
+        let call = CallResources {
+            contract_address: Felt252::TWO,
+            class_hash: Some(Felt252::THREE),
+            selector: Felt252::ZERO,
+            used: snapshot(70, 4),
+            inner_calls: vec![inner_call],
+        };
+        assert_eq!(
+            report,
+            ResourceReport {
+                transactions: vec![TxResources {
+                    tx_hash: Some(Felt252::ONE),
+                    used: snapshot(100, 4),
+                    calls: vec![call]
+                }]
+            }
+        );
+    }
+
+    #[test]
+    fn test_folded_stacks() {
+        let report = profile_tx();
+
+        assert_eq!(
+            report.folded_stacks(ResourceWeight::Steps),
+            "tx 0x1 30\ntx 0x1;0x2:0x3:0x0 40\ntx 0x1;0x2:0x3:0x0;0x3:?:0x1 30\n"
+        );
+        assert_eq!(
+            report.folded_stacks(ResourceWeight::Builtin("pedersen")),
+            "tx 0x1;0x2:0x3:0x0 2\ntx 0x1;0x2:0x3:0x0;0x3:?:0x1 2\n"
+        );
+    }
+
+    #[test]
+    fn test_resource_profiler_call_outside_of_tx() {
+        let mut profiler = ResourceProfiler::default();
+        assert!(profiler.enter_call(Felt252::ONE, None, Felt252::ZERO, snapshot(0, 0)).is_err());
+        assert!(profiler.end_tx(snapshot(0, 0)).is_err());
+    }
+}
@@ -49,7 +49,7 @@ pub fn call_contract(
         return Err(SyscallExecutionError::SyscallError { error_data: retdata });
     }
 
-    println!(
+    log::debug!(
         "CallContract syscall, contract address: {}, selector: {} -> failed: {}, {:?}?",
         request.contract_address,
         request.function_selector.to_hex_string(),
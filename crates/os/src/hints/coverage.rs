@@ -0,0 +1,413 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::fmt;
+
+use cairo_lang_casm::hints::{Hint, PythonicHint, StarknetHint};
+use cairo_lang_starknet::casm_contract_class::CasmContractClass;
+use cairo_vm::hint_processor::builtin_hint_processor::hint_code;
+use lazy_static::lazy_static;
+use serde::{Deserialize, Serialize};
+
+use crate::error::SnOsError;
+use crate::hints::SnosHintProcessor;
+
+/// Scope of the hints that do not belong to any function.
+const NO_FUNCTION: &str = "<unknown>";
+
+/// Scope of the missing hints of a compiled class.
+const COMPILED_CLASS: &str = "<compiled class>";
+
+/// Hint codes implemented by the builtin hint processor of cairo-vm, which does not expose
+/// them. Keep in sync with the `match` of `BuiltinHintProcessor::execute_hint` when updating
+/// cairo-vm.
+const BUILTIN_HINT_CODES: &[&str] = &[
+    hint_code::ADD_NO_UINT384_CHECK,
+    hint_code::ADD_SEGMENT,
+    hint_code::ASSERT_250_BITS,
+    hint_code::ASSERT_LE_FELT,
+    hint_code::ASSERT_LE_FELT_EXCLUDED_0,
+    hint_code::ASSERT_LE_FELT_EXCLUDED_1,
+    hint_code::ASSERT_LE_FELT_EXCLUDED_2,
+    hint_code::ASSERT_LE_FELT_V_0_6,
+    hint_code::ASSERT_LE_FELT_V_0_8,
+    hint_code::ASSERT_LT_FELT,
+    hint_code::ASSERT_NN,
+    hint_code::ASSERT_NOT_EQUAL,
+    hint_code::ASSERT_NOT_ZERO,
+    hint_code::A_B_BITAND_1,
+    hint_code::BIGINT_PACK_DIV_MOD,
+    hint_code::BIGINT_SAFE_DIV,
+    hint_code::BIGINT_TO_UINT256,
+    hint_code::BLAKE2S_ADD_UINT256,
+    hint_code::BLAKE2S_ADD_UINT256_BIGEND,
+    hint_code::BLAKE2S_COMPUTE,
+    hint_code::BLAKE2S_FINALIZE,
+    hint_code::BLAKE2S_FINALIZE_V2,
+    hint_code::BLAKE2S_FINALIZE_V3,
+    hint_code::BLOCK_PERMUTATION,
+    hint_code::BLOCK_PERMUTATION_WHITELIST_V1,
+    hint_code::BLOCK_PERMUTATION_WHITELIST_V2,
+    hint_code::CAIRO_KECCAK_FINALIZE_V1,
+    hint_code::CAIRO_KECCAK_FINALIZE_V2,
+    hint_code::CAIRO_KECCAK_INPUT_IS_FULL_WORD,
+    hint_code::CHAINED_EC_OP_RANDOM_EC_POINT,
+    hint_code::COMPARE_BYTES_IN_WORD_NONDET,
+    hint_code::COMPARE_KECCAK_FULL_RATE_IN_BYTES_NONDET,
+    hint_code::COMPUTE_SLOPE_V1,
+    hint_code::COMPUTE_SLOPE_V2,
+    hint_code::COMPUTE_SLOPE_WHITELIST,
+    hint_code::DEFAULT_DICT_NEW,
+    hint_code::DICT_NEW,
+    hint_code::DICT_READ,
+    hint_code::DICT_SQUASH_COPY_DICT,
+    hint_code::DICT_SQUASH_UPDATE_PTR,
+    hint_code::DICT_UPDATE,
+    hint_code::DICT_WRITE,
+    hint_code::DIV_MOD_N_PACKED_DIVMOD_EXTERNAL_N,
+    hint_code::DIV_MOD_N_PACKED_DIVMOD_V1,
+    hint_code::DIV_MOD_N_SAFE_DIV,
+    hint_code::DIV_MOD_N_SAFE_DIV_PLUS_ONE,
+    hint_code::DI_BIT,
+    hint_code::EC_DOUBLE_ASSIGN_NEW_X_V1,
+    hint_code::EC_DOUBLE_ASSIGN_NEW_X_V2,
+    hint_code::EC_DOUBLE_ASSIGN_NEW_X_V3,
+    hint_code::EC_DOUBLE_ASSIGN_NEW_X_V4,
+    hint_code::EC_DOUBLE_ASSIGN_NEW_Y,
+    hint_code::EC_DOUBLE_SLOPE_EXTERNAL_CONSTS,
+    hint_code::EC_DOUBLE_SLOPE_V1,
+    hint_code::EC_DOUBLE_SLOPE_V2,
+    hint_code::EC_DOUBLE_SLOPE_V3,
+    hint_code::EC_MUL_INNER,
+    hint_code::EC_NEGATE,
+    hint_code::EC_NEGATE_EMBEDDED_SECP,
+    hint_code::EC_RECOVER_DIV_MOD_N_PACKED,
+    hint_code::EC_RECOVER_PRODUCT_DIV_M,
+    hint_code::EC_RECOVER_PRODUCT_MOD,
+    hint_code::EC_RECOVER_SUB_A_B,
+    hint_code::EXAMPLE_BLAKE2S_COMPRESS,
+    hint_code::FAST_EC_ADD_ASSIGN_NEW_X,
+    hint_code::FAST_EC_ADD_ASSIGN_NEW_X_V2,
+    hint_code::FAST_EC_ADD_ASSIGN_NEW_X_V3,
+    hint_code::FAST_EC_ADD_ASSIGN_NEW_Y,
+    hint_code::FIND_ELEMENT,
+    hint_code::GET_FELT_BIT_LENGTH,
+    hint_code::GET_POINT_FROM_X,
+    hint_code::HI_MAX_BITLEN,
+    hint_code::IMPORT_SECP256R1_ALPHA,
+    hint_code::IMPORT_SECP256R1_N,
+    hint_code::IMPORT_SECP256R1_P,
+    hint_code::INV_MOD_P_UINT256,
+    hint_code::INV_MOD_P_UINT512,
+    hint_code::IS_250_BITS,
+    hint_code::IS_ADDR_BOUNDED,
+    hint_code::IS_LE_FELT,
+    hint_code::IS_NN,
+    hint_code::IS_NN_OUT_OF_RANGE,
+    hint_code::IS_POSITIVE,
+    hint_code::IS_QUAD_RESIDUE,
+    hint_code::IS_ZERO_ASSIGN_SCOPE_VARS,
+    hint_code::IS_ZERO_ASSIGN_SCOPE_VARS_ED25519,
+    hint_code::IS_ZERO_ASSIGN_SCOPE_VARS_EXTERNAL_SECP,
+    hint_code::IS_ZERO_INT,
+    hint_code::IS_ZERO_NONDET,
+    hint_code::IS_ZERO_PACK_ED25519,
+    hint_code::IS_ZERO_PACK_EXTERNAL_SECP_V1,
+    hint_code::IS_ZERO_PACK_EXTERNAL_SECP_V2,
+    hint_code::IS_ZERO_PACK_V1,
+    hint_code::IS_ZERO_PACK_V2,
+    hint_code::KECCAK_WRITE_ARGS,
+    hint_code::MEMCPY_CONTINUE_COPYING,
+    hint_code::MEMCPY_ENTER_SCOPE,
+    hint_code::MEMSET_CONTINUE_LOOP,
+    hint_code::MEMSET_ENTER_SCOPE,
+    hint_code::NONDET_BIGINT3_V1,
+    hint_code::NONDET_BIGINT3_V2,
+    hint_code::NONDET_N_GREATER_THAN_10,
+    hint_code::NONDET_N_GREATER_THAN_2,
+    hint_code::PACK_MODN_DIV_MODN,
+    hint_code::POW,
+    hint_code::QUAD_BIT,
+    hint_code::RANDOM_EC_POINT,
+    hint_code::RECOVER_Y,
+    hint_code::REDUCE_ED25519,
+    hint_code::REDUCE_V1,
+    hint_code::REDUCE_V2,
+    hint_code::RELOCATE_SEGMENT,
+    hint_code::SEARCH_SORTED_LOWER,
+    hint_code::SET_ADD,
+    hint_code::SHA256_FINALIZE,
+    hint_code::SHA256_INPUT,
+    hint_code::SHA256_MAIN_ARBITRARY_INPUT_LENGTH,
+    hint_code::SHA256_MAIN_CONSTANT_INPUT_LENGTH,
+    hint_code::SIGNED_DIV_REM,
+    hint_code::SPLIT_64,
+    hint_code::SPLIT_FELT,
+    hint_code::SPLIT_INPUT_12,
+    hint_code::SPLIT_INPUT_15,
+    hint_code::SPLIT_INPUT_3,
+    hint_code::SPLIT_INPUT_6,
+    hint_code::SPLIT_INPUT_9,
+    hint_code::SPLIT_INT,
+    hint_code::SPLIT_INT_ASSERT_RANGE,
+    hint_code::SPLIT_N_BYTES,
+    hint_code::SPLIT_OUTPUT_0,
+    hint_code::SPLIT_OUTPUT_1,
+    hint_code::SPLIT_OUTPUT_MID_LOW_HIGH,
+    hint_code::SPLIT_XX,
+    hint_code::SQRT,
+    hint_code::SQUARE_SLOPE_X_MOD_P,
+    hint_code::SQUASH_DICT,
+    hint_code::SQUASH_DICT_INNER_ASSERT_LEN_KEYS,
+    hint_code::SQUASH_DICT_INNER_CHECK_ACCESS_INDEX,
+    hint_code::SQUASH_DICT_INNER_CONTINUE_LOOP,
+    hint_code::SQUASH_DICT_INNER_FIRST_ITERATION,
+    hint_code::SQUASH_DICT_INNER_LEN_ASSERT,
+    hint_code::SQUASH_DICT_INNER_NEXT_KEY,
+    hint_code::SQUASH_DICT_INNER_SKIP_LOOP,
+    hint_code::SQUASH_DICT_INNER_USED_ACCESSES_ASSERT,
+    hint_code::SUB_REDUCED_A_AND_REDUCED_B,
+    hint_code::TEMPORARY_ARRAY,
+    hint_code::UINT128_ADD,
+    hint_code::UINT256_ADD,
+    hint_code::UINT256_ADD_LOW,
+    hint_code::UINT256_EXPANDED_UNSIGNED_DIV_REM,
+    hint_code::UINT256_GET_SQUARE_ROOT,
+    hint_code::UINT256_MUL_DIV_MOD,
+    hint_code::UINT256_SIGNED_NN,
+    hint_code::UINT256_SQRT,
+    hint_code::UINT256_SQRT_FELT,
+    hint_code::UINT256_SUB,
+    hint_code::UINT256_UNSIGNED_DIV_REM,
+    hint_code::UINT384_DIV,
+    hint_code::UINT384_GET_SQUARE_ROOT,
+    hint_code::UINT384_SIGNED_NN,
+    hint_code::UINT384_SPLIT_128,
+    hint_code::UINT384_SQRT,
+    hint_code::UINT384_UNSIGNED_DIV_REM,
+    hint_code::UINT512_UNSIGNED_DIV_REM,
+    hint_code::UNSAFE_KECCAK,
+    hint_code::UNSAFE_KECCAK_FINALIZE,
+    hint_code::UNSIGNED_DIV_REM,
+    hint_code::UNSIGNED_DIV_REM_UINT768_BY_UINT384,
+    hint_code::UNSIGNED_DIV_REM_UINT768_BY_UINT384_STRIPPED,
+    hint_code::USORT_BODY,
+    hint_code::USORT_ENTER_SCOPE,
+    hint_code::USORT_VERIFY,
+    hint_code::USORT_VERIFY_MULTIPLICITY_ASSERT,
+    hint_code::USORT_VERIFY_MULTIPLICITY_BODY,
+    hint_code::VERIFY_ECDSA_SIGNATURE,
+    hint_code::VERIFY_ZERO_EXTERNAL_SECP,
+    hint_code::VERIFY_ZERO_V1,
+    hint_code::VERIFY_ZERO_V2,
+    hint_code::VERIFY_ZERO_V3,
+    hint_code::VM_ENTER_SCOPE,
+    hint_code::VM_EXIT_SCOPE,
+    hint_code::XS_SAFE_DIV,
+];
+
+lazy_static! {
+    static ref BUILTIN_HINTS: HashSet<&'static str> = BUILTIN_HINT_CODES.iter().copied().collect();
+}
+
+/// Hints of a compiled program or class, by the processor that runs them.
+///
+/// Cairo 0 hints, such as the ones of the OS program, are either handled by the
+/// [`SnosHintProcessor`], left to the builtin hint processor of cairo-vm, or missing. Cairo 1
+/// hints are only found in the compiled classes executed by the OS: syscalls are handled by the
+/// [`SnosHintProcessor`], the other hints are left to the Cairo 1 hint processor of cairo-vm.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize)]
+pub struct HintCoverageReport {
+    /// Hint codes implemented by the [`SnosHintProcessor`].
+    pub snos: BTreeSet<String>,
+    /// Hint codes implemented by the builtin hint processor of cairo-vm.
+    pub builtin: BTreeSet<String>,
+    /// Cairo 1 hints left to the Cairo 1 hint processor of cairo-vm.
+    pub cairo1: BTreeSet<String>,
+    /// Hint codes that no processor implements, by the Cairo functions they appear in.
+    pub missing: BTreeMap<String, BTreeSet<String>>,
+}
+
+impl HintCoverageReport {
+    /// Whether every hint of the program is implemented.
+    pub fn is_complete(&self) -> bool {
+        self.missing.is_empty()
+    }
+
+    /// Number of distinct missing hint codes.
+    pub fn n_missing(&self) -> usize {
+        self.missing.values().flatten().collect::<BTreeSet<_>>().len()
+    }
+}
+
+impl fmt::Display for HintCoverageReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "{} hints implemented by the SNOS hint processor, {} by the builtin hint processor, {} by the Cairo 1 \
+             hint processor, {} missing",
+            self.snos.len(),
+            self.builtin.len(),
+            self.cairo1.len(),
+            self.n_missing()
+        )?;
+        for (function, hint_codes) in &self.missing {
+            writeln!(f, "\nIn {function}:")?;
+            for hint_code in hint_codes {
+                writeln!(f, "---\n{hint_code}")?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// The fields of a compiled program needed to enumerate its hints.
+#[derive(Deserialize)]
+struct CompiledProgramHints {
+    hints: HashMap<String, Vec<HintParams>>,
+}
+
+#[derive(Deserialize)]
+struct HintParams {
+    code: String,
+    accessible_scopes: Vec<String>,
+}
+
+/// Enumerates the hints of a compiled program, such as the Starknet OS, and reports which
+/// processor implements each of them.
+pub fn hint_coverage(
+    compiled_program: &[u8],
+    hint_processor: &SnosHintProcessor,
+) -> Result<HintCoverageReport, SnOsError> {
+    let program: CompiledProgramHints = serde_json::from_slice(compiled_program)?;
+    let snos_hints = hint_processor.hints();
+
+    let mut report = HintCoverageReport::default();
+    for hint in program.hints.into_values().flatten() {
+        if snos_hints.contains(&hint.code) {
+            report.snos.insert(hint.code);
+        } else if BUILTIN_HINTS.contains(hint.code.as_str()) {
+            report.builtin.insert(hint.code);
+        } else {
+            // The innermost scope of a hint is the function it appears in
+            let function = hint.accessible_scopes.last().map(String::as_str).unwrap_or(NO_FUNCTION);
+            report.missing.entry(function.to_string()).or_default().insert(hint.code);
+        }
+    }
+
+    Ok(report)
+}
+
+/// Reports which processor runs each hint of a compiled class executed by the OS.
+pub fn compiled_class_hint_coverage(compiled_class: &CasmContractClass) -> HintCoverageReport {
+    cairo1_hint_coverage(compiled_class.hints.iter().flat_map(|(_pc, hints)| hints))
+}
+
+fn cairo1_hint_coverage<'a>(hints: impl IntoIterator<Item = &'a Hint>) -> HintCoverageReport {
+    let mut report = HintCoverageReport::default();
+    for hint in hints {
+        let hint_code = hint.get_pythonic_hint();
+        match hint {
+            Hint::Starknet(StarknetHint::SystemCall { .. }) => {
+                report.snos.insert(hint_code);
+            }
+            Hint::Core(_) => {
+                report.cairo1.insert(hint_code);
+            }
+            // The Cairo 1 hint processor only implements the core hints
+            _ => {
+                report.missing.entry(COMPILED_CLASS.to_string()).or_default().insert(hint_code);
+            }
+        }
+    }
+
+    report
+}
+
+/// Panics with the list of missing hints unless every hint of the program is implemented by
+/// the default [`SnosHintProcessor`] or the builtin hint processor.
+///
+/// Meant for tests that check a new version of the OS program before running it.
+pub fn assert_hint_coverage(compiled_program: &[u8]) {
+    let report = hint_coverage(compiled_program, &SnosHintProcessor::default())
+        .unwrap_or_else(|e| panic!("Could not read the hints of the program: {e}"));
+    assert!(report.is_complete(), "{report}");
+}
+
+#[cfg(test)]
+mod tests {
+    use cairo_lang_casm::hints::CoreHint;
+    use cairo_lang_casm::operand::{CellRef, Register, ResOperand};
+    use cairo_vm::hint_processor::builtin_hint_processor::hint_code::ADD_SEGMENT;
+    use serde_json::json;
+
+    use super::*;
+    use crate::hints::STARKNET_OS_INPUT;
+
+    const UNKNOWN_HINT: &str = "ids.x = compute_x()";
+
+    fn compiled_program(hints: &[(&str, &str)]) -> Vec<u8> {
+        let hints: serde_json::Map<_, _> = hints
+            .iter()
+            .enumerate()
+            .map(|(pc, (code, function))| {
+                let hint = json!({
+                    "code": code,
+                    "accessible_scopes": ["__main__", function],
+                    "flow_tracking_data": {"ap_tracking": {"group": 0, "offset": 0}, "reference_ids": {}},
+                });
+                (pc.to_string(), json!([hint]))
+            })
+            .collect();
+
+        serde_json::to_vec(&json!({ "hints": hints })).unwrap()
+    }
+
+    #[test]
+    fn test_hint_coverage() {
+        let program = compiled_program(&[
+            (STARKNET_OS_INPUT, "__main__.main"),
+            (ADD_SEGMENT, "__main__.main"),
+            (UNKNOWN_HINT, "__main__.compute"),
+        ]);
+        let report = hint_coverage(&program, &SnosHintProcessor::default()).unwrap();
+
+        assert_eq!(report.snos, BTreeSet::from([STARKNET_OS_INPUT.to_string()]));
+        assert_eq!(report.builtin, BTreeSet::from([ADD_SEGMENT.to_string()]));
+        assert_eq!(
+            report.missing,
+            BTreeMap::from([("__main__.compute".to_string(), BTreeSet::from([UNKNOWN_HINT.to_string()]))])
+        );
+        assert_eq!(report.n_missing(), 1);
+        assert!(!report.is_complete());
+    }
+
+    #[test]
+    fn test_cairo1_hint_coverage() {
+        let cell = CellRef { register: Register::AP, offset: 0 };
+        let syscall = Hint::Starknet(StarknetHint::SystemCall { system: ResOperand::Deref(cell) });
+        let alloc_segment: Hint = CoreHint::AllocSegment { dst: cell }.into();
+        let report = cairo1_hint_coverage([&syscall, &alloc_segment, &syscall]);
+
+        assert_eq!(report.snos, BTreeSet::from([syscall.get_pythonic_hint()]));
+        assert_eq!(report.cairo1, BTreeSet::from([alloc_segment.get_pythonic_hint()]));
+        assert!(report.builtin.is_empty());
+        assert!(report.is_complete());
+    }
+
+    #[test]
+    fn test_builtin_hint_codes() {
+        assert!(BUILTIN_HINTS.contains(ADD_SEGMENT));
+        assert_eq!(BUILTIN_HINTS.len(), BUILTIN_HINT_CODES.len());
+    }
+
+    #[test]
+    fn test_assert_hint_coverage_complete() {
+        assert_hint_coverage(&compiled_program(&[(STARKNET_OS_INPUT, "__main__.main")]));
+    }
+
+    #[test]
+    #[should_panic(expected = "In __main__.compute")]
+    fn test_assert_hint_coverage_missing() {
+        assert_hint_coverage(&compiled_program(&[(UNKNOWN_HINT, "__main__.compute")]));
+    }
+}
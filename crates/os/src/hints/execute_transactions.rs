@@ -11,6 +11,7 @@ use indoc::indoc;
 
 use crate::cairo_types::structs::ExecutionContext;
 use crate::execution::helper::ExecutionHelperWrapper;
+use crate::hints::execution::resource_profiler_start_tx;
 use crate::hints::vars;
 
 pub const START_TX_VALIDATE_DECLARE_EXECUTION_CONTEXT: &str = indoc! {r#"
@@ -32,5 +33,7 @@ pub fn start_tx_validate_declare_execution_context(
 
     execution_helper.start_tx(Some(deprecated_tx_info_ptr));
 
+    resource_profiler_start_tx(vm, exec_scopes, ids_data, ap_tracking);
+
     Ok(())
 }
@@ -28,6 +28,7 @@ use crate::cairo_types::syscalls::{
 };
 use crate::execution::deprecated_syscall_handler::DeprecatedOsSyscallHandlerWrapper;
 use crate::execution::helper::ExecutionHelperWrapper;
+use crate::execution::os_logger::ResourceSnapshot;
 use crate::execution::syscall_handler::OsSyscallHandlerWrapper;
 use crate::execution::syscall_utils::SyscallSelector;
 use crate::hints::types::{PatriciaSkipValidationRunner, Preimage};
@@ -75,25 +76,105 @@ pub fn load_next_tx(
     let mut transactions = exec_scopes.get::<IntoIter<InternalTransaction>>("transactions")?;
     // Safe to unwrap because the remaining number of txs is checked in the cairo code.
     let tx = transactions.next().unwrap();
-    println!("executing {} on: {}", tx.r#type, tx.sender_address.unwrap());
+    log::debug!("executing {} on: {}", tx.r#type, tx.sender_address.unwrap());
     exec_scopes.insert_value("transactions", transactions);
     exec_scopes.insert_value("tx", tx.clone());
-    insert_value_from_var_name("tx_type", Felt252::from_bytes_be_slice(tx.r#type.as_bytes()), vm, ids_data, ap_tracking)
-    // TODO: add logger
+    insert_value_from_var_name(
+        "tx_type",
+        Felt252::from_bytes_be_slice(tx.r#type.as_bytes()),
+        vm,
+        ids_data,
+        ap_tracking,
+    )?;
+
+    log_os_event(|| {
+        let resources = resource_snapshot(vm, ids_data, ap_tracking)?;
+        let execution_helper = os_logger_execution_helper(exec_scopes)?;
+        let mut eh_ref = execution_helper.execution_helper.as_ref().borrow_mut();
+        eh_ref.os_logger.enter_tx(tx.r#type.clone(), tx.hash_value, resources)
+    });
+
+    Ok(())
 }
 
 pub const EXIT_TX: &str = "exit_tx()";
 pub fn exit_tx(
-    _vm: &mut VirtualMachine,
-    _exec_scopes: &mut ExecutionScopes,
-    _ids_data: &HashMap<String, HintReference>,
-    _ap_tracking: &ApTracking,
+    vm: &mut VirtualMachine,
+    exec_scopes: &mut ExecutionScopes,
+    ids_data: &HashMap<String, HintReference>,
+    ap_tracking: &ApTracking,
     _constants: &HashMap<String, Felt252>,
 ) -> Result<(), HintError> {
-    // TODO: add logger
+    log_os_event(|| {
+        let resources = resource_snapshot(vm, ids_data, ap_tracking)?;
+        let execution_helper = os_logger_execution_helper(exec_scopes)?;
+        let mut eh_ref = execution_helper.execution_helper.as_ref().borrow_mut();
+        eh_ref.os_logger.exit_tx(resources)
+    });
+
     Ok(())
 }
 
+/// Runs an update of the OS logger or of the resource profiler, logging its failure instead
+/// of failing the hint.
+///
+/// They do not change the execution of the OS, so a modified OS without the builtin pointers
+/// they read, or events out of order, only cost the trace or the report of the run.
+pub(crate) fn log_os_event(update: impl FnOnce() -> Result<(), HintError>) {
+    if let Err(e) = update() {
+        log::warn!("Could not trace the OS run, continuing without it: {e}");
+    }
+}
+
+/// Resources used so far, from the builtin pointers of the current OS function.
+pub(crate) fn resource_snapshot(
+    vm: &VirtualMachine,
+    ids_data: &HashMap<String, HintReference>,
+    ap_tracking: &ApTracking,
+) -> Result<ResourceSnapshot, HintError> {
+    let builtin_ptrs = get_ptr_from_var_name(vars::ids::BUILTIN_PTRS, vm, ids_data, ap_tracking)?;
+    let range_check_ptr = get_ptr_from_var_name(vars::ids::RANGE_CHECK_PTR, vm, ids_data, ap_tracking)?;
+    ResourceSnapshot::take(vm, builtin_ptrs, range_check_ptr)
+}
+
+/// Starts profiling the transaction started by the current hint.
+pub(crate) fn resource_profiler_start_tx(
+    vm: &VirtualMachine,
+    exec_scopes: &ExecutionScopes,
+    ids_data: &HashMap<String, HintReference>,
+    ap_tracking: &ApTracking,
+) {
+    log_os_event(|| {
+        let resources = resource_snapshot(vm, ids_data, ap_tracking)?;
+        let tx_hash = match exec_scopes.get_ref::<InternalTransaction>("tx") {
+            Ok(tx) => Some(tx.hash_value),
+            Err(e) => {
+                log::warn!("Profiling a transaction without its hash: {e}");
+                None
+            }
+        };
+        let execution_helper = exec_scopes.get::<ExecutionHelperWrapper>(EXECUTION_HELPER)?;
+        let mut eh_ref = execution_helper.execution_helper.as_ref().borrow_mut();
+        eh_ref.resource_profiler.start_tx(tx_hash, resources)
+    });
+}
+
+/// The execution helper holding the OS logger.
+///
+/// Syscalls are executed in the scope of their syscall handler, which does not hold the
+/// execution helper itself.
+pub(crate) fn os_logger_execution_helper(exec_scopes: &ExecutionScopes) -> Result<ExecutionHelperWrapper, HintError> {
+    if let Ok(execution_helper) = exec_scopes.get::<ExecutionHelperWrapper>(EXECUTION_HELPER) {
+        return Ok(execution_helper);
+    }
+    if let Ok(syscall_handler) = exec_scopes.get::<OsSyscallHandlerWrapper>(SYSCALL_HANDLER) {
+        return Ok(syscall_handler.syscall_handler.as_ref().borrow().exec_wrapper.clone());
+    }
+    let syscall_handler = exec_scopes.get::<DeprecatedOsSyscallHandlerWrapper>(SYSCALL_HANDLER)?;
+    let exec_wrapper = syscall_handler.deprecated_syscall_handler.as_ref().borrow().exec_wrapper.clone();
+    Ok(exec_wrapper)
+}
+
 pub const PREPARE_CONSTRUCTOR_EXECUTION: &str = indoc! {r#"
     ids.contract_address_salt = tx.contract_address_salt
     ids.class_hash = tx.class_hash
@@ -359,7 +440,7 @@ pub fn check_is_deprecated(
     let execution_into_ptr = vm.get_relocatable((execution_context + 4usize)?).unwrap();
     let contract_address = vm.get_integer((execution_into_ptr + 3usize)?).unwrap();
 
-    println!(
+    log::debug!(
         "about to call contract_address: {}, class_hash: {}, is_deprecated: {}",
         contract_address,
         class_hash,
@@ -459,14 +540,21 @@ pub fn start_deploy_tx(
 
 pub const END_TX: &str = "execution_helper.end_tx()";
 pub fn end_tx(
-    _vm: &mut VirtualMachine,
+    vm: &mut VirtualMachine,
     exec_scopes: &mut ExecutionScopes,
-    _ids_data: &HashMap<String, HintReference>,
-    _ap_tracking: &ApTracking,
+    ids_data: &HashMap<String, HintReference>,
+    ap_tracking: &ApTracking,
     _constants: &HashMap<String, Felt252>,
 ) -> Result<(), HintError> {
     let execution_helper = exec_scopes.get::<ExecutionHelperWrapper>("execution_helper")?;
     execution_helper.end_tx();
+
+    log_os_event(|| {
+        let resources = resource_snapshot(vm, ids_data, ap_tracking)?;
+        let mut eh_ref = execution_helper.execution_helper.as_ref().borrow_mut();
+        eh_ref.resource_profiler.end_tx(resources)
+    });
+
     Ok(())
 }
 
@@ -486,22 +574,39 @@ pub fn enter_call(
 
     let execution_helper = exec_scopes.get::<ExecutionHelperWrapper>("execution_helper")?;
     execution_helper.enter_call(Some(execution_info_ptr));
+    log_os_event(|| execution_helper.resource_profiler_enter_call(resource_snapshot(vm, ids_data, ap_tracking)?));
+
     Ok(())
 }
 
 pub const EXIT_CALL: &str = "execution_helper.exit_call()";
 pub fn exit_call(
-    _vm: &mut VirtualMachine,
+    vm: &mut VirtualMachine,
     exec_scopes: &mut ExecutionScopes,
-    _ids_data: &HashMap<String, HintReference>,
-    _ap_tracking: &ApTracking,
+    ids_data: &HashMap<String, HintReference>,
+    ap_tracking: &ApTracking,
     _constants: &HashMap<String, Felt252>,
 ) -> Result<(), HintError> {
     let mut execution_helper = exec_scopes.get::<ExecutionHelperWrapper>("execution_helper")?;
     execution_helper.exit_call();
+    resource_profiler_exit_call(vm, &execution_helper, ids_data, ap_tracking);
+
     Ok(())
 }
 
+fn resource_profiler_exit_call(
+    vm: &VirtualMachine,
+    execution_helper: &ExecutionHelperWrapper,
+    ids_data: &HashMap<String, HintReference>,
+    ap_tracking: &ApTracking,
+) {
+    log_os_event(|| {
+        let resources = resource_snapshot(vm, ids_data, ap_tracking)?;
+        let mut eh_ref = execution_helper.execution_helper.as_ref().borrow_mut();
+        eh_ref.resource_profiler.exit_call(resources)
+    });
+}
+
 pub const CONTRACT_ADDRESS: &str = indoc! {r#"
     from starkware.starknet.business_logic.transaction.deprecated_objects import (
         InternalL1Handler,
@@ -868,16 +973,18 @@ pub const START_TX: &str = indoc! {r#"
     execution_helper.start_tx(tx_info_ptr=tx_info_ptr)"#
 };
 pub fn start_tx(
-    _vm: &mut VirtualMachine,
+    vm: &mut VirtualMachine,
     exec_scopes: &mut ExecutionScopes,
     ids_data: &HashMap<String, HintReference>,
-    _ap_tracking: &ApTracking,
+    ap_tracking: &ApTracking,
     _constants: &HashMap<String, Felt252>,
 ) -> Result<(), HintError> {
-    let tx_execution_context = get_relocatable_from_var_name("tx_execution_context", _vm, ids_data, _ap_tracking)?;
+    let tx_execution_context = get_relocatable_from_var_name("tx_execution_context", vm, ids_data, ap_tracking)?;
     let execution_helper = exec_scopes.get::<ExecutionHelperWrapper>("execution_helper")?;
     let tx_info_ptr = (tx_execution_context + ExecutionContext::deprecated_tx_info_offset())?;
     execution_helper.start_tx(Some(tx_info_ptr));
+    resource_profiler_start_tx(vm, exec_scopes, ids_data, ap_tracking);
+
     Ok(())
 }
 
@@ -945,11 +1052,14 @@ pub fn check_execution(
         let execution_context = get_relocatable_from_var_name(EXECUTION_CONTEXT, vm, ids_data, ap_tracking)?;
         let class_hash = vm.get_integer((execution_context + ExecutionContext::class_hash_offset())?)?;
         let selector = vm.get_integer((execution_context + ExecutionContext::execution_info_offset())?)?;
-        println!("Invalid return value in execute_entry_point:");
-        println!("  Class hash: {}", class_hash.to_hex_string());
-        println!("  Selector: {}", selector.to_hex_string());
-        println!("  Size: {}", retdata_size);
-        println!("  Error (at most 100 elements): {:?}", error);
+        log::warn!(
+            "Invalid return value in execute_entry_point: class hash {}, selector {}, size {}, error (at most 100 \
+             elements) {:?}",
+            class_hash.to_hex_string(),
+            selector.to_hex_string(),
+            retdata_size,
+            error
+        );
     }
 
     let mut execution_helper = exec_scopes.get::<ExecutionHelperWrapper>(EXECUTION_HELPER)?;
@@ -971,6 +1081,8 @@ pub fn check_execution(
     syscall_handler.validate_and_discard_syscall_ptr(syscall_ptr_end)?;
     execution_helper.exit_call();
 
+    resource_profiler_exit_call(vm, &execution_helper, ids_data, ap_tracking);
+
     Ok(())
 }
 
@@ -1110,15 +1222,19 @@ pub const LOG_ENTER_SYSCALL: &str = indoc! {r#"
 };
 
 pub fn log_enter_syscall(
-    _vm: &mut VirtualMachine,
-    _exec_scopes: &mut ExecutionScopes,
+    vm: &mut VirtualMachine,
+    exec_scopes: &mut ExecutionScopes,
     ids_data: &HashMap<String, HintReference>,
-    _ap_tracking: &ApTracking,
+    ap_tracking: &ApTracking,
     _constants: &HashMap<String, Felt252>,
 ) -> Result<(), HintError> {
-    let selector = get_integer_from_var_name(SELECTOR, _vm, ids_data, _ap_tracking)?;
-    println!("entering syscall: {:?} execution", SyscallSelector::try_from(selector)?);
-    // TODO: implement logging
+    log_os_event(|| {
+        let selector = get_integer_from_var_name(SELECTOR, vm, ids_data, ap_tracking)?;
+        let syscall = SyscallSelector::try_from(selector)?;
+        let resources = resource_snapshot(vm, ids_data, ap_tracking)?;
+        os_logger_execution_helper(exec_scopes)?.os_logger_enter_syscall(format!("{syscall:?}"), false, resources)
+    });
+
     Ok(())
 }
 
@@ -1181,7 +1297,7 @@ pub fn check_response_return_value(
     assert_eq!(expected, actual, "Return value mismatch; expected={:?}, actual={:?}", expected, actual);
 
     // relocate_segment(src_ptr=response.retdata_start, dest_ptr=retdata);
-    println!("response_retdata_start: {}, retdata: {}", response_retdata_start, retdata);
+    log::debug!("response_retdata_start: {}, retdata: {}", response_retdata_start, retdata);
 
     Ok(())
 }
@@ -1614,7 +1730,7 @@ pub fn write_old_block_to_storage(
     let old_block_number = get_integer_from_var_name(vars::ids::OLD_BLOCK_NUMBER, vm, ids_data, ap_tracking)?;
     let old_block_hash = get_integer_from_var_name(vars::ids::OLD_BLOCK_HASH, vm, ids_data, ap_tracking)?;
 
-    println!("writing block number: {} -> block hash: {}", old_block_number, old_block_hash);
+    log::debug!("writing block number: {} -> block hash: {}", old_block_number, old_block_hash);
     execution_helper
         .write_storage_for_address(*block_hash_contract_address, old_block_number, old_block_hash)
         .map_err(|_| {
@@ -1734,9 +1850,10 @@ pub fn fetch_result(
     // This hint is weird, there is absolutely no need to fetch 100 elements to do this.
     // Nonetheless, we implement it 1-1 with the Python version.
     if n_elements != 1 || result[0] != Some(Cow::Borrowed(&MaybeRelocatable::Int(*validated))) {
-        println!("Invalid return value from __validate__:");
-        println!("  Size: {n_elements}");
-        println!("  Result (at most 100 elements): {:?}", result);
+        log::warn!(
+            "Invalid return value from __validate__: size {n_elements}, result (at most 100 elements) {:?}",
+            result
+        );
     }
 
     Ok(())
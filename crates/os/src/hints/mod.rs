@@ -24,6 +24,7 @@ use crate::execution::execute_syscalls;
 use crate::execution::helper::ExecutionHelperWrapper;
 use crate::execution::syscall_handler::OsSyscallHandlerWrapper;
 use crate::hints::block_context::is_leaf;
+use crate::hints::execution::resource_profiler_start_tx;
 use crate::io::input::StarknetOsInput;
 
 pub mod block_context;
@@ -31,6 +32,7 @@ mod bls_field;
 mod bls_utils;
 pub mod builtins;
 mod compiled_class;
+pub mod coverage;
 mod execute_transactions;
 pub mod execution;
 mod output;
@@ -230,7 +232,7 @@ static HINTS: [(&str, HintImpl); 171] = [
 /// Hint Extensions extend the current map of hints used by the VM.
 /// This behaviour achieves what the `vm_load_data` primitive does for cairo-lang
 /// and is needed to implement os hints like `vm_load_program`.
-type ExtensiveHintImpl = fn(
+pub type ExtensiveHintImpl = fn(
     &dyn HintProcessor,
     &mut VirtualMachine,
     &mut ExecutionScopes,
@@ -270,14 +272,58 @@ impl ResourceTracker for SnosHintProcessor {
 }
 
 impl Default for SnosHintProcessor {
+    fn default() -> Self {
+        SnosHintProcessorBuilder::default().build()
+    }
+}
+
+/// Builds a [`SnosHintProcessor`] from the default hint table, with hints added, overridden or
+/// removed by hint code. Meant for modified versions of the OS.
+#[derive(Clone)]
+pub struct SnosHintProcessorBuilder {
+    hints: HashMap<String, HintImpl>,
+    extensive_hints: HashMap<String, ExtensiveHintImpl>,
+}
+
+impl Default for SnosHintProcessorBuilder {
     fn default() -> Self {
         let hints = HINTS.into_iter().map(|(h, i)| (h.to_string(), i)).collect();
         let extensive_hints = EXTENSIVE_HINTS.into_iter().map(|(h, i)| (h.to_string(), i)).collect();
-        Self {
+        Self { hints, extensive_hints }
+    }
+}
+
+impl SnosHintProcessorBuilder {
+    /// Adds a hint, or replaces the implementation of an existing one.
+    pub fn with_hint(mut self, hint_code: impl Into<String>, hint_impl: HintImpl) -> Self {
+        let hint_code = hint_code.into();
+        self.extensive_hints.remove(&hint_code);
+        self.hints.insert(hint_code, hint_impl);
+        self
+    }
+
+    /// Adds a hint that extends the hints of the VM, or replaces the implementation of an
+    /// existing one.
+    pub fn with_extensive_hint(mut self, hint_code: impl Into<String>, hint_impl: ExtensiveHintImpl) -> Self {
+        let hint_code = hint_code.into();
+        self.hints.remove(&hint_code);
+        self.extensive_hints.insert(hint_code, hint_impl);
+        self
+    }
+
+    /// Removes a hint. Removed hints fall back to the builtin hint processor of cairo-vm.
+    pub fn without_hint(mut self, hint_code: &str) -> Self {
+        self.hints.remove(hint_code);
+        self.extensive_hints.remove(hint_code);
+        self
+    }
+
+    pub fn build(self) -> SnosHintProcessor {
+        SnosHintProcessor {
             builtin_hint_proc: BuiltinHintProcessor::new_empty(),
             cairo1_builtin_hint_proc: Cairo1HintProcessor::new(Default::default(), Default::default()),
-            hints,
-            extensive_hints,
+            hints: self.hints,
+            extensive_hints: self.extensive_hints,
             run_resources: Default::default(),
         }
     }
@@ -307,6 +353,11 @@ fn get_ptr_from_res_operand(vm: &mut VirtualMachine, res: &ResOperand) -> Result
 }
 
 impl SnosHintProcessor {
+    /// Starts from the default hints, see [`SnosHintProcessorBuilder`].
+    pub fn builder() -> SnosHintProcessorBuilder {
+        SnosHintProcessorBuilder::default()
+    }
+
     pub fn hints(&self) -> HashSet<String> {
         self.hints
             .keys()
@@ -570,6 +621,8 @@ pub fn start_tx(
     let execution_helper = exec_scopes.get::<ExecutionHelperWrapper>(vars::scopes::EXECUTION_HELPER)?;
     execution_helper.start_tx(Some(deprecated_tx_info_ptr));
 
+    resource_profiler_start_tx(vm, exec_scopes, ids_data, ap_tracking);
+
     Ok(())
 }
 
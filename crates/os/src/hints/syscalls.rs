@@ -1,6 +1,8 @@
 use std::collections::HashMap;
 
-use cairo_vm::hint_processor::builtin_hint_processor::hint_utils::{get_ptr_from_var_name, insert_value_from_var_name};
+use cairo_vm::hint_processor::builtin_hint_processor::hint_utils::{
+    get_integer_from_var_name, get_ptr_from_var_name, insert_value_from_var_name,
+};
 use cairo_vm::hint_processor::hint_processor_definition::HintReference;
 use cairo_vm::serde::deserialize_program::ApTracking;
 use cairo_vm::types::exec_scope::ExecutionScopes;
@@ -11,6 +13,8 @@ use indoc::indoc;
 
 use crate::execution::deprecated_syscall_handler::DeprecatedOsSyscallHandlerWrapper;
 use crate::execution::syscall_handler::OsSyscallHandlerWrapper;
+use crate::execution::syscall_utils::SyscallSelector;
+use crate::hints::execution::{log_os_event, os_logger_execution_helper, resource_snapshot};
 use crate::hints::vars;
 
 pub const CALL_CONTRACT: &str = "syscall_handler.call_contract(segments=segments, syscall_ptr=ids.syscall_ptr)";
@@ -370,13 +374,19 @@ pub const OS_LOGGER_ENTER_SYSCALL_PREPRARE_EXIT_SYSCALL: &str = indoc! {r#"
         )"#
 };
 pub fn os_logger_enter_syscall_preprare_exit_syscall(
-    _vm: &mut VirtualMachine,
-    _exec_scopes: &mut ExecutionScopes,
-    _ids_data: &HashMap<String, HintReference>,
-    _ap_tracking: &ApTracking,
+    vm: &mut VirtualMachine,
+    exec_scopes: &mut ExecutionScopes,
+    ids_data: &HashMap<String, HintReference>,
+    ap_tracking: &ApTracking,
     _constants: &HashMap<String, Felt252>,
 ) -> Result<(), HintError> {
-    // TODO: os_logger enter/exit calls
+    log_os_event(|| {
+        let selector = get_integer_from_var_name(vars::ids::SELECTOR, vm, ids_data, ap_tracking)?;
+        let syscall = SyscallSelector::try_from(selector)?;
+        let resources = resource_snapshot(vm, ids_data, ap_tracking)?;
+        os_logger_execution_helper(exec_scopes)?.os_logger_enter_syscall(format!("{syscall:?}"), true, resources)
+    });
+
     Ok(())
 }
 
@@ -434,17 +444,44 @@ mod tests {
         let syscall_handler: OsSyscallHandlerWrapper = exec_scopes.get(vars::scopes::SYSCALL_HANDLER).unwrap();
         assert_eq!(syscall_handler.syscall_ptr(), Some(syscall_ptr));
     }
+
+    #[rstest]
+    fn test_os_logger_failure_does_not_fail_the_hint(mut exec_scopes: ExecutionScopes) {
+        let mut vm = VirtualMachine::new(false);
+        vm.add_memory_segment();
+        vm.add_memory_segment();
+        vm.set_fp(1);
+        // The selector is there, but not the builtin pointers read by the logger
+        vm.insert_value(Relocatable::from((1, 0)), Felt252::from(0x10)).unwrap();
+        let ids_data = HashMap::from([(vars::ids::SELECTOR.to_string(), HintReference::new_simple(-1))]);
+        let ap_tracking = ApTracking::new();
+        let constants = HashMap::new();
+
+        os_logger_enter_syscall_preprare_exit_syscall(&mut vm, &mut exec_scopes, &ids_data, &ap_tracking, &constants)
+            .unwrap();
+        exit_syscall("", &mut vm, &mut exec_scopes, &ids_data, &ap_tracking, &constants).unwrap();
+
+        let execution_helper = os_logger_execution_helper(&exec_scopes).unwrap();
+        assert!(execution_helper.execution_helper.borrow().os_logger.events().is_empty());
+    }
 }
 
 pub fn exit_syscall(
     _selector_name: &str,
-    _vm: &mut VirtualMachine,
-    _exec_scopes: &mut ExecutionScopes,
-    _ids_data: &HashMap<String, HintReference>,
-    _ap_tracking: &ApTracking,
+    vm: &mut VirtualMachine,
+    exec_scopes: &mut ExecutionScopes,
+    ids_data: &HashMap<String, HintReference>,
+    ap_tracking: &ApTracking,
     _constants: &HashMap<String, Felt252>,
 ) -> Result<(), HintError> {
-    // TODO: add logging
+    // The syscall being exited is the last one entered by the logger
+    log_os_event(|| {
+        let resources = resource_snapshot(vm, ids_data, ap_tracking)?;
+        let execution_helper = os_logger_execution_helper(exec_scopes)?;
+        let mut eh_ref = execution_helper.execution_helper.as_ref().borrow_mut();
+        eh_ref.os_logger.exit_syscall(resources)
+    });
+
     Ok(())
 }
 pub const EXIT_CALL_CONTRACT_SYSCALL: &str = "exit_syscall(selector=ids.CALL_CONTRACT_SELECTOR)";
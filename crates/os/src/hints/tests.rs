@@ -147,18 +147,28 @@ pub mod tests {
         assert_eq!(is_on_curve, 1.into());
     }
 
+    /// A VM with the ids of the hints that start a transaction: `deprecated_tx_info`, and the
+    /// builtin pointers used to profile the transaction.
+    fn start_tx_vm() -> (VirtualMachine, HashMap<String, HintReference>) {
+        let mut vm = VirtualMachine::new(false);
+        vm.set_fp(3);
+        vm.add_memory_segment();
+        vm.add_memory_segment();
+        let builtin_ptrs = vm.add_memory_segment();
+        vm.insert_value(Relocatable::from((1, 1)), builtin_ptrs).unwrap();
+        vm.insert_value(Relocatable::from((1, 2)), builtin_ptrs).unwrap();
+
+        let ids_data = ids_data![vars::ids::DEPRECATED_TX_INFO, vars::ids::BUILTIN_PTRS, vars::ids::RANGE_CHECK_PTR];
+        (vm, ids_data)
+    }
+
     #[rstest]
     fn test_start_tx(
         block_context: BlockContext,
         transaction_execution_info: TransactionExecutionInfo,
         old_block_number_and_hash: (Felt252, Felt252),
     ) {
-        let mut vm = VirtualMachine::new(false);
-        vm.set_fp(1);
-        vm.add_memory_segment();
-        vm.add_memory_segment();
-
-        let ids_data = ids_data![vars::ids::DEPRECATED_TX_INFO];
+        let (mut vm, ids_data) = start_tx_vm();
         let ap_tracking = ApTracking::default();
 
         let mut exec_scopes = ExecutionScopes::new();
@@ -181,6 +191,11 @@ pub mod tests {
 
         // after starting tx, tx_execution_info should be some
         assert!(exec_helper_box.execution_helper.borrow().tx_execution_info.is_some());
+
+        // ending the tx adds it to the resource report
+        execution::end_tx(&mut vm, &mut exec_scopes, &ids_data, &ap_tracking, &Default::default()).expect("end_tx");
+        let report = exec_helper_box.execution_helper.borrow_mut().resource_profiler.take_report();
+        assert_eq!(report.transactions.len(), 1);
     }
 
     #[rstest]
@@ -228,12 +243,7 @@ pub mod tests {
         transaction_execution_info: TransactionExecutionInfo,
         old_block_number_and_hash: (Felt252, Felt252),
     ) {
-        let mut vm = VirtualMachine::new(false);
-        vm.set_fp(1);
-        vm.add_memory_segment();
-        vm.add_memory_segment();
-
-        let ids_data = ids_data![vars::ids::DEPRECATED_TX_INFO];
+        let (mut vm, ids_data) = start_tx_vm();
         let ap_tracking = ApTracking::default();
 
         let mut exec_scopes = ExecutionScopes::new();
@@ -320,4 +330,24 @@ pub mod tests {
             );
         }
     }
+
+    #[test]
+    fn test_hint_processor_builder() {
+        const CUSTOM_HINT: &str = "ids.x = compute_x()";
+
+        let hint_processor = SnosHintProcessor::builder()
+            .with_hint(CUSTOM_HINT, hint_stub)
+            .with_hint(block_context::LOAD_CLASS, hint_stub)
+            .without_hint(STARKNET_OS_INPUT)
+            .build();
+
+        let hints = hint_processor.hints();
+        assert!(hints.contains(CUSTOM_HINT));
+        assert!(!hints.contains(STARKNET_OS_INPUT));
+        assert_eq!(hints.len(), HINTS.len() + EXTENSIVE_HINTS.len());
+
+        // Overriding an extensive hint with a regular one replaces it
+        assert!(hint_processor.hints.contains_key(block_context::LOAD_CLASS));
+        assert!(!hint_processor.extensive_hints.contains_key(block_context::LOAD_CLASS));
+    }
 }
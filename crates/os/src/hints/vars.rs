@@ -25,6 +25,7 @@ pub mod scopes {
 pub mod ids {
     pub const ADDITIONAL_DATA: &str = "additional_data";
     pub const BIT: &str = "bit";
+    pub const BUILTIN_PTRS: &str = "builtin_ptrs";
     pub const CALL_RESPONSE: &str = "call_response";
     pub const CALLDATA: &str = "calldata";
     pub const CHILD_BIT: &str = "CHILD_BIT";
@@ -67,6 +68,7 @@ pub mod ids {
     pub const OS_CONTEXT: &str = "os_context";
     pub const REQUIRED_GAS: &str = "required_gas";
     pub const OUTPUT_PTR: &str = "output_ptr";
+    pub const RANGE_CHECK_PTR: &str = "range_check_ptr";
     pub const REQUEST_BLOCK_NUMBER: &str = "request_block_number";
     pub const PATH: &str = "path";
     pub const PREV_ROOT: &str = "prev_root";
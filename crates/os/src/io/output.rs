@@ -2,9 +2,12 @@ use cairo_vm::types::relocatable::MaybeRelocatable;
 use cairo_vm::vm::runners::builtin_runner::BuiltinRunner;
 use cairo_vm::vm::vm_core::VirtualMachine;
 use cairo_vm::Felt252;
+use num_traits::ToPrimitive;
+use serde::Serialize;
+use serde_with::serde_as;
 
 use crate::error::SnOsError;
-use crate::utils::felt_vm2usize;
+use crate::utils::{felt_vm2usize, Felt252Str};
 
 const PREVIOUS_MERKLE_UPDATE_OFFSET: usize = 0;
 const NEW_MERKLE_UPDATE_OFFSET: usize = 1;
@@ -13,25 +16,36 @@ const BLOCK_HASH_OFFSET: usize = 3;
 const CONFIG_HASH_OFFSET: usize = 4;
 const HEADER_SIZE: usize = 5;
 
-#[derive(Debug)]
+#[serde_as]
+#[derive(Debug, Serialize)]
 pub struct StarknetOsOutput {
     /// The state commitment before this block.
+    #[serde_as(as = "Felt252Str")]
     pub prev_state_root: Felt252,
     /// The state commitment after this block.
+    #[serde_as(as = "Felt252Str")]
     pub new_state_root: Felt252,
     /// The number (height) of this block.
+    #[serde_as(as = "Felt252Str")]
     pub block_number: Felt252,
     /// The hash of this block.
+    #[serde_as(as = "Felt252Str")]
     pub block_hash: Felt252,
     /// The Starknet chain config hash
+    #[serde_as(as = "Felt252Str")]
     pub config_hash: Felt252,
     /// List of messages sent to L1 in this block
+    #[serde_as(as = "Vec<Felt252Str>")]
     pub messages_to_l1: Vec<Felt252>,
     /// List of messages from L1 handled in this block
+    #[serde_as(as = "Vec<Felt252Str>")]
     pub messages_to_l2: Vec<Felt252>,
-    /// List of the storage updates.
+    /// Changes of each modified contract: address, header, new class hash if the class
+    /// changed, then the storage updates.
+    #[serde_as(as = "Vec<Felt252Str>")]
     pub state_updates: Vec<Felt252>,
-    /// List of the newly declared contract classes.
+    /// Class hash and compiled class hash of the newly declared contract classes.
+    #[serde_as(as = "Vec<Felt252Str>")]
     pub contract_class_diff: Vec<Felt252>,
 }
 
@@ -67,8 +81,84 @@ impl StarknetOsOutput {
     }
 }
 
-pub fn decode_output(mut os_output: Vec<Felt252>) -> Result<StarknetOsOutput, SnOsError> {
-    let header: Vec<Felt252> = os_output.drain(..HEADER_SIZE).collect();
+/// Bound of the nonce and of the number of storage updates in the header of a contract.
+const NONCE_BOUND: u128 = 1 << 64;
+const N_UPDATES_BOUND: u128 = 1 << 64;
+
+/// Reads the felts of the OS output one section after the other.
+struct OutputReader<'a> {
+    output: &'a [Felt252],
+    position: usize,
+}
+
+impl<'a> OutputReader<'a> {
+    fn read_slice(&mut self, len: usize, section: &str) -> Result<&'a [Felt252], SnOsError> {
+        let end = self.position.checked_add(len).filter(|end| *end <= self.output.len()).ok_or_else(|| {
+            SnOsError::Output(format!("{section} ends after the end of the output ({} felts)", self.output.len()))
+        })?;
+        let slice = &self.output[self.position..end];
+        self.position = end;
+        Ok(slice)
+    }
+
+    fn read(&mut self, section: &str) -> Result<Felt252, SnOsError> {
+        Ok(self.read_slice(1, section)?[0])
+    }
+
+    fn read_usize(&mut self, section: &str) -> Result<usize, SnOsError> {
+        let value = self.read(section)?;
+        value.to_usize().ok_or_else(|| SnOsError::Output(format!("invalid {section}: {value}")))
+    }
+
+    /// Reads a section made of its size in felts followed by its content.
+    fn read_sized_section(&mut self, section: &str) -> Result<Vec<Felt252>, SnOsError> {
+        let size = self.read_usize(&format!("{section} size"))?;
+        Ok(self.read_slice(size, section)?.to_vec())
+    }
+}
+
+/// Splits the raw output of the OS into its sections.
+///
+/// The header is followed by the messages to L1 and the messages to L2, each preceded by
+/// their size in felts, then by the number of modified contracts and their changes, and by
+/// the number of declared classes and their compiled class hashes. The sizes and counts are
+/// not part of the sections.
+pub fn decode_output(os_output: Vec<Felt252>) -> Result<StarknetOsOutput, SnOsError> {
+    let mut reader = OutputReader { output: &os_output, position: 0 };
+    let header = reader.read_slice(HEADER_SIZE, "header")?;
+    let messages_to_l1 = reader.read_sized_section("messages to L1")?;
+    let messages_to_l2 = reader.read_sized_section("messages to L2")?;
+
+    // The contract changes have a variable size, given by the header of each contract:
+    // class_updated * 2^128 + nonce * 2^64 + n_updates
+    let n_contracts = reader.read_usize("number of modified contracts")?;
+    let state_updates_start = reader.position;
+    for _ in 0..n_contracts {
+        reader.read("contract address")?;
+        let contract_header = reader.read("contract header")?.to_biguint();
+        let n_updates = &contract_header % N_UPDATES_BOUND;
+        let class_updated = contract_header / (N_UPDATES_BOUND * NONCE_BOUND);
+        if class_updated > 1u8.into() {
+            return Err(SnOsError::Output(format!("invalid class_updated flag in contract header: {class_updated}")));
+        }
+        if class_updated == 1u8.into() {
+            reader.read("contract class hash")?;
+        }
+        let n_updates = usize::try_from(n_updates)
+            .map_err(|e| SnOsError::Output(format!("invalid number of storage updates: {e}")))?;
+        reader.read_slice(n_updates.saturating_mul(2), "storage updates")?;
+    }
+    let state_updates = os_output[state_updates_start..reader.position].to_vec();
+
+    let n_classes = reader.read_usize("number of declared classes")?;
+    let contract_class_diff = reader.read_slice(n_classes.saturating_mul(2), "declared classes")?.to_vec();
+
+    if reader.position != os_output.len() {
+        return Err(SnOsError::Output(format!(
+            "{} unexpected felts after the declared classes",
+            os_output.len() - reader.position
+        )));
+    }
 
     Ok(StarknetOsOutput {
         prev_state_root: header[PREVIOUS_MERKLE_UPDATE_OFFSET],
@@ -76,9 +166,80 @@ pub fn decode_output(mut os_output: Vec<Felt252>) -> Result<StarknetOsOutput, Sn
         block_number: header[BLOCK_NUMBER_OFFSET],
         block_hash: header[BLOCK_HASH_OFFSET],
         config_hash: header[CONFIG_HASH_OFFSET],
-        messages_to_l1: os_output.drain(1..felt_vm2usize(os_output.first())?).collect(),
-        messages_to_l2: os_output.drain(1..felt_vm2usize(os_output.first())?).collect(),
-        state_updates: os_output.drain(1..felt_vm2usize(os_output.first())?).collect(),
-        contract_class_diff: os_output.drain(1..felt_vm2usize(os_output.first())?).collect(),
+        messages_to_l1,
+        messages_to_l2,
+        state_updates,
+        contract_class_diff,
     })
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn felts(values: &[u64]) -> Vec<Felt252> {
+        values.iter().copied().map(Felt252::from).collect()
+    }
+
+    /// class_updated = 1, nonce = 2, n_updates = 2
+    fn contract_header() -> Felt252 {
+        Felt252::from_hex("0x100000000000000020000000000000002").unwrap()
+    }
+
+    /// Output of a block with a message to L1, a message to L2, a storage update of the block
+    /// hash contract, a contract whose class, nonce and storage changed, and a declared class.
+    fn os_output() -> Vec<Felt252> {
+        let mut output = felts(&[0x1111, 0x2222, 1000, 0xabcd, 0x5555]);
+        // Messages to L1: from address, to address, payload size, payload
+        output.extend(felts(&[5, 0x100, 0x200, 2, 7, 8]));
+        // Messages to L2: from address, to address, nonce, selector, payload size, payload
+        output.extend(felts(&[6, 0x300, 0x400, 1, 0x500, 1, 9]));
+        // Modified contracts
+        output.extend(felts(&[2, 0x1, 1, 990, 0x9999, 0x100]));
+        output.push(contract_header());
+        output.extend(felts(&[0xc1a55, 0x10, 0x64, 0x11, 0xc8]));
+        // Declared classes
+        output.extend(felts(&[1, 0xc1a55, 0xc0ffee]));
+        output
+    }
+
+    #[test]
+    fn test_decode_output() {
+        let output = decode_output(os_output()).unwrap();
+
+        assert_eq!(output.prev_state_root, Felt252::from(0x1111));
+        assert_eq!(output.new_state_root, Felt252::from(0x2222));
+        assert_eq!(output.block_number, Felt252::from(1000));
+        assert_eq!(output.block_hash, Felt252::from(0xabcd));
+        assert_eq!(output.config_hash, Felt252::from(0x5555));
+        assert_eq!(output.messages_to_l1, felts(&[0x100, 0x200, 2, 7, 8]));
+        assert_eq!(output.messages_to_l2, felts(&[0x300, 0x400, 1, 0x500, 1, 9]));
+
+        let mut state_updates = felts(&[0x1, 1, 990, 0x9999, 0x100]);
+        state_updates.push(contract_header());
+        state_updates.extend(felts(&[0xc1a55, 0x10, 0x64, 0x11, 0xc8]));
+        assert_eq!(output.state_updates, state_updates);
+        assert_eq!(output.contract_class_diff, felts(&[0xc1a55, 0xc0ffee]));
+    }
+
+    #[test]
+    fn test_decode_empty_block_output() {
+        let output = decode_output(felts(&[0x1111, 0x1111, 1000, 0xabcd, 0x5555, 0, 0, 0, 0])).unwrap();
+
+        assert!(output.messages_to_l1.is_empty());
+        assert!(output.messages_to_l2.is_empty());
+        assert!(output.state_updates.is_empty());
+        assert!(output.contract_class_diff.is_empty());
+    }
+
+    #[test]
+    fn test_decode_truncated_output() {
+        let mut output = os_output();
+        output.pop();
+        assert!(decode_output(output).is_err());
+
+        let mut output = os_output();
+        output.push(Felt252::ZERO);
+        assert!(decode_output(output).is_err());
+    }
+}
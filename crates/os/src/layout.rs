@@ -0,0 +1,336 @@
+use std::fmt;
+use std::str::FromStr;
+
+use cairo_vm::types::builtin_name::BuiltinName;
+use cairo_vm::types::layout_name::LayoutName;
+use cairo_vm::vm::runners::cairo_runner::ExecutionResources;
+use serde::Serialize;
+
+/// Layouts tried by [`LayoutSelection::Auto`], from the smallest to the largest.
+pub const AUTO_LAYOUTS: [LayoutName; 3] = [LayoutName::starknet, LayoutName::starknet_with_keccak, LayoutName::dynamic];
+
+/// Memory cells available per step, in every layout.
+const MEMORY_UNITS_PER_STEP: usize = 8;
+/// Memory cells used by the instruction of each step: pc, dst, op0 and op1.
+const INSTRUCTION_MEMORY_UNITS: usize = 4;
+/// Range check units per step used by the offsets of the instruction of each step.
+const INSTRUCTION_RANGE_CHECK_UNITS: usize = 3;
+/// Parts of 16 bits checked by the permutation range check for each range check instance.
+const RANGE_CHECK_N_PARTS: usize = 8;
+
+/// A builtin of a layout.
+struct LayoutBuiltin {
+    name: BuiltinName,
+    /// One component every `ratio` steps, 0 for the output builtin which has no ratio.
+    ratio: usize,
+    instances_per_component: usize,
+    cells_per_instance: usize,
+}
+
+const fn builtin(name: BuiltinName, ratio: usize, cells_per_instance: usize) -> LayoutBuiltin {
+    LayoutBuiltin { name, ratio, instances_per_component: 1, cells_per_instance }
+}
+
+/// The parameters of a layout that bound the resources of a run.
+struct LayoutParams {
+    builtins: &'static [LayoutBuiltin],
+    /// Range check units per step.
+    rc_units: usize,
+    /// One memory cell every `public_memory_fraction` is reserved for the public memory.
+    public_memory_fraction: usize,
+}
+
+/// Builtins of the `starknet` layout.
+const STARKNET_BUILTINS: &[LayoutBuiltin] = &[
+    builtin(BuiltinName::output, 0, 1),
+    builtin(BuiltinName::pedersen, 32, 3),
+    builtin(BuiltinName::range_check, 16, 1),
+    builtin(BuiltinName::ecdsa, 2048, 2),
+    builtin(BuiltinName::bitwise, 64, 5),
+    builtin(BuiltinName::ec_op, 1024, 7),
+    builtin(BuiltinName::poseidon, 32, 6),
+];
+
+/// Builtins of the `starknet_with_keccak` layout.
+const STARKNET_WITH_KECCAK_BUILTINS: &[LayoutBuiltin] = &[
+    builtin(BuiltinName::output, 0, 1),
+    builtin(BuiltinName::pedersen, 32, 3),
+    builtin(BuiltinName::range_check, 16, 1),
+    builtin(BuiltinName::ecdsa, 2048, 2),
+    builtin(BuiltinName::bitwise, 64, 5),
+    builtin(BuiltinName::ec_op, 1024, 7),
+    // A keccak component holds 16 instances
+    LayoutBuiltin { name: BuiltinName::keccak, ratio: 2048, instances_per_component: 16, cells_per_instance: 16 },
+    builtin(BuiltinName::poseidon, 32, 6),
+];
+
+/// How the layout of an OS run is chosen.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LayoutSelection {
+    /// Always run in this layout.
+    Fixed(LayoutName),
+    /// Run in the smallest layout of [`AUTO_LAYOUTS`] that fits the execution resources of the
+    /// block.
+    Auto,
+}
+
+impl From<LayoutName> for LayoutSelection {
+    fn from(layout: LayoutName) -> Self {
+        LayoutSelection::Fixed(layout)
+    }
+}
+
+impl fmt::Display for LayoutSelection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LayoutSelection::Fixed(layout) => write!(f, "{}", layout.to_str()),
+            LayoutSelection::Auto => write!(f, "auto"),
+        }
+    }
+}
+
+impl FromStr for LayoutSelection {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let layout = match s {
+            "auto" => return Ok(LayoutSelection::Auto),
+            "plain" => LayoutName::plain,
+            "small" => LayoutName::small,
+            "dex" => LayoutName::dex,
+            "recursive" => LayoutName::recursive,
+            "starknet" => LayoutName::starknet,
+            "starknet_with_keccak" => LayoutName::starknet_with_keccak,
+            "recursive_large_output" => LayoutName::recursive_large_output,
+            "recursive_with_poseidon" => LayoutName::recursive_with_poseidon,
+            "all_solidity" => LayoutName::all_solidity,
+            "all_cairo" => LayoutName::all_cairo,
+            "dynamic" => LayoutName::dynamic,
+            _ => return Err(format!("unknown layout {s}")),
+        };
+
+        Ok(LayoutSelection::Fixed(layout))
+    }
+}
+
+/// Why a layout cannot be used for a run.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum LayoutRejection {
+    /// The run uses a builtin that the layout does not have.
+    MissingBuiltin { builtin: String },
+    /// The run uses more instances of a builtin than the layout allows for its number of steps.
+    InsufficientCapacity { builtin: String, used: usize, capacity: usize },
+    /// The memory holes of the run do not fit in the memory cells left by the instructions, the
+    /// public memory and the builtins.
+    InsufficientMemory { used: usize, capacity: usize },
+    /// The range checked values of the run span more values than the range check units left by
+    /// the instructions and the range check builtin.
+    InsufficientRangeCheckUnits { used: usize, capacity: usize },
+}
+
+impl fmt::Display for LayoutRejection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LayoutRejection::MissingBuiltin { builtin } => write!(f, "no {builtin} builtin"),
+            LayoutRejection::InsufficientCapacity { builtin, used, capacity } => {
+                write!(f, "{used} {builtin} instances used, {capacity} available")
+            }
+            LayoutRejection::InsufficientMemory { used, capacity } => {
+                write!(f, "{used} memory cells used, {capacity} available")
+            }
+            LayoutRejection::InsufficientRangeCheckUnits { used, capacity } => {
+                write!(f, "{used} range check units used, {capacity} available")
+            }
+        }
+    }
+}
+
+/// A layout rejected by [`LayoutSelection::Auto`], and why.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct RejectedLayout {
+    pub layout: String,
+    pub reasons: Vec<LayoutRejection>,
+}
+
+impl fmt::Display for RejectedLayout {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let reasons: Vec<_> = self.reasons.iter().map(ToString::to_string).collect();
+        write!(f, "{}: {}", self.layout, reasons.join(", "))
+    }
+}
+
+/// Parameters of a layout, `None` for layouts whose ratios are only chosen when proving, like
+/// `dynamic`, which fit any run.
+fn layout_params(layout: LayoutName) -> Option<LayoutParams> {
+    match layout {
+        LayoutName::starknet => {
+            Some(LayoutParams { builtins: STARKNET_BUILTINS, rc_units: 4, public_memory_fraction: 8 })
+        }
+        LayoutName::starknet_with_keccak => {
+            Some(LayoutParams { builtins: STARKNET_WITH_KECCAK_BUILTINS, rc_units: 4, public_memory_fraction: 8 })
+        }
+        _ => None,
+    }
+}
+
+/// Checks whether the execution resources of a run fit in a layout.
+///
+/// The prover pads the trace to the next power of two, and a builtin with ratio `r` has one
+/// component every `r` steps of the padded trace. The memory cells and the range check units
+/// of the padded trace must also cover the memory holes and the `range_check_span` of the run,
+/// the difference between the largest and the smallest value checked by the permutation range
+/// check. Returns the reasons why the layout does not fit, empty if it does.
+pub fn check_layout(
+    layout: LayoutName,
+    resources: &ExecutionResources,
+    range_check_span: usize,
+) -> Vec<LayoutRejection> {
+    let Some(params) = layout_params(layout) else {
+        return vec![];
+    };
+    let n_steps = resources.n_steps.next_power_of_two();
+    let used_instances = |name: BuiltinName| resources.builtin_instance_counter.get(&name).copied().unwrap_or_default();
+
+    let mut rejections = vec![];
+    let mut used_builtins: Vec<_> = resources.builtin_instance_counter.iter().filter(|(_, used)| **used > 0).collect();
+    used_builtins.sort_by_key(|(builtin, _)| builtin.to_str());
+    for (builtin, used) in used_builtins {
+        match params.builtins.iter().find(|layout_builtin| layout_builtin.name == *builtin) {
+            None => rejections.push(LayoutRejection::MissingBuiltin { builtin: builtin.to_str().to_string() }),
+            // The output builtin has no ratio
+            Some(LayoutBuiltin { ratio: 0, .. }) => {}
+            Some(layout_builtin) => {
+                let capacity = builtin_capacity(layout_builtin, n_steps);
+                if *used > capacity {
+                    rejections.push(LayoutRejection::InsufficientCapacity {
+                        builtin: builtin.to_str().to_string(),
+                        used: *used,
+                        capacity,
+                    });
+                }
+            }
+        }
+    }
+
+    // Builtins take all the memory cells of their capacity, except the output builtin
+    let builtin_memory_units: usize = params
+        .builtins
+        .iter()
+        .map(|layout_builtin| match layout_builtin.ratio {
+            0 => used_instances(layout_builtin.name) * layout_builtin.cells_per_instance,
+            _ => builtin_capacity(layout_builtin, n_steps) * layout_builtin.cells_per_instance,
+        })
+        .sum();
+    let memory_units = MEMORY_UNITS_PER_STEP * n_steps;
+    let used_memory_units = memory_units / params.public_memory_fraction
+        + INSTRUCTION_MEMORY_UNITS * n_steps
+        + builtin_memory_units
+        + resources.n_memory_holes;
+    if used_memory_units > memory_units {
+        rejections.push(LayoutRejection::InsufficientMemory { used: used_memory_units, capacity: memory_units });
+    }
+
+    let range_check_units = (params.rc_units - INSTRUCTION_RANGE_CHECK_UNITS) * n_steps;
+    let used_range_check_units = used_instances(BuiltinName::range_check) * RANGE_CHECK_N_PARTS + range_check_span;
+    if used_range_check_units > range_check_units {
+        rejections.push(LayoutRejection::InsufficientRangeCheckUnits {
+            used: used_range_check_units,
+            capacity: range_check_units,
+        });
+    }
+
+    rejections
+}
+
+/// Instances of a builtin available in `n_steps` steps.
+fn builtin_capacity(builtin: &LayoutBuiltin, n_steps: usize) -> usize {
+    n_steps / builtin.ratio * builtin.instances_per_component
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+
+    fn execution_resources(n_steps: usize, builtins: &[(BuiltinName, usize)]) -> ExecutionResources {
+        ExecutionResources {
+            n_steps,
+            n_memory_holes: 0,
+            builtin_instance_counter: builtins.iter().copied().collect::<HashMap<_, _>>(),
+        }
+    }
+
+    #[test]
+    fn test_parse_layout_selection() {
+        assert_eq!("auto".parse::<LayoutSelection>(), Ok(LayoutSelection::Auto));
+        assert_eq!("starknet".parse::<LayoutSelection>(), Ok(LayoutSelection::Fixed(LayoutName::starknet)));
+        assert!("starknet_with_sha".parse::<LayoutSelection>().is_err());
+    }
+
+    #[test]
+    fn test_check_layout_fits() {
+        let resources = execution_resources(1000, &[(BuiltinName::output, 1), (BuiltinName::pedersen, 32)]);
+        assert_eq!(check_layout(LayoutName::starknet, &resources, 0), vec![]);
+    }
+
+    #[test]
+    fn test_check_layout_missing_builtin() {
+        let resources = execution_resources(4096, &[(BuiltinName::keccak, 1)]);
+        assert_eq!(
+            check_layout(LayoutName::starknet, &resources, 0),
+            vec![LayoutRejection::MissingBuiltin { builtin: "keccak".to_string() }]
+        );
+        assert_eq!(check_layout(LayoutName::starknet_with_keccak, &resources, 0), vec![]);
+    }
+
+    #[test]
+    fn test_check_layout_keccak_capacity() {
+        // 4096 steps leave room for 2 keccak components of 16 instances
+        let resources = execution_resources(4096, &[(BuiltinName::keccak, 32)]);
+        assert_eq!(check_layout(LayoutName::starknet_with_keccak, &resources, 0), vec![]);
+
+        let resources = execution_resources(4096, &[(BuiltinName::keccak, 33)]);
+        assert_eq!(
+            check_layout(LayoutName::starknet_with_keccak, &resources, 0),
+            vec![LayoutRejection::InsufficientCapacity { builtin: "keccak".to_string(), used: 33, capacity: 32 }]
+        );
+    }
+
+    #[test]
+    fn test_check_layout_insufficient_memory() {
+        // Out of 8192 memory cells, 1024 are public, 4096 used by instructions and 439 by builtins
+        let mut resources = execution_resources(1000, &[]);
+        resources.n_memory_holes = 2633;
+        assert_eq!(check_layout(LayoutName::starknet, &resources, 0), vec![]);
+
+        resources.n_memory_holes = 2634;
+        assert_eq!(
+            check_layout(LayoutName::starknet, &resources, 0),
+            vec![LayoutRejection::InsufficientMemory { used: 8193, capacity: 8192 }]
+        );
+    }
+
+    #[test]
+    fn test_check_layout_insufficient_range_check_units() {
+        // 1024 steps leave 1024 range check units, 80 of them used by the range check builtin
+        let resources = execution_resources(1000, &[(BuiltinName::range_check, 10)]);
+        assert_eq!(check_layout(LayoutName::starknet, &resources, 944), vec![]);
+        assert_eq!(
+            check_layout(LayoutName::starknet, &resources, 945),
+            vec![LayoutRejection::InsufficientRangeCheckUnits { used: 1025, capacity: 1024 }]
+        );
+    }
+
+    #[test]
+    fn test_check_layout_insufficient_capacity() {
+        // 1000 steps are padded to 1024, which leaves room for 32 pedersen instances
+        let resources = execution_resources(1000, &[(BuiltinName::pedersen, 33)]);
+        assert_eq!(
+            check_layout(LayoutName::starknet, &resources, 0),
+            vec![LayoutRejection::InsufficientCapacity { builtin: "pedersen".to_string(), used: 33, capacity: 32 }]
+        );
+        assert_eq!(check_layout(LayoutName::dynamic, &resources, 0), vec![]);
+    }
+}
@@ -1,17 +1,25 @@
-use std::fs;
+use std::borrow::Cow;
+use std::time::{Duration, Instant};
 
 use blockifier::block_context::BlockContext;
 use cairo_vm::cairo_run::CairoRunConfig;
+use cairo_vm::types::builtin_name::BuiltinName;
 use cairo_vm::types::layout_name::LayoutName;
 use cairo_vm::types::program::Program;
 use cairo_vm::vm::errors::vm_exception::VmException;
-use cairo_vm::vm::runners::cairo_pie::CairoPie;
-use cairo_vm::vm::runners::cairo_runner::CairoRunner;
+use cairo_vm::vm::runners::cairo_runner::{CairoRunner, ExecutionResources};
 use cairo_vm::vm::vm_core::VirtualMachine;
 use error::SnOsError;
 use execution::deprecated_syscall_handler::DeprecatedOsSyscallHandlerWrapper;
 use execution::helper::ExecutionHelperWrapper;
+use execution::os_logger::OsLogEvent;
+use execution::resource_report::ResourceReport;
+use hints::SnosHintProcessor;
 use io::output::StarknetOsOutput;
+use layout::{check_layout, LayoutRejection, LayoutSelection, RejectedLayout, AUTO_LAYOUTS};
+use program::{load_os_program, CompiledOs};
+use prover_input::{write_prover_input, ProverInputPaths};
+use run_result::{OsProofRunResult, OsRunResult, OsRunTimings};
 
 use crate::execution::syscall_handler::OsSyscallHandlerWrapper;
 use crate::hints::types::PatriciaSkipValidationRunner;
@@ -25,6 +33,11 @@ pub mod error;
 pub mod execution;
 pub mod hints;
 pub mod io;
+pub mod layout;
+pub mod program;
+pub mod program_hash;
+pub mod prover_input;
+pub mod run_result;
 pub mod sharp;
 pub mod starknet;
 pub mod starkware_utils;
@@ -32,23 +45,224 @@ pub mod state;
 pub mod storage;
 pub mod utils;
 
-pub fn run_os(
-    os_path: String,
+/// Runs the Starknet OS and returns the Cairo PIE of the run, the decoded OS output, the
+/// execution resources and the time spent in each phase.
+///
+/// With [`LayoutSelection::Auto`], the OS runs in the smallest layout that fits the builtin usage
+/// and step count of the block. Execution resources do not depend on the layout, so the OS runs
+/// again only if the first layout tried turns out to be too small.
+///
+/// The OS can be given as the bytes of its JSON, parsed once per process, or as a parsed
+/// [`Program`], see [`CompiledOs`]. Modified versions of the OS can bring their own hints with
+/// [`SnosHintProcessor::builder`].
+pub fn run_os<'a>(
+    compiled_os: impl Into<CompiledOs<'a>>,
+    layout: LayoutSelection,
+    os_input: StarknetOsInput,
+    block_context: BlockContext,
+    execution_helper: ExecutionHelperWrapper,
+    mut hint_processor: SnosHintProcessor,
+) -> Result<OsRunResult, SnOsError> {
+    let (program, program_loading) = load_program(compiled_os.into())?;
+    let mut run = run_os_with_layout_selection(
+        &program,
+        layout,
+        false,
+        os_input,
+        block_context,
+        execution_helper,
+        &mut hint_processor,
+    )?;
+
+    // Parse the Cairo VM output
+    let start = Instant::now();
+    let pie = run.cairo_runner.get_cairo_pie(&run.vm).map_err(|e| SnOsError::PieParsing(format!("{e}")))?;
+    run.timings.finalization += start.elapsed();
+    run.timings.program_loading = program_loading;
+
+    Ok(OsRunResult {
+        pie,
+        os_output: run.os_output,
+        execution_resources: run.execution_resources,
+        layout: run.layout,
+        rejected_layouts: run.rejected_layouts,
+        timings: run.timings,
+        os_log: run.os_log,
+        resource_report: run.resource_report,
+    })
+}
+
+/// Runs the Starknet OS in proof mode and writes the inputs of the Stone prover: the relocated
+/// trace and memory, and the AIR public and private inputs.
+///
+/// The OS program must be compiled in proof mode, with the `__start__` and `__end__` labels.
+/// Layouts are selected as in [`run_os`].
+pub fn run_os_proof_mode<'a>(
+    compiled_os: impl Into<CompiledOs<'a>>,
+    layout: LayoutSelection,
+    os_input: StarknetOsInput,
+    block_context: BlockContext,
+    execution_helper: ExecutionHelperWrapper,
+    mut hint_processor: SnosHintProcessor,
+    prover_input: &ProverInputPaths,
+) -> Result<OsProofRunResult, SnOsError> {
+    let (program, program_loading) = load_program(compiled_os.into())?;
+    let mut run = run_os_with_layout_selection(
+        &program,
+        layout,
+        true,
+        os_input,
+        block_context,
+        execution_helper,
+        &mut hint_processor,
+    )?;
+
+    let start = Instant::now();
+    write_prover_input(&run.cairo_runner, &run.vm, prover_input)?;
+    run.timings.finalization += start.elapsed();
+    run.timings.program_loading = program_loading;
+
+    Ok(OsProofRunResult {
+        os_output: run.os_output,
+        execution_resources: run.execution_resources,
+        layout: run.layout,
+        rejected_layouts: run.rejected_layouts,
+        timings: run.timings,
+        os_log: run.os_log,
+        resource_report: run.resource_report,
+        prover_input: prover_input.clone(),
+    })
+}
+
+/// Loads the Starknet OS program, and returns how long it took.
+fn load_program(compiled_os: CompiledOs<'_>) -> Result<(Cow<'_, Program>, Duration), SnOsError> {
+    let start = Instant::now();
+    let program = match compiled_os {
+        CompiledOs::Bytes(compiled_os) => Cow::Owned(load_os_program(compiled_os)?),
+        CompiledOs::Program(program) => Cow::Borrowed(program),
+    };
+
+    Ok((program, start.elapsed()))
+}
+
+/// A finished OS run, before the PIE or the prover input are extracted from it.
+struct OsRun {
+    cairo_runner: CairoRunner,
+    vm: VirtualMachine,
+    os_output: StarknetOsOutput,
+    execution_resources: ExecutionResources,
+    /// Difference between the largest and the smallest value checked by the permutation range
+    /// check, see [`check_layout`].
+    range_check_span: usize,
     layout: LayoutName,
+    rejected_layouts: Vec<RejectedLayout>,
+    timings: OsRunTimings,
+    os_log: Vec<OsLogEvent>,
+    resource_report: ResourceReport,
+}
+
+fn run_os_with_layout_selection(
+    program: &Program,
+    layout: LayoutSelection,
+    proof_mode: bool,
     os_input: StarknetOsInput,
     block_context: BlockContext,
     execution_helper: ExecutionHelperWrapper,
-) -> Result<CairoPie, SnOsError> {
-    // Init CairoRunConfig
-    let cairo_run_config = CairoRunConfig { layout, relocate_mem: true, trace_enabled: true, ..Default::default() };
+    hint_processor: &mut SnosHintProcessor,
+) -> Result<OsRun, SnOsError> {
+    match layout {
+        LayoutSelection::Fixed(layout) => {
+            run_os_in_layout(program, layout, proof_mode, os_input, block_context, execution_helper, hint_processor)
+        }
+        LayoutSelection::Auto => {
+            run_os_auto_layout(program, proof_mode, os_input, block_context, execution_helper, hint_processor)
+        }
+    }
+}
 
-    // Load the Starknet OS Program
-    let starknet_os = fs::read(os_path).map_err(|e| SnOsError::CatchAll(format!("{e}")))?;
-    let program = Program::from_bytes(&starknet_os, Some(cairo_run_config.entrypoint))
-        .map_err(|e| SnOsError::Runner(e.into()))?;
+fn run_os_auto_layout(
+    program: &Program,
+    proof_mode: bool,
+    os_input: StarknetOsInput,
+    block_context: BlockContext,
+    execution_helper: ExecutionHelperWrapper,
+    hint_processor: &mut SnosHintProcessor,
+) -> Result<OsRun, SnOsError> {
+    let program_builtins: Vec<_> = program.iter_builtins().copied().collect();
+    // Execution resources and range check span of the last run
+    let mut usage: Option<(ExecutionResources, usize)> = None;
+    let mut rejected_layouts = vec![];
+    let mut rejected_runs = Duration::ZERO;
+
+    for layout in AUTO_LAYOUTS {
+        // Reject the layout without running the OS when possible
+        let mut reasons = match &usage {
+            Some((execution_resources, range_check_span)) => {
+                check_layout(layout, execution_resources, *range_check_span)
+            }
+            None => check_program_builtins(layout, &program_builtins),
+        };
+        if reasons.is_empty() {
+            // The OS consumes the execution helper, keep the original for a possible second run
+            let mut run = run_os_in_layout(
+                program,
+                layout,
+                proof_mode,
+                os_input.clone(),
+                block_context.clone(),
+                execution_helper.deep_clone(),
+                hint_processor,
+            )?;
+            reasons = check_layout(layout, &run.execution_resources, run.range_check_span);
+            if reasons.is_empty() {
+                log::info!("Running the OS in the {} layout", layout.to_str());
+                run.rejected_layouts = rejected_layouts;
+                run.timings.rejected_runs = rejected_runs;
+                return Ok(run);
+            }
+            rejected_runs += run.timings.total();
+            usage = Some((run.execution_resources, run.range_check_span));
+        }
+
+        let rejected_layout = RejectedLayout { layout: layout.to_str().to_string(), reasons };
+        log::info!("Rejected layout {}", rejected_layout);
+        rejected_layouts.push(rejected_layout);
+    }
+
+    Err(SnOsError::NoSuitableLayout(rejected_layouts))
+}
+
+/// Checks that a layout has every builtin of the program, before running it.
+fn check_program_builtins(layout: LayoutName, program_builtins: &[BuiltinName]) -> Vec<LayoutRejection> {
+    let execution_resources = ExecutionResources {
+        n_steps: 0,
+        n_memory_holes: 0,
+        builtin_instance_counter: program_builtins.iter().map(|builtin| (*builtin, 1)).collect(),
+    };
+
+    check_layout(layout, &execution_resources, 0)
+        .into_iter()
+        .filter(|rejection| matches!(rejection, LayoutRejection::MissingBuiltin { .. }))
+        .collect()
+}
+
+fn run_os_in_layout(
+    program: &Program,
+    layout: LayoutName,
+    proof_mode: bool,
+    os_input: StarknetOsInput,
+    block_context: BlockContext,
+    execution_helper: ExecutionHelperWrapper,
+    hint_processor: &mut SnosHintProcessor,
+) -> Result<OsRun, SnOsError> {
+    let start = Instant::now();
+
+    // Init CairoRunConfig
+    let cairo_run_config =
+        CairoRunConfig { layout, relocate_mem: true, trace_enabled: true, proof_mode, ..Default::default() };
 
     // Init cairo runner
-    let mut cairo_runner = CairoRunner::new(&program, cairo_run_config.layout, cairo_run_config.proof_mode)
+    let mut cairo_runner = CairoRunner::new(program, cairo_run_config.layout, cairo_run_config.proof_mode)
         .map_err(|e| SnOsError::Runner(e.into()))?;
 
     // Init the Cairo VM
@@ -64,40 +278,65 @@ pub fn run_os(
     // Setup Globals
     cairo_runner.exec_scopes.insert_value("os_input", os_input);
     cairo_runner.exec_scopes.insert_box("block_context", Box::new(block_context));
-    cairo_runner.exec_scopes.insert_value("execution_helper", execution_helper);
+    cairo_runner.exec_scopes.insert_value("execution_helper", execution_helper.clone());
     cairo_runner.exec_scopes.insert_value("deprecated_syscall_handler", deprecated_syscall_handler);
     cairo_runner.exec_scopes.insert_value("syscall_handler", syscall_handler);
     cairo_runner
         .exec_scopes
         .insert_value(vars::scopes::PATRICIA_SKIP_VALIDATION_RUNNER, None::<PatriciaSkipValidationRunner>);
 
+    let initialization = start.elapsed();
+
     // Run the Cairo VM
-    let mut sn_hint_processor = hints::SnosHintProcessor::default();
+    let start = Instant::now();
     cairo_runner
-        .run_until_pc(end, &mut vm, &mut sn_hint_processor)
+        .run_until_pc(end, &mut vm, hint_processor)
         .map_err(|err| VmException::from_vm_error(&cairo_runner, &vm, err))
         .map_err(|e| SnOsError::Runner(e.into()))?;
+    if cairo_run_config.proof_mode {
+        // Run one more step so that the final `jmp rel 0` of `__end__` is the last step of the trace
+        cairo_runner.run_for_steps(1, &mut vm, hint_processor).map_err(|e| SnOsError::Runner(e.into()))?;
+    }
+    let execution = start.elapsed();
 
     // End the Cairo VM run
+    let start = Instant::now();
     cairo_runner
-        .end_run(cairo_run_config.disable_trace_padding, false, &mut vm, &mut sn_hint_processor)
+        .end_run(cairo_run_config.disable_trace_padding, false, &mut vm, hint_processor)
         .map_err(|e| SnOsError::Runner(e.into()))?;
 
-    if cairo_run_config.proof_mode {
-        cairo_runner.finalize_segments(&mut vm).map_err(|e| SnOsError::Runner(e.into()))?;
-    }
-
     // Prepare and check expected output.
     let os_output = StarknetOsOutput::from_run(&vm)?;
 
-    println!("output: {:?}", os_output);
-
     vm.verify_auto_deductions().map_err(|e| SnOsError::Runner(e.into()))?;
     cairo_runner.read_return_values(&mut vm, false).map_err(|e| SnOsError::Runner(e.into()))?;
+    if cairo_run_config.proof_mode {
+        // The AIR public input needs the final size of each segment
+        cairo_runner.finalize_segments(&mut vm).map_err(|e| SnOsError::Runner(e.into()))?;
+    }
     cairo_runner.relocate(&mut vm, cairo_run_config.relocate_mem).map_err(|e| SnOsError::Runner(e.into()))?;
 
-    // Parse the Cairo VM output
-    let pie = cairo_runner.get_cairo_pie(&vm).map_err(|e| SnOsError::PieParsing(format!("{e}")))?;
+    let execution_resources = cairo_runner.get_execution_resources(&vm).map_err(|e| SnOsError::Runner(e.into()))?;
+    let range_check_span =
+        cairo_runner.get_perm_range_check_limits(&vm).map(|(min, max)| max.abs_diff(min)).unwrap_or_default();
+
+    let timings = OsRunTimings { initialization, execution, finalization: start.elapsed(), ..Default::default() };
+
+    let (os_log, resource_report) = {
+        let mut eh_ref = execution_helper.execution_helper.as_ref().borrow_mut();
+        (eh_ref.os_logger.take_events(), eh_ref.resource_profiler.take_report())
+    };
 
-    Ok(pie)
+    Ok(OsRun {
+        cairo_runner,
+        vm,
+        os_output,
+        execution_resources,
+        range_check_span,
+        layout,
+        rejected_layouts: vec![],
+        timings,
+        os_log,
+        resource_report,
+    })
 }
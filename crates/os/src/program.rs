@@ -0,0 +1,87 @@
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+use std::sync::Mutex;
+
+use cairo_vm::types::program::Program;
+use lazy_static::lazy_static;
+
+use crate::error::SnOsError;
+
+/// The compiled Starknet OS, embedded with the `embedded-os` feature.
+#[cfg(feature = "embedded-os")]
+pub const EMBEDDED_COMPILED_OS: &[u8] = include_bytes!(concat!(env!("OUT_DIR"), "/compiled_os.json"));
+
+/// Program hash of [`EMBEDDED_COMPILED_OS`], checked when building the crate.
+#[cfg(feature = "embedded-os")]
+pub const EMBEDDED_OS_PROGRAM_HASH: &str = env!("ARCANE_OS_PROGRAM_HASH");
+
+lazy_static! {
+    /// Parsed OS programs by hash of their JSON.
+    static ref PROGRAM_CACHE: Mutex<HashMap<u64, Program>> = Mutex::new(HashMap::new());
+}
+
+/// The Starknet OS program to run.
+#[derive(Debug, Clone, Copy)]
+pub enum CompiledOs<'a> {
+    /// The JSON of the compiled OS. It is parsed once, later runs reuse the parsed program.
+    Bytes(&'a [u8]),
+    /// An already parsed OS program.
+    Program(&'a Program),
+}
+
+#[cfg(feature = "embedded-os")]
+impl CompiledOs<'static> {
+    /// The OS embedded in the crate.
+    pub fn embedded() -> Self {
+        CompiledOs::Bytes(EMBEDDED_COMPILED_OS)
+    }
+}
+
+impl<'a> From<&'a [u8]> for CompiledOs<'a> {
+    fn from(compiled_os: &'a [u8]) -> Self {
+        CompiledOs::Bytes(compiled_os)
+    }
+}
+
+impl<'a> From<&'a Vec<u8>> for CompiledOs<'a> {
+    fn from(compiled_os: &'a Vec<u8>) -> Self {
+        CompiledOs::Bytes(compiled_os)
+    }
+}
+
+impl<'a> From<&'a Program> for CompiledOs<'a> {
+    fn from(program: &'a Program) -> Self {
+        CompiledOs::Program(program)
+    }
+}
+
+/// Parses the JSON of the compiled OS, or returns the program parsed by a previous call.
+///
+/// Parsing the OS takes longer than running it on small blocks, the cache keeps one program
+/// per distinct JSON for the lifetime of the process.
+pub fn load_os_program(compiled_os: &[u8]) -> Result<Program, SnOsError> {
+    let mut hasher = DefaultHasher::new();
+    compiled_os.hash(&mut hasher);
+    let key = hasher.finish();
+
+    if let Some(program) = PROGRAM_CACHE.lock().unwrap().get(&key) {
+        return Ok(program.clone());
+    }
+
+    // Parse outside of the lock, concurrent runs of other programs do not wait for this one
+    let program = Program::from_bytes(compiled_os, Some("main")).map_err(|e| SnOsError::Runner(e.into()))?;
+    PROGRAM_CACHE.lock().unwrap().insert(key, program.clone());
+
+    Ok(program)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_load_os_program_invalid_json() {
+        assert!(load_os_program(b"not a program").is_err());
+    }
+}
@@ -0,0 +1,85 @@
+//! Hash of a compiled Cairo program, as computed by `compute_program_hash_chain` in cairo-lang.
+//!
+//! Also used by the build script to check the embedded OS, so it only depends on
+//! `serde_json` and `starknet-crypto`.
+
+use serde_json::Value;
+use starknet_crypto::{pedersen_hash, FieldElement};
+
+/// Version of the bootloader the program hash is computed for.
+const BOOTLOADER_VERSION: u64 = 0;
+
+/// Computes the hash of a compiled program from its JSON.
+///
+/// The hash covers the header of the program (bootloader version, entry point and builtins)
+/// followed by its bytecode, hashed as a Pedersen chain.
+pub fn compute_program_hash(compiled_program: &[u8]) -> Result<FieldElement, String> {
+    let program: Value =
+        serde_json::from_slice(compiled_program).map_err(|e| format!("invalid compiled program: {e}"))?;
+    let main = program["identifiers"]["__main__.main"]["pc"].as_u64().ok_or("the program has no main function")?;
+    let builtins = program["builtins"].as_array().ok_or("the program has no builtins")?;
+    let data = program["data"].as_array().ok_or("the program has no data")?;
+
+    let mut data_chain = Vec::with_capacity(3 + builtins.len() + data.len());
+    data_chain.push(FieldElement::from(BOOTLOADER_VERSION));
+    data_chain.push(FieldElement::from(main));
+    data_chain.push(FieldElement::from(builtins.len() as u64));
+    for builtin in builtins {
+        let builtin = builtin.as_str().ok_or("invalid builtin name")?;
+        // Builtins are encoded as the short string of their name
+        let builtin =
+            FieldElement::from_byte_slice_be(builtin.as_bytes()).map_err(|e| format!("invalid builtin name: {e}"))?;
+        data_chain.push(builtin);
+    }
+    for word in data {
+        let word = word.as_str().ok_or("invalid program data")?;
+        data_chain.push(FieldElement::from_hex_be(word).map_err(|e| format!("invalid program data {word}: {e}"))?);
+    }
+
+    Ok(compute_hash_chain(&data_chain))
+}
+
+/// Hashes `[len(data), data...]` as `h(len, h(x_0, h(x_1, ... h(x_n-2, x_n-1))))`.
+fn compute_hash_chain(data: &[FieldElement]) -> FieldElement {
+    let len = FieldElement::from(data.len() as u64);
+    std::iter::once(&len).chain(data).rev().copied().reduce(|hash, x| pedersen_hash(&x, &hash)).unwrap_or(len)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_compute_hash_chain() {
+        let data = [FieldElement::from(1u64), FieldElement::from(2u64)];
+        let expected = pedersen_hash(
+            &FieldElement::from(2u64),
+            &pedersen_hash(&FieldElement::from(1u64), &FieldElement::from(2u64)),
+        );
+        assert_eq!(compute_hash_chain(&data), expected);
+    }
+
+    #[test]
+    fn test_compute_program_hash() {
+        let program = br#"{
+            "builtins": ["output"],
+            "data": ["0x1", "0x2"],
+            "identifiers": {"__main__.main": {"pc": 0, "type": "function"}}
+        }"#;
+        let data_chain = [
+            FieldElement::ZERO,
+            FieldElement::ZERO,
+            FieldElement::ONE,
+            FieldElement::from_byte_slice_be(b"output").unwrap(),
+            FieldElement::ONE,
+            FieldElement::TWO,
+        ];
+        assert_eq!(compute_program_hash(program), Ok(compute_hash_chain(&data_chain)));
+    }
+
+    #[test]
+    fn test_compute_program_hash_without_main() {
+        let program = br#"{"builtins": [], "data": [], "identifiers": {}}"#;
+        assert!(compute_program_hash(program).is_err());
+    }
+}
@@ -0,0 +1,131 @@
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+use cairo_vm::vm::runners::cairo_runner::CairoRunner;
+use cairo_vm::vm::trace::trace_entry::RelocatedTraceEntry;
+use cairo_vm::vm::vm_core::VirtualMachine;
+use cairo_vm::Felt252;
+
+use crate::error::SnOsError;
+
+/// Where the inputs of the Stone prover are written after a proof-mode run.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProverInputPaths {
+    /// Relocated trace, one `(ap, fp, pc)` entry per step.
+    pub trace: PathBuf,
+    /// Relocated memory, as `(address, value)` pairs.
+    pub memory: PathBuf,
+    /// AIR public input: layout, number of steps, memory segments and public memory.
+    pub air_public_input: PathBuf,
+    /// AIR private input: paths of the trace and memory files and the builtin inputs.
+    pub air_private_input: PathBuf,
+}
+
+impl ProverInputPaths {
+    /// Uses the file names of the Stone prover examples, in `directory`.
+    pub fn in_directory(directory: impl AsRef<Path>) -> Self {
+        let directory = directory.as_ref();
+        Self {
+            trace: directory.join("trace.bin"),
+            memory: directory.join("memory.bin"),
+            air_public_input: directory.join("air_public_input.json"),
+            air_private_input: directory.join("air_private_input.json"),
+        }
+    }
+}
+
+/// Writes the trace, memory and AIR inputs of a proof-mode run, relocated beforehand.
+pub fn write_prover_input(
+    cairo_runner: &CairoRunner,
+    vm: &VirtualMachine,
+    paths: &ProverInputPaths,
+) -> Result<(), SnOsError> {
+    let trace = vm.get_relocated_trace().map_err(|e| SnOsError::ProverInput(format!("{e}")))?;
+    write_to_file(&paths.trace, |writer| write_encoded_trace(trace, writer))?;
+    write_to_file(&paths.memory, |writer| write_encoded_memory(&cairo_runner.relocated_memory, writer))?;
+
+    let public_input = cairo_runner
+        .get_air_public_input(vm)
+        .and_then(|public_input| public_input.serialize_json())
+        .map_err(|e| SnOsError::ProverInput(format!("{e}")))?;
+    std::fs::write(&paths.air_public_input, public_input)?;
+
+    // The private input refers to the trace and memory files, the prover may run elsewhere
+    let trace_path = paths.trace.canonicalize()?;
+    let memory_path = paths.memory.canonicalize()?;
+    let private_input = cairo_runner
+        .get_air_private_input(vm)
+        .to_serializable(trace_path.to_string_lossy().to_string(), memory_path.to_string_lossy().to_string())
+        .serialize_json()?;
+    std::fs::write(&paths.air_private_input, private_input)?;
+
+    Ok(())
+}
+
+fn write_to_file<F>(path: &Path, write: F) -> Result<(), SnOsError>
+where
+    F: FnOnce(&mut BufWriter<File>) -> std::io::Result<()>,
+{
+    let mut writer = BufWriter::new(File::create(path)?);
+    write(&mut writer)?;
+    writer.flush()?;
+
+    Ok(())
+}
+
+/// Encodes the trace in the binary format of the Stone prover: `ap`, `fp` and `pc` of each
+/// step as 64-bit little-endian integers.
+pub fn write_encoded_trace(trace: &[RelocatedTraceEntry], writer: &mut impl Write) -> std::io::Result<()> {
+    for entry in trace {
+        writer.write_all(&(entry.ap as u64).to_le_bytes())?;
+        writer.write_all(&(entry.fp as u64).to_le_bytes())?;
+        writer.write_all(&(entry.pc as u64).to_le_bytes())?;
+    }
+
+    Ok(())
+}
+
+/// Encodes the memory in the binary format of the Stone prover: the address of each cell as a
+/// 64-bit little-endian integer followed by its value as a 32-byte little-endian integer.
+/// Memory holes are skipped.
+pub fn write_encoded_memory(memory: &[Option<Felt252>], writer: &mut impl Write) -> std::io::Result<()> {
+    for (address, value) in memory.iter().enumerate() {
+        if let Some(value) = value {
+            writer.write_all(&(address as u64).to_le_bytes())?;
+            writer.write_all(&value.to_bytes_le())?;
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_write_encoded_trace() {
+        let trace = vec![RelocatedTraceEntry { pc: 1, ap: 2, fp: 3 }, RelocatedTraceEntry { pc: 4, ap: 5, fp: 6 }];
+        let mut encoded = vec![];
+        write_encoded_trace(&trace, &mut encoded).unwrap();
+
+        let words: Vec<u64> = encoded.chunks(8).map(|chunk| u64::from_le_bytes(chunk.try_into().unwrap())).collect();
+        assert_eq!(words, vec![2, 3, 1, 5, 6, 4]);
+    }
+
+    #[test]
+    fn test_write_encoded_memory_skips_holes() {
+        // Relocated memory starts at address 1
+        let memory = vec![None, Some(Felt252::from(7)), None, Some(Felt252::from(0x0102))];
+        let mut encoded = vec![];
+        write_encoded_memory(&memory, &mut encoded).unwrap();
+
+        assert_eq!(encoded.len(), 2 * 40);
+        assert_eq!(&encoded[0..8], &1u64.to_le_bytes());
+        assert_eq!(encoded[8], 7);
+        assert_eq!(&encoded[40..48], &3u64.to_le_bytes());
+        assert_eq!(&encoded[48..50], &[0x02, 0x01]);
+        assert!(encoded[50..80].iter().all(|byte| *byte == 0));
+    }
+}
@@ -0,0 +1,65 @@
+use std::time::Duration;
+
+use cairo_vm::types::layout_name::LayoutName;
+use cairo_vm::vm::runners::cairo_pie::CairoPie;
+use cairo_vm::vm::runners::cairo_runner::ExecutionResources;
+
+use crate::execution::os_logger::OsLogEvent;
+use crate::execution::resource_report::ResourceReport;
+use crate::io::output::StarknetOsOutput;
+use crate::layout::RejectedLayout;
+use crate::prover_input::ProverInputPaths;
+
+/// Time spent in each phase of an OS run.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct OsRunTimings {
+    /// Parsing the compiled OS program.
+    pub program_loading: Duration,
+    /// Creating the runner and setting up the syscall handlers and scopes.
+    pub initialization: Duration,
+    /// Running the OS program until its end.
+    pub execution: Duration,
+    /// Ending the run, decoding the output and building the PIE or writing the prover input.
+    pub finalization: Duration,
+    /// Runs in layouts that turned out to be too small, see [`crate::layout::LayoutSelection::Auto`].
+    pub rejected_runs: Duration,
+}
+
+impl OsRunTimings {
+    pub fn total(&self) -> Duration {
+        self.program_loading + self.initialization + self.execution + self.finalization + self.rejected_runs
+    }
+}
+
+/// Everything produced by a successful OS run.
+#[derive(Debug)]
+pub struct OsRunResult {
+    pub pie: CairoPie,
+    /// The output of the OS, decoded from the output builtin segment.
+    pub os_output: StarknetOsOutput,
+    /// Steps, memory holes and builtin instances used by the run.
+    pub execution_resources: ExecutionResources,
+    /// The layout the OS ran in.
+    pub layout: LayoutName,
+    /// The layouts tried before `layout` and why they were rejected, with automatic layout selection.
+    pub rejected_layouts: Vec<RejectedLayout>,
+    pub timings: OsRunTimings,
+    /// Transactions and syscalls executed by the OS, with the resources they used.
+    pub os_log: Vec<OsLogEvent>,
+    /// Steps and builtins used by each transaction and call.
+    pub resource_report: ResourceReport,
+}
+
+/// Everything produced by a successful proof-mode OS run, see [`crate::run_os_proof_mode`].
+#[derive(Debug)]
+pub struct OsProofRunResult {
+    pub os_output: StarknetOsOutput,
+    pub execution_resources: ExecutionResources,
+    pub layout: LayoutName,
+    pub rejected_layouts: Vec<RejectedLayout>,
+    pub timings: OsRunTimings,
+    pub os_log: Vec<OsLogEvent>,
+    pub resource_report: ResourceReport,
+    /// Where the trace, memory and AIR inputs were written.
+    pub prover_input: ProverInputPaths,
+}
@@ -0,0 +1,75 @@
+use crate::state_utils::compile_contract_class;
+use crate::ProveBlockError;
+use arcane_os::config::STORED_BLOCK_HASH_BUFFER;
+use arcane_os_type::compiled_class::GenericCompiledClass;
+use rpc_replay::rpc_state_reader::ClassCache;
+use starknet::core::types::ContractClass;
+use starknet_types_core::felt::Felt;
+use std::cell::RefCell;
+use std::collections::HashMap;
+
+/// Data that stays valid from one block to the next, kept around when proving several blocks.
+///
+/// Classes are immutable once declared, so they can be cached by class hash forever.
+#[derive(Default)]
+pub struct ProverCache {
+    /// Classes fetched from the RPC node, by class hash.
+    classes: HashMap<Felt, ContractClass>,
+    /// Compiled classes, by class hash.
+    compiled_classes: RefCell<HashMap<Felt, GenericCompiledClass>>,
+    /// Hashes of the blocks fetched recently, by block number, see
+    /// [`ProverCache::insert_block_hash`].
+    block_hashes: HashMap<u64, Felt>,
+    /// Classes compiled by the RPC state readers, shared between blocks.
+    rpc_class_cache: ClassCache,
+}
+
+impl ProverCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn get_class(&self, class_hash: &Felt) -> Option<&ContractClass> {
+        self.classes.get(class_hash)
+    }
+
+    pub fn insert_class(&mut self, class_hash: Felt, contract_class: ContractClass) {
+        self.classes.insert(class_hash, contract_class);
+    }
+
+    /// Returns the compiled version of a class, compiling it on first use.
+    pub fn get_compiled_class(
+        &self,
+        class_hash: Felt,
+        contract_class: &ContractClass,
+    ) -> Result<GenericCompiledClass, ProveBlockError> {
+        if let Some(compiled_class) = self.compiled_classes.borrow().get(&class_hash) {
+            return Ok(compiled_class.clone());
+        }
+
+        let compiled_class = compile_contract_class(contract_class.clone())?;
+        self.compiled_classes
+            .borrow_mut()
+            .insert(class_hash, compiled_class.clone());
+
+        Ok(compiled_class)
+    }
+
+    pub fn rpc_class_cache(&self) -> ClassCache {
+        self.rpc_class_cache.clone()
+    }
+
+    pub fn get_block_hash(&self, block_number: u64) -> Option<Felt> {
+        self.block_hashes.get(&block_number).copied()
+    }
+
+    /// Caches the hash of a block, and forgets the hashes of the blocks too far from it to be
+    /// used with it: a block only refers to the previous block and to the block
+    /// `STORED_BLOCK_HASH_BUFFER` blocks before it.
+    pub fn insert_block_hash(&mut self, block_number: u64, block_hash: Felt) {
+        self.block_hashes.retain(|cached_block_number, _| {
+            cached_block_number.abs_diff(block_number) <= STORED_BLOCK_HASH_BUFFER + 1
+        });
+        self.block_hashes.insert(block_number, block_hash);
+    }
+}
@@ -0,0 +1,426 @@
+use blockifier::execution::call_info::CallInfo;
+use blockifier::state::cached_state::CommitmentStateDiff;
+use blockifier::transaction::objects::TransactionExecutionInfo;
+use serde::Serialize;
+use starknet::core::types::{
+    ExecuteInvocation, ExecutionResult, FunctionInvocation, StateDiff, TransactionReceipt,
+    TransactionTrace, TransactionTraceWithHash,
+};
+use starknet_types_core::felt::Felt;
+use std::collections::BTreeMap;
+
+/// An event, as emitted by a call.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ReportedEvent {
+    pub keys: Vec<Felt>,
+    pub data: Vec<Felt>,
+}
+
+/// An L2 to L1 message, as sent by a call.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ReportedMessage {
+    pub to_address: Felt,
+    pub payload: Vec<Felt>,
+}
+
+/// A difference between the re-execution of a transaction and what the RPC node reports.
+///
+/// `expected` is always the value of the node, `actual` the value of blockifier.
+/// Calls are identified by their path in the call tree, e.g. `execute/0/2` is the third
+/// inner call of the first inner call of the execute entry point.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Divergence {
+    /// The transaction reverted on one side only. Holds the revert reasons.
+    RevertStatus {
+        expected: Option<String>,
+        actual: Option<String>,
+    },
+    /// The transaction reverted on both sides, for different reasons.
+    RevertReason {
+        expected: String,
+        actual: String,
+    },
+    ActualFee {
+        expected: Felt,
+        actual: Felt,
+    },
+    /// The inner calls of a call target different contracts.
+    InnerCalls {
+        call: String,
+        expected: Vec<Felt>,
+        actual: Vec<Felt>,
+    },
+    Events {
+        call: String,
+        contract_address: Felt,
+        expected: Vec<ReportedEvent>,
+        actual: Vec<ReportedEvent>,
+    },
+    Messages {
+        call: String,
+        contract_address: Felt,
+        expected: Vec<ReportedMessage>,
+        actual: Vec<ReportedMessage>,
+    },
+    /// A storage write made by the transaction. `None` means the key was not written.
+    StorageWrite {
+        contract_address: Felt,
+        key: Felt,
+        expected: Option<Felt>,
+        actual: Option<Felt>,
+    },
+    /// A nonce update made by the transaction. `None` means the nonce was not updated.
+    Nonce {
+        contract_address: Felt,
+        expected: Option<Felt>,
+        actual: Option<Felt>,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct TransactionDivergences {
+    pub index: usize,
+    pub tx_hash: Felt,
+    pub divergences: Vec<Divergence>,
+}
+
+/// Every divergence found in a block. Transactions that match the node are left out.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct DivergenceReport {
+    pub block_number: u64,
+    pub transactions: Vec<TransactionDivergences>,
+}
+
+impl DivergenceReport {
+    pub fn is_empty(&self) -> bool {
+        self.transactions.is_empty()
+    }
+
+    /// Logs every divergence as a warning.
+    pub fn log(&self) {
+        for tx in &self.transactions {
+            for divergence in &tx.divergences {
+                log::warn!(
+                    "Transaction 0x{:x} (index {}) of block {} diverges from the RPC node: {:?}",
+                    tx.tx_hash,
+                    tx.index,
+                    self.block_number,
+                    divergence
+                );
+            }
+        }
+    }
+}
+
+/// Compares the re-execution of a block with the traces and receipts of the RPC node.
+///
+/// `state_diffs` holds the state diff of each transaction, as returned by
+/// `reexecute_transactions_with_blockifier`.
+pub fn check_divergences(
+    block_number: u64,
+    traces: &[TransactionTraceWithHash],
+    receipts: &[TransactionReceipt],
+    tx_execution_infos: &[TransactionExecutionInfo],
+    state_diffs: &[CommitmentStateDiff],
+) -> DivergenceReport {
+    let mut transactions = Vec::new();
+
+    for (index, (trace, info)) in traces.iter().zip(tx_execution_infos).enumerate() {
+        let mut divergences = Vec::new();
+
+        check_revert(trace, receipts.get(index), info, &mut divergences);
+        if let Some(receipt) = receipts.get(index) {
+            let expected_fee = receipt.actual_fee().amount;
+            let actual_fee = Felt::from(info.actual_fee.0);
+            if expected_fee != actual_fee {
+                divergences.push(Divergence::ActualFee {
+                    expected: expected_fee,
+                    actual: actual_fee,
+                });
+            }
+        }
+        for (call, expected, actual) in entry_points(&trace.trace_root, info) {
+            check_call(call.to_string(), expected, actual, &mut divergences);
+        }
+        if let (Some(expected), Some(actual)) =
+            (trace_state_diff(&trace.trace_root), state_diffs.get(index))
+        {
+            check_state_diff(expected, actual, &mut divergences);
+        }
+
+        if !divergences.is_empty() {
+            transactions.push(TransactionDivergences {
+                index,
+                tx_hash: trace.transaction_hash,
+                divergences,
+            });
+        }
+    }
+
+    DivergenceReport {
+        block_number,
+        transactions,
+    }
+}
+
+fn check_revert(
+    trace: &TransactionTraceWithHash,
+    receipt: Option<&TransactionReceipt>,
+    info: &TransactionExecutionInfo,
+    divergences: &mut Vec<Divergence>,
+) {
+    // Receipts are more precise than traces: only invoke traces report reverts
+    let expected = match receipt.map(|receipt| receipt.execution_result()) {
+        Some(ExecutionResult::Reverted { reason }) => Some(reason.clone()),
+        Some(ExecutionResult::Succeeded) => None,
+        None => match &trace.trace_root {
+            TransactionTrace::Invoke(invoke_trace) => match &invoke_trace.execute_invocation {
+                ExecuteInvocation::Reverted(reverted) => Some(reverted.revert_reason.clone()),
+                ExecuteInvocation::Success(_) => None,
+            },
+            _ => None,
+        },
+    };
+    let actual = info.revert_error.clone();
+
+    match (expected, actual) {
+        (None, None) => {}
+        (Some(expected), Some(actual)) => {
+            if expected != actual {
+                divergences.push(Divergence::RevertReason { expected, actual });
+            }
+        }
+        (expected, actual) => divergences.push(Divergence::RevertStatus { expected, actual }),
+    }
+}
+
+/// Pairs the entry points of the trace with the calls of blockifier.
+fn entry_points<'a>(
+    trace: &'a TransactionTrace,
+    info: &'a TransactionExecutionInfo,
+) -> Vec<(
+    &'static str,
+    Option<&'a FunctionInvocation>,
+    Option<&'a CallInfo>,
+)> {
+    let execute = info.execute_call_info.as_ref();
+    let validate = info.validate_call_info.as_ref();
+    let fee_transfer = info.fee_transfer_call_info.as_ref();
+
+    match trace {
+        TransactionTrace::Invoke(invoke_trace) => {
+            let execute_invocation = match &invoke_trace.execute_invocation {
+                ExecuteInvocation::Success(invocation) => Some(invocation),
+                ExecuteInvocation::Reverted(_) => None,
+            };
+            vec![
+                (
+                    "validate",
+                    invoke_trace.validate_invocation.as_ref(),
+                    validate,
+                ),
+                ("execute", execute_invocation, execute),
+                (
+                    "fee_transfer",
+                    invoke_trace.fee_transfer_invocation.as_ref(),
+                    fee_transfer,
+                ),
+            ]
+        }
+        TransactionTrace::Declare(declare_trace) => vec![
+            (
+                "validate",
+                declare_trace.validate_invocation.as_ref(),
+                validate,
+            ),
+            (
+                "fee_transfer",
+                declare_trace.fee_transfer_invocation.as_ref(),
+                fee_transfer,
+            ),
+        ],
+        TransactionTrace::DeployAccount(deploy_trace) => vec![
+            (
+                "validate",
+                deploy_trace.validate_invocation.as_ref(),
+                validate,
+            ),
+            (
+                "constructor",
+                Some(&deploy_trace.constructor_invocation),
+                execute,
+            ),
+            (
+                "fee_transfer",
+                deploy_trace.fee_transfer_invocation.as_ref(),
+                fee_transfer,
+            ),
+        ],
+        TransactionTrace::L1Handler(l1_handler_trace) => {
+            vec![(
+                "l1_handler",
+                Some(&l1_handler_trace.function_invocation),
+                execute,
+            )]
+        }
+    }
+}
+
+fn trace_state_diff(trace: &TransactionTrace) -> Option<&StateDiff> {
+    match trace {
+        TransactionTrace::Invoke(invoke_trace) => invoke_trace.state_diff.as_ref(),
+        TransactionTrace::Declare(declare_trace) => declare_trace.state_diff.as_ref(),
+        TransactionTrace::DeployAccount(deploy_trace) => deploy_trace.state_diff.as_ref(),
+        TransactionTrace::L1Handler(l1_handler_trace) => l1_handler_trace.state_diff.as_ref(),
+    }
+}
+
+/// Compares the events and messages of a call and of its inner calls.
+///
+/// A call missing on one side is already reported as a revert divergence, or as an
+/// inner calls divergence by the parent call.
+fn check_call(
+    call: String,
+    expected: Option<&FunctionInvocation>,
+    actual: Option<&CallInfo>,
+    divergences: &mut Vec<Divergence>,
+) {
+    let (Some(expected), Some(actual)) = (expected, actual) else {
+        return;
+    };
+    let contract_address = *actual.call.storage_address.key();
+
+    let mut expected_events = expected.events.clone();
+    expected_events.sort_by_key(|event| event.order);
+    let expected_events: Vec<_> = expected_events
+        .into_iter()
+        .map(|event| ReportedEvent {
+            keys: event.keys,
+            data: event.data,
+        })
+        .collect();
+    let mut actual_events = actual.execution.events.clone();
+    actual_events.sort_by_key(|event| event.order);
+    let actual_events: Vec<_> = actual_events
+        .into_iter()
+        .map(|event| ReportedEvent {
+            keys: event.event.keys.into_iter().map(|key| key.0).collect(),
+            data: event.event.data.0,
+        })
+        .collect();
+    if expected_events != actual_events {
+        divergences.push(Divergence::Events {
+            call: call.clone(),
+            contract_address,
+            expected: expected_events,
+            actual: actual_events,
+        });
+    }
+
+    let mut expected_messages = expected.messages.clone();
+    expected_messages.sort_by_key(|message| message.order);
+    let expected_messages: Vec<_> = expected_messages
+        .into_iter()
+        .map(|message| ReportedMessage {
+            to_address: message.to_address,
+            payload: message.payload,
+        })
+        .collect();
+    let mut actual_messages = actual.execution.l2_to_l1_messages.clone();
+    actual_messages.sort_by_key(|message| message.order);
+    let actual_messages: Vec<_> = actual_messages
+        .into_iter()
+        .map(|message| ReportedMessage {
+            to_address: Felt::from_bytes_be_slice(message.message.to_address.0.as_bytes()),
+            payload: message.message.payload.0,
+        })
+        .collect();
+    if expected_messages != actual_messages {
+        divergences.push(Divergence::Messages {
+            call: call.clone(),
+            contract_address,
+            expected: expected_messages,
+            actual: actual_messages,
+        });
+    }
+
+    let expected_callees: Vec<_> = expected
+        .calls
+        .iter()
+        .map(|inner_call| inner_call.contract_address)
+        .collect();
+    let actual_callees: Vec<_> = actual
+        .inner_calls
+        .iter()
+        .map(|inner_call| *inner_call.call.storage_address.key())
+        .collect();
+    if expected_callees != actual_callees {
+        divergences.push(Divergence::InnerCalls {
+            call,
+            expected: expected_callees,
+            actual: actual_callees,
+        });
+        return;
+    }
+
+    for (i, (expected, actual)) in expected.calls.iter().zip(&actual.inner_calls).enumerate() {
+        check_call(
+            format!("{call}/{i}"),
+            Some(expected),
+            Some(actual),
+            divergences,
+        );
+    }
+}
+
+fn check_state_diff(
+    expected: &StateDiff,
+    actual: &CommitmentStateDiff,
+    divergences: &mut Vec<Divergence>,
+) {
+    let mut storage_writes: BTreeMap<(Felt, Felt), (Option<Felt>, Option<Felt>)> = BTreeMap::new();
+    for contract_diff in &expected.storage_diffs {
+        for entry in &contract_diff.storage_entries {
+            storage_writes
+                .entry((contract_diff.address, entry.key))
+                .or_default()
+                .0 = Some(entry.value);
+        }
+    }
+    for (contract_address, storage) in &actual.storage_updates {
+        for (key, value) in storage {
+            storage_writes
+                .entry((*contract_address.key(), *key.key()))
+                .or_default()
+                .1 = Some(*value);
+        }
+    }
+    for ((contract_address, key), (expected, actual)) in storage_writes {
+        if expected != actual {
+            divergences.push(Divergence::StorageWrite {
+                contract_address,
+                key,
+                expected,
+                actual,
+            });
+        }
+    }
+
+    let mut nonces: BTreeMap<Felt, (Option<Felt>, Option<Felt>)> = BTreeMap::new();
+    for nonce_update in &expected.nonces {
+        nonces.entry(nonce_update.contract_address).or_default().0 = Some(nonce_update.nonce);
+    }
+    for (contract_address, nonce) in &actual.address_to_nonce {
+        nonces.entry(*contract_address.key()).or_default().1 = Some(nonce.0);
+    }
+    for (contract_address, (expected, actual)) in nonces {
+        if expected != actual {
+            divergences.push(Divergence::Nonce {
+                contract_address,
+                expected,
+                actual,
+            });
+        }
+    }
+}
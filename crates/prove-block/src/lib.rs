@@ -1,24 +1,44 @@
 #![feature(async_iter_from_iter)]
 
+pub mod cache;
+pub mod divergence;
+pub mod os_output;
+pub mod prover;
 pub mod reexecute;
 pub mod rpc_utils;
 pub mod state_utils;
 pub mod types;
 pub mod utils;
+pub mod witness;
 
+use crate::cache::ProverCache;
+use crate::divergence::{check_divergences, DivergenceReport};
+use crate::os_output::{verify_os_output, StateDiffMismatch};
 use crate::reexecute::{
     format_commitment_facts, reexecute_transactions_with_blockifier, ProverPerContractStorage,
 };
-use crate::rpc_utils::{get_class_proofs, get_storage_proofs};
-use crate::state_utils::get_formatted_state_update;
+use crate::rpc_utils::{
+    get_storage_and_class_proofs, zero_if_contract_not_found, DEFAULT_MAX_CONCURRENT_REQUESTS,
+};
+use crate::state_utils::{
+    fetch_class, fetch_classes, format_state_update, get_class_hashes_to_prove, get_receipts,
+    get_state_update_and_traces,
+};
+use crate::types::starknet_rs_tx_to_internal_tx;
+use crate::utils::{get_accessed_keys_from_traces, get_all_accessed_keys};
+use crate::witness::{
+    BlockWitness, RecordingStateReader, WitnessStateReader, BLOCK_WITNESS_VERSION,
+};
 use arcane_os::config::{StarknetGeneralConfig, StarknetOsConfig, STORED_BLOCK_HASH_BUFFER};
 use arcane_os::crypto::pedersen::PedersenHash;
 use arcane_os::crypto::poseidon::PoseidonHash;
 use arcane_os::error::ArcaneError;
 use arcane_os::execution::helper::{ContractStorageMap, ExecutionHelperWrapper};
+use arcane_os::hints::SnosHintProcessor;
 use arcane_os::io::input::StarknetOsInput;
-use arcane_os::io::output::StarknetOsOutput;
+use arcane_os::layout::LayoutSelection;
 use arcane_os::run_os;
+use arcane_os::run_result::OsRunResult;
 use arcane_os::starknet::business_logic::fact_state::contract_class_object::ContractState;
 use arcane_os::starknet::starknet_storage::CommitmentInfo;
 use arcane_os::starkware_utils::commitment_tree::base_types::Height;
@@ -26,20 +46,28 @@ use arcane_os::starkware_utils::commitment_tree::error::TreeError;
 use arcane_os::starkware_utils::commitment_tree::patricia_tree::patricia_tree::PatriciaTree;
 use arcane_os_type::arcane_core_addons::LegacyContractDecompressionError;
 use arcane_os_type::error::ContractClassError;
+use blockifier::context::BlockContext;
 use blockifier::state::cached_state::CachedState;
-use cairo_vm::vm::runners::cairo_pie::CairoPie;
+use blockifier::transaction::errors::TransactionExecutionError;
+use blockifier::transaction::transaction_execution::Transaction;
 use cairo_vm::Felt252;
 use rpc_client::client::RpcClient;
+use rpc_client::pathfinder::client::ClientError;
 use rpc_client::pathfinder::proofs::{PathfinderClassProof, ProofVerificationError};
-use rpc_replay::block_context::build_block_context;
+use rpc_client::proof_provider::ProofApi;
+use rpc_replay::block_context::{build_block_context, BlockContextError};
+use rpc_replay::network::NetworkProfile;
 use rpc_replay::rpc_state_reader::AsyncRpcStateReader;
-use rpc_replay::transaction::{starknet_rs_to_blockifier, ToBlockifierError};
+use rpc_replay::transaction::{
+    create_class_info, declared_class_hash, starknet_rs_to_blockifier_with_class_info,
+    ToBlockifierError,
+};
 use rpc_replay::utils::FeltConversionError;
-use starknet::core::types::{BlockId, MaybePendingBlockWithTxs};
+use starknet::core::types::{BlockId, MaybePendingBlockWithTxHashes, MaybePendingBlockWithTxs};
+use starknet::core::utils::cairo_short_string_to_felt;
 use starknet::providers::{Provider, ProviderError};
-use starknet_api::core::ChainId;
+use starknet_api::contract_address;
 use starknet_api::StarknetApiError;
-use starknet_core::types::StarknetError;
 use starknet_types_core::felt::Felt;
 use std::async_iter::FromIter;
 use std::collections::HashMap;
@@ -66,136 +94,444 @@ pub enum ProveBlockError {
     ToBlockifierError(#[from] ToBlockifierError),
     #[error("Felt Conversion Error: {0}")]
     FeltConversionError(#[from] FeltConversionError),
+    #[error("IO Error: {0}")]
+    IoError(#[from] std::io::Error),
+    #[error("Serde Error: {0}")]
+    SerdeError(#[from] serde_json::Error),
+    #[error("Unsupported block witness version: {0}")]
+    UnsupportedWitnessVersion(u32),
+    #[error("Incomplete block witness: {0}")]
+    IncompleteWitness(String),
+    #[error("RPC Client Error: {0}")]
+    RpcClientError(#[from] ClientError),
+    #[error("Block {0:?} is still pending")]
+    PendingBlock(BlockId),
+    /// `key` is the contract address for storage proofs and the class hash for class proofs.
+    #[error("Proof verification failed for 0x{key:x}: {error}")]
+    ProofVerificationFailed { key: Felt, error: String },
+    #[error("Block has {transactions} transactions but {traces} traces")]
+    TraceMismatch { transactions: usize, traces: usize },
+    #[error("Transaction 0x{tx_hash:x} (index {index}) failed in blockifier: {error}")]
+    TransactionExecutionFailed {
+        tx_hash: Felt,
+        index: usize,
+        #[source]
+        error: TransactionExecutionError,
+    },
+    #[error("Missing storage proof for contract 0x{0:x}")]
+    MissingStorageProof(Felt),
+    #[error("Storage key 0x{key:x} of contract 0x{contract_address:x} is missing from the block witness")]
+    MissingStorageKey { contract_address: Felt, key: Felt },
+    #[error("The genesis block has no previous block to prove it against")]
+    GenesisBlock,
+    #[error("Block Context Error: {0}")]
+    BlockContextError(#[from] BlockContextError),
+    #[error("Invalid chain id: {0}")]
+    InvalidChainId(String),
+    #[error("RPC node serves chain 0x{actual:x}, expected {expected}")]
+    ChainIdMismatch { expected: String, actual: Felt },
+    #[error("Could not decode the OS output: {0}")]
+    OsOutputDecodingError(String),
+    #[error("OS output does not match the state update of the block: {} mismatches", .0.len())]
+    OsOutputMismatch(Vec<StateDiffMismatch>),
 }
 
 pub async fn prove_block(
     complied_os: &[u8],
     block_number: u64,
     rpc_provider: &str,
-    layout: &str,
+    network: &NetworkProfile,
+    layout: LayoutSelection,
     full_output: bool,
-) -> Result<(CairoPie, StarknetOsOutput), ProveBlockError> {
+) -> Result<OsRunResult, ProveBlockError> {
     let provider = RpcClient::new(rpc_provider);
+    let witness = collect_block_witness(&provider, network, block_number, None).await?;
+
+    prove_block_from_witness(complied_os, &witness, layout, full_output)
+}
+
+/// Runs the OS on a block from a witness, without any network access.
+///
+/// Fails if the state diff output by the OS does not match the state update of the block.
+pub fn prove_block_from_witness(
+    complied_os: &[u8],
+    witness: &BlockWitness,
+    layout: LayoutSelection,
+    full_output: bool,
+) -> Result<OsRunResult, ProveBlockError> {
+    let os_input = build_os_input_from_witness(witness, full_output)?;
+
+    prove_block_from_os_input(complied_os, witness, os_input, layout, full_output)
+}
+
+/// Runs the OS on an input built with [`build_os_input_from_witness`], e.g. after checking
+/// its divergences.
+pub fn prove_block_from_os_input(
+    complied_os: &[u8],
+    witness: &BlockWitness,
+    os_input: BlockOsInput,
+    layout: LayoutSelection,
+    full_output: bool,
+) -> Result<OsRunResult, ProveBlockError> {
+    let result = run_os(
+        complied_os,
+        layout,
+        os_input.os_input,
+        os_input.block_context,
+        os_input.execution_helper,
+        SnosHintProcessor::default(),
+    )?;
+    verify_os_output(&result.os_output, witness, full_output)?;
+
+    Ok(result)
+}
+
+/// Fetches every piece of data required to prove a block.
+///
+/// The transactions are re-executed against the RPC state to find out which storage keys
+/// and classes are accessed, and every value read in the process is recorded.
+///
+/// Proofs are fetched with `proof_api`, or with the best API the node supports if `None`.
+pub async fn collect_block_witness(
+    provider: &RpcClient,
+    network: &NetworkProfile,
+    block_number: u64,
+    proof_api: Option<ProofApi>,
+) -> Result<BlockWitness, ProveBlockError> {
+    let proof_api = match proof_api {
+        Some(proof_api) => proof_api,
+        None => provider.detect_proof_api().await,
+    };
+
+    collect_block_witness_with_cache(
+        provider,
+        network,
+        &mut ProverCache::new(),
+        block_number,
+        proof_api,
+        DEFAULT_MAX_CONCURRENT_REQUESTS,
+    )
+    .await
+}
 
-    let chain_id = provider.starknet_rpc().chain_id().await?.to_string();
+pub(crate) async fn collect_block_witness_with_cache(
+    provider: &RpcClient,
+    network: &NetworkProfile,
+    cache: &mut ProverCache,
+    block_number: u64,
+    proof_api: ProofApi,
+    max_concurrent_requests: usize,
+) -> Result<BlockWitness, ProveBlockError> {
+    check_chain_id(provider, network).await?;
     let block_id = BlockId::Number(block_number);
-    let previous_block_id = BlockId::Number(block_number - 1);
+    let previous_block_number = block_number
+        .checked_sub(1)
+        .ok_or(ProveBlockError::GenesisBlock)?;
+    let previous_block_id = BlockId::Number(previous_block_number);
     let old_block_number = if block_number <= STORED_BLOCK_HASH_BUFFER {
         0
     } else {
         block_number - STORED_BLOCK_HASH_BUFFER
     };
-    let old_block_id = BlockId::Number(old_block_number);
 
     let block_with_txs = match provider.starknet_rpc().get_block_with_txs(block_id).await? {
         MaybePendingBlockWithTxs::Block(block_with_txs) => block_with_txs,
         MaybePendingBlockWithTxs::PendingBlock(_) => {
-            panic!("Block is still pending!")
+            return Err(ProveBlockError::PendingBlock(block_id));
         }
     };
+    cache.insert_block_hash(block_number, block_with_txs.block_hash);
 
-    let previous_block_with_txs = match provider
-        .starknet_rpc()
-        .get_block_with_txs(previous_block_id)
-        .await?
+    let previous_block_hash = get_block_hash(provider, cache, previous_block_number).await?;
+    let old_block_hash = get_block_hash(provider, cache, old_block_number).await?;
+
+    let block_context = build_block_context(network, &block_with_txs)?;
+
+    let (state_update, traces) = get_state_update_and_traces(provider, block_id).await?;
+    let receipts = get_receipts(provider, block_id).await?;
+
+    let mut witness = BlockWitness {
+        version: BLOCK_WITNESS_VERSION,
+        network: network.clone(),
+        block: block_with_txs,
+        previous_block_hash,
+        old_block_number,
+        old_block_hash,
+        state_update,
+        traces,
+        receipts,
+        classes: Default::default(),
+        class_hashes: Default::default(),
+        previous_state: Default::default(),
+        storage_proofs: Default::default(),
+        previous_storage_proofs: Default::default(),
+        class_proofs: Default::default(),
+        previous_class_proofs: Default::default(),
+    };
+
+    fetch_classes(provider, cache, &mut witness, previous_block_id, block_id).await?;
+
+    let txs = convert_transactions(&witness, &block_context)?;
+    // Read the state that the block is known to access in a few batches, instead of one
+    // request at a time during execution
+    let rpc_state_reader = AsyncRpcStateReader::new(provider.clone(), previous_block_id)
+        .with_class_cache(cache.rpc_class_cache());
+    rpc_state_reader
+        .prefetch_accessed_keys(&get_accessed_keys_from_traces(
+            &witness.traces,
+            &witness.state_update.state_diff,
+        ))
+        .await
+        .map_err(ClientError::from)?;
+    let blockifier_state_reader =
+        RecordingStateReader::new(rpc_state_reader, &witness.classes, cache);
+    let mut blockifier_state = CachedState::new(blockifier_state_reader);
+    let (tx_execution_infos, state_diffs) = reexecute_transactions_with_blockifier(
+        &mut blockifier_state,
+        &block_context,
+        witness.old_block_hash,
+        txs,
+    )?;
+    check_divergences(
+        block_number,
+        &witness.traces,
+        &witness.receipts,
+        &tx_execution_infos,
+        &state_diffs,
+    )
+    .log();
+
+    let (recorded_state, requested_classes) = blockifier_state.state.into_recording();
+    witness.previous_state.extend(recorded_state);
+    for class_hash in requested_classes {
+        fetch_class(provider, cache, &mut witness, previous_block_id, class_hash).await?;
+    }
+
+    let accessed_keys_by_address = {
+        let mut keys = get_all_accessed_keys(&tx_execution_infos);
+        keys.entry(contract_address!("0x1"))
+            .or_default()
+            .insert(Felt::from(old_block_number).try_into().unwrap());
+        keys
+    };
+
+    // The OS needs the previous class hash and nonce and the new class hash of every accessed
+    // contract, except for the system contracts
+    let contract_addresses: Vec<Felt> = accessed_keys_by_address
+        .keys()
+        .map(|contract_address| *contract_address.key())
+        .filter(|contract_address| ![Felt::ZERO, Felt::ONE].contains(contract_address))
+        .collect();
+    let previous_class_hashes = provider
+        .get_class_hash_at_batch(previous_block_id, &contract_addresses)
+        .await
+        .map_err(ClientError::from)?;
+    let previous_nonces = provider
+        .get_nonce_batch(previous_block_id, &contract_addresses)
+        .await
+        .map_err(ClientError::from)?;
+    let class_hashes = provider
+        .get_class_hash_at_batch(block_id, &contract_addresses)
+        .await
+        .map_err(ClientError::from)?;
+    for (((contract_address, previous_class_hash), previous_nonce), class_hash) in
+        contract_addresses
+            .into_iter()
+            .zip(previous_class_hashes)
+            .zip(previous_nonces)
+            .zip(class_hashes)
     {
-        MaybePendingBlockWithTxs::Block(block_with_txs) => block_with_txs,
-        MaybePendingBlockWithTxs::PendingBlock(_) => {
-            panic!("Block is still pending!")
-        }
+        witness.previous_state.class_hashes.insert(
+            contract_address,
+            zero_if_contract_not_found(previous_class_hash)?,
+        );
+        witness.previous_state.nonces.insert(
+            contract_address,
+            zero_if_contract_not_found(previous_nonce)?,
+        );
+        witness.class_hashes.insert(contract_address, class_hash?);
+    }
+
+    // The OS reads the previous value of every accessed key. Most of them were already
+    // read by blockifier, fetch the others.
+    let missing_storage_slots: Vec<(Felt, Felt)> = accessed_keys_by_address
+        .iter()
+        .flat_map(|(contract_address, storage_keys)| {
+            storage_keys
+                .iter()
+                .map(|storage_key| (*contract_address.key(), *storage_key.key()))
+        })
+        .filter(|(contract_address, key)| {
+            witness
+                .previous_state
+                .get_storage_at(*contract_address, *key)
+                .is_none()
+        })
+        .collect();
+    let values = provider
+        .get_storage_at_batch(previous_block_id, &missing_storage_slots)
+        .await
+        .map_err(ClientError::from)?;
+    for ((contract_address, key), value) in missing_storage_slots.into_iter().zip(values) {
+        witness
+            .previous_state
+            .storage
+            .entry(contract_address)
+            .or_default()
+            .insert(key, zero_if_contract_not_found(value)?);
+    }
+
+    // Query the storage proofs of each accessed contract and the class proofs together,
+    // at the block and at the previous block
+    let class_hashes = get_class_hashes_to_prove(&witness);
+    // TODO: we fetch proofs here for block-1, but we probably also need to fetch at the current
+    //       block, likely for contracts that are deployed in this block
+    log::info!("Fetching proofs with the {} API", proof_api);
+    let proof_provider = provider.proof_provider(proof_api);
+    let (storage_proofs, class_proofs) = get_storage_and_class_proofs(
+        proof_provider,
+        block_number,
+        &accessed_keys_by_address,
+        &class_hashes,
+        max_concurrent_requests,
+    )
+    .await?;
+    let (previous_storage_proofs, previous_class_proofs) = get_storage_and_class_proofs(
+        proof_provider,
+        previous_block_number,
+        &accessed_keys_by_address,
+        &class_hashes,
+        max_concurrent_requests,
+    )
+    .await?;
+    witness.storage_proofs = storage_proofs;
+    witness.class_proofs = class_proofs;
+    witness.previous_storage_proofs = previous_storage_proofs;
+    witness.previous_class_proofs = previous_class_proofs;
+
+    Ok(witness)
+}
+
+/// Makes sure the RPC node serves the network of the profile, the OS output would be
+/// meaningless otherwise.
+async fn check_chain_id(
+    provider: &RpcClient,
+    network: &NetworkProfile,
+) -> Result<(), ProveBlockError> {
+    let chain_id = provider.starknet_rpc().chain_id().await?;
+    let expected_chain_id = chain_id_to_felt(&network.chain_id.to_string())?;
+    if chain_id != expected_chain_id {
+        return Err(ProveBlockError::ChainIdMismatch {
+            expected: network.chain_id.to_string(),
+            actual: chain_id,
+        });
+    }
+
+    Ok(())
+}
+
+/// Converts a chain id to the felt returned by `starknet_chainId`. Chain ids are either
+/// short strings such as `SN_MAIN`, or already encoded as hex such as `0x534e5f4d41494e`.
+fn chain_id_to_felt(chain_id: &str) -> Result<Felt, ProveBlockError> {
+    let chain_id = if chain_id.starts_with("0x") {
+        Felt::from_hex(chain_id).map_err(|e| e.to_string())
+    } else {
+        cairo_short_string_to_felt(chain_id).map_err(|e| e.to_string())
     };
 
-    let old_block_with_txs_hashes = match provider
+    chain_id.map_err(ProveBlockError::InvalidChainId)
+}
+
+/// Returns the hash of a block, from the cache if it was already fetched.
+async fn get_block_hash(
+    provider: &RpcClient,
+    cache: &mut ProverCache,
+    block_number: u64,
+) -> Result<Felt, ProveBlockError> {
+    if let Some(block_hash) = cache.get_block_hash(block_number) {
+        return Ok(block_hash);
+    }
+
+    let block_id = BlockId::Number(block_number);
+    let block_hash = match provider
         .starknet_rpc()
-        .get_block_with_tx_hashes(old_block_id)
+        .get_block_with_tx_hashes(block_id)
         .await?
     {
-        MaybePendingBlockWithTxs::Block(block_with_txs) => block_with_txs,
-        MaybePendingBlockWithTxs::PendingBlock(_) => {
-            panic!("Block is still pending!")
+        MaybePendingBlockWithTxHashes::Block(block) => block.block_hash,
+        MaybePendingBlockWithTxHashes::PendingBlock(_) => {
+            return Err(ProveBlockError::PendingBlock(block_id));
         }
     };
-    let old_block_hash = old_block_with_txs_hashes.block_hash;
+    cache.insert_block_hash(block_number, block_hash);
 
-    let block_context = build_block_context(chain_id.clone(), &block_with_txs)?;
+    Ok(block_hash)
+}
 
-    let (processed_state_update, traces) =
-        get_formatted_state_update(&provider, previous_block_id, block_id).await?;
-    let class_hash_to_compiled_class_hash =
-        processed_state_update.class_hash_to_compiled_class_hash;
+/// Everything the OS needs to run on a block.
+pub struct BlockOsInput {
+    pub os_input: Rc<StarknetOsInput>,
+    pub execution_helper: ExecutionHelperWrapper<ProverPerContractStorage>,
+    pub block_context: BlockContext,
+    /// Differences between the re-execution the input is built from and the traces and
+    /// receipts of the RPC node.
+    pub divergences: DivergenceReport,
+}
 
-    let blockifier_state_reader = AsyncRpcStateReader::new(provider.clone(), previous_block_id);
-    let mut blockifier_state = CachedState::new(blockifier_state_reader);
+/// Rebuilds the OS input and the execution helper of a block from its witness alone.
+///
+/// The transactions are re-executed with blockifier against the state recorded in the witness.
+pub fn build_os_input_from_witness(
+    witness: &BlockWitness,
+    full_output: bool,
+) -> Result<BlockOsInput, ProveBlockError> {
+    build_os_input_with_cache(witness, &ProverCache::new(), full_output)
+}
 
-    assert_eq!(
-        block_with_txs.transactions.len(),
-        traces.len(),
-        "Transactions and traces must have the same length"
-    );
+pub(crate) fn build_os_input_with_cache(
+    witness: &BlockWitness,
+    cache: &ProverCache,
+    full_output: bool,
+) -> Result<BlockOsInput, ProveBlockError> {
+    let block_context = build_block_context(&witness.network, &witness.block)?;
 
-    let mut txs = Vec::new();
-    for (tx, trace) in block_with_txs.transactions.iter().zip(traces.iter()) {
-        let transaction = starknet_rs_to_blockifier(
-            tx,
-            trace,
-            &block_context.block_info().gas_prices,
-            &provider,
-            block_number,
-        )
-        .await?;
-        txs.push(transaction);
-    }
+    let processed_state_update = format_state_update(witness, cache)?;
+    let class_hash_to_compiled_class_hash =
+        processed_state_update.class_hash_to_compiled_class_hash;
 
-    let tx_execution_infos = reexecute_transactions_with_blockifier(
+    let txs = convert_transactions(witness, &block_context)?;
+    let mut blockifier_state = CachedState::new(WitnessStateReader::new(witness, cache));
+    let (tx_execution_infos, state_diffs) = reexecute_transactions_with_blockifier(
         &mut blockifier_state,
         &block_context,
-        old_block_hash,
+        witness.old_block_hash,
         txs,
     )?;
-
-    let storage_proofs = get_storage_proofs(
-        &provider,
-        block_number,
-        &tx_execution_infos,
-        Felt::from(old_block_number),
-    )
-    .await
-    .expect("Failed to fetch storage proofs");
-
-    let previous_storage_proofs = get_storage_proofs(
-        &provider,
-        block_number - 1,
+    let divergences = check_divergences(
+        witness.block_number(),
+        &witness.traces,
+        &witness.receipts,
         &tx_execution_infos,
-        Felt::from(old_block_number),
-    )
-    .await
-    .expect("Failed to fetch storage proofs");
-
-    let default_general_config = StarknetGeneralConfig::default();
+        &state_diffs,
+    );
 
-    let general_config = StarknetGeneralConfig {
-        starknet_os_config: StarknetOsConfig {
-            chain_id: ChainId::Other(chain_id),
-            fee_token_address: block_context
-                .chain_info()
-                .fee_token_addresses
-                .strk_fee_token_address,
-            deprecated_fee_token_address: block_context
-                .chain_info()
-                .fee_token_addresses
-                .eth_fee_token_address,
-        },
-        ..default_general_config
-    };
+    let general_config = general_config(&witness.network);
+    let accessed_keys_by_address = get_all_accessed_keys(&tx_execution_infos);
 
     let mut contract_states = HashMap::new();
     let mut contract_storages = ContractStorageMap::new();
     let mut contract_address_to_class_hash = HashMap::new();
 
-    for (contract_address, storage_proof) in storage_proofs.clone() {
-        let previous_storage_proof = previous_storage_proofs
+    for (contract_address, storage_proof) in witness.storage_proofs.clone() {
+        let accessed_keys: Vec<Felt> = accessed_keys_by_address
+            .iter()
+            .find(|(address, _)| *address.key() == contract_address)
+            .map(|(_, keys)| keys.iter().map(|key| *key.key()).collect())
+            .unwrap_or_default();
+        let previous_storage_proof = witness
+            .previous_storage_proofs
             .get(&contract_address)
-            .expect("failed to find previous storage proof");
+            .ok_or(ProveBlockError::MissingStorageProof(contract_address))?;
         let contract_storage_root = previous_storage_proof
             .contract_data
             .as_ref()
@@ -215,12 +551,16 @@ pub async fn prove_block(
         };
 
         let contract_storage = ProverPerContractStorage::new(
-            provider.clone(),
-            previous_block_id,
             Felt252::from(contract_address),
             previous_tree.root.into(),
             storage_proof,
             previous_storage_proof.clone(),
+            witness
+                .previous_state
+                .storage
+                .get(&contract_address)
+                .unwrap_or(&HashMap::new()),
+            &accessed_keys,
         )?;
         contract_storages.insert(Felt252::from(contract_address), contract_storage);
 
@@ -228,34 +568,18 @@ pub async fn prove_block(
             if [Felt252::ZERO, Felt252::ONE].contains(&Felt252::from(contract_address)) {
                 (Felt252::ZERO, Felt252::ZERO)
             } else {
-                let previous_class_hash = match provider
-                    .starknet_rpc()
-                    .get_class_hash_at(previous_block_id, contract_address)
-                    .await
-                {
-                    Ok(class_hash) => Ok(class_hash),
-                    Err(ProviderError::StarknetError(StarknetError::ContractNotFound)) => {
-                        Ok(Felt::ZERO)
-                    }
-                    Err(e) => Err(e),
-                }?;
-
-                let previous_nonce = match provider
-                    .starknet_rpc()
-                    .get_nonce(previous_block_id, contract_address)
-                    .await
-                {
-                    Ok(nonce) => Ok(nonce),
-                    Err(ProviderError::StarknetError(StarknetError::ContractNotFound)) => {
-                        Ok(Felt::ZERO)
-                    }
-                    Err(e) => Err(e),
-                }?;
-
-                let class_hash = provider
-                    .starknet_rpc()
-                    .get_class_hash_at(block_id, contract_address)
-                    .await?;
+                let previous_class_hash = get_from_witness(
+                    &witness.previous_state.class_hashes,
+                    contract_address,
+                    "previous class hash",
+                )?;
+                let previous_nonce = get_from_witness(
+                    &witness.previous_state.nonces,
+                    contract_address,
+                    "previous nonce",
+                )?;
+                let class_hash =
+                    get_from_witness(&witness.class_hashes, contract_address, "class hash")?;
                 contract_address_to_class_hash.insert(contract_address, class_hash);
 
                 (
@@ -281,17 +605,6 @@ pub async fn prove_block(
         .map(|(class_hash, component_hashes)| (class_hash, component_hashes.to_vec()))
         .collect();
 
-    // query storage proofs for each accessed contract
-    let class_hashes: Vec<&Felt> = class_hash_to_compiled_class_hash.keys().collect();
-    // TODO: we fetch proofs here for block-1, but we probably also need to fetch at the current
-    //       block, likely for contracts that are deployed in this block
-    let class_proofs = get_class_proofs(&provider, block_number, &class_hashes[..])
-        .await
-        .expect("Failed to fetch class proofs");
-    let previous_class_proofs = get_class_proofs(&provider, block_number - 1, &class_hashes[..])
-        .await
-        .expect("Failed to fetch previous class proofs");
-
     let visited_pcs: HashMap<Felt, Vec<Felt252>> = blockifier_state
         .visited_pcs
         .iter()
@@ -308,12 +621,14 @@ pub async fn prove_block(
         .collect();
 
     // We can extract data from any storage proof, use the one of the block hash contract
-    let block_hash_storage_proof = storage_proofs
+    let block_hash_storage_proof = witness
+        .storage_proofs
         .get(&Felt::ONE)
-        .expect("there should be a storage proof for the block hash contract");
-    let previous_block_hash_storage_proof = previous_storage_proofs
+        .ok_or(ProveBlockError::MissingStorageProof(Felt::ONE))?;
+    let previous_block_hash_storage_proof = witness
+        .previous_storage_proofs
         .get(&Felt::ONE)
-        .expect("there should be a previous storage proof for the block hash contract");
+        .ok_or(ProveBlockError::MissingStorageProof(Felt::ONE))?;
 
     // The root of the class commitment tree for previous and current block
     // Using requested storage proof instead of getting them from class proofs
@@ -337,13 +652,15 @@ pub async fn prove_block(
         None => Felt::ZERO,
     };
 
-    let previous_contract_proofs: Vec<_> = previous_storage_proofs
+    let previous_contract_proofs: Vec<_> = witness
+        .previous_storage_proofs
         .values()
         .map(|proof| proof.contract_proof.clone())
         .collect();
     let previous_state_commitment_facts =
         format_commitment_facts::<PedersenHash>(&previous_contract_proofs);
-    let current_contract_proofs: Vec<_> = storage_proofs
+    let current_contract_proofs: Vec<_> = witness
+        .storage_proofs
         .values()
         .map(|proof| proof.contract_proof.clone())
         .collect();
@@ -363,11 +680,19 @@ pub async fn prove_block(
     };
 
     let contract_class_commitment_info = compute_class_commitment(
-        &previous_class_proofs,
-        &class_proofs,
+        &witness.previous_class_proofs,
+        &witness.class_proofs,
         previous_root,
         updated_root,
-    );
+    )?;
+
+    let transactions: Vec<_> = witness
+        .block
+        .transactions
+        .iter()
+        .cloned()
+        .map(starknet_rs_tx_to_internal_tx)
+        .collect();
 
     let os_input = Rc::new(StarknetOsInput {
         contract_state_commitment_info,
@@ -381,8 +706,8 @@ pub async fn prove_block(
         general_config,
         transactions,
         declared_class_hash_to_component_hashes: declared_class_hash_component_hashes,
-        new_block_hash: block_with_txs.block_hash,
-        prev_block_hash: previous_block_with_txs.block_hash,
+        new_block_hash: witness.block.block_hash,
+        prev_block_hash: witness.previous_block_hash,
         full_output,
     });
     let execution_helper = ExecutionHelperWrapper::<ProverPerContractStorage>::new(
@@ -391,18 +716,75 @@ pub async fn prove_block(
         &block_context,
         Some(os_input.clone()),
         (
-            Felt252::from(old_block_number),
-            Felt252::from(old_block_hash),
+            Felt252::from(witness.old_block_number),
+            Felt252::from(witness.old_block_hash),
         ),
     );
 
-    Ok(run_os(
-        complied_os,
-        layout,
+    Ok(BlockOsInput {
         os_input,
-        block_context,
         execution_helper,
-    )?)
+        block_context,
+        divergences,
+    })
+}
+
+/// Converts the transactions of the block to blockifier transactions, using the classes
+/// recorded in the witness for declare transactions.
+fn convert_transactions(
+    witness: &BlockWitness,
+    block_context: &BlockContext,
+) -> Result<Vec<Transaction>, ProveBlockError> {
+    if witness.block.transactions.len() != witness.traces.len() {
+        return Err(ProveBlockError::TraceMismatch {
+            transactions: witness.block.transactions.len(),
+            traces: witness.traces.len(),
+        });
+    }
+
+    let mut txs = Vec::new();
+    for (tx, trace) in witness.block.transactions.iter().zip(witness.traces.iter()) {
+        let class_info = match declared_class_hash(tx) {
+            Some(class_hash) => Some(create_class_info(witness.get_class(class_hash)?.clone())?),
+            None => None,
+        };
+        let transaction = starknet_rs_to_blockifier_with_class_info(
+            tx,
+            trace,
+            &block_context.block_info().gas_prices,
+            class_info,
+        )?;
+        txs.push(transaction);
+    }
+
+    Ok(txs)
+}
+
+fn general_config(network: &NetworkProfile) -> StarknetGeneralConfig {
+    let default_general_config = StarknetGeneralConfig::default();
+
+    StarknetGeneralConfig {
+        starknet_os_config: StarknetOsConfig {
+            chain_id: network.chain_id.clone(),
+            fee_token_address: network.strk_fee_token_address,
+            deprecated_fee_token_address: network.eth_fee_token_address,
+        },
+        sequencer_address: network.sequencer_address,
+        ..default_general_config
+    }
+}
+
+fn get_from_witness(
+    values: &HashMap<Felt, Felt>,
+    contract_address: Felt,
+    what: &str,
+) -> Result<Felt, ProveBlockError> {
+    values.get(&contract_address).copied().ok_or_else(|| {
+        ProveBlockError::IncompleteWitness(format!(
+            "missing {} of contract 0x{:x}",
+            what, contract_address
+        ))
+    })
 }
 
 fn compute_class_commitment(
@@ -410,12 +792,17 @@ fn compute_class_commitment(
     class_proofs: &HashMap<Felt252, PathfinderClassProof>,
     previous_root: Felt,
     updated_root: Felt,
-) -> CommitmentInfo {
+) -> Result<CommitmentInfo, ProveBlockError> {
     for (class_hash, previous_class_proof) in previous_class_proofs {
         if let Err(e) = previous_class_proof.verify(*class_hash) {
             match e {
                 ProofVerificationError::NonExistenceProof { .. } => {}
-                _ => panic!("Previous class proof verification failed"),
+                _ => {
+                    return Err(ProveBlockError::ProofVerificationFailed {
+                        key: *class_hash,
+                        error: e.to_string(),
+                    })
+                }
             }
         }
     }
@@ -424,7 +811,12 @@ fn compute_class_commitment(
         if let Err(e) = class_proof.verify(*class_hash) {
             match e {
                 ProofVerificationError::NonExistenceProof { .. } => {}
-                _ => panic!("Current class proof verification failed"),
+                _ => {
+                    return Err(ProveBlockError::ProofVerificationFailed {
+                        key: *class_hash,
+                        error: e.to_string(),
+                    })
+                }
             }
         }
     }
@@ -450,10 +842,10 @@ fn compute_class_commitment(
         .chain(current_class_commitment_facts)
         .collect();
 
-    CommitmentInfo {
+    Ok(CommitmentInfo {
         previous_root,
         updated_root,
         tree_height: 251,
         commitment_facts: class_commitment_facts,
-    }
+    })
 }
@@ -0,0 +1,442 @@
+use arcane_os::execution::resource_report::ResourceWeight;
+use arcane_os::layout::LayoutSelection;
+use arcane_os::sharp::pie::encode_pie;
+use clap::Parser;
+use prove_block::witness::BlockWitness;
+use prove_block::{
+    build_os_input_from_witness, collect_block_witness, prove_block_from_os_input, ProveBlockError,
+};
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
+use reqwest::Certificate;
+use rpc_client::client::{RpcClient, RpcClientBuildError, DEFAULT_SPEC_VERSION};
+use rpc_client::disk_cache::{DiskCache, DiskCacheConfig, DEFAULT_MAX_CACHE_SIZE};
+use rpc_client::proof_provider::ProofApi;
+use rpc_client::retry::{RateLimiter, RetryPolicy};
+use rpc_client::transport::{
+    RpcRecording, TlsConfig, TransportConfig, TransportError, TransportMode,
+};
+use rpc_replay::network::NetworkProfile;
+use std::borrow::Cow;
+use std::fs::File;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Exit code for failures not covered by a more specific code (I/O, invalid arguments, ...).
+const EXIT_FAILURE: u8 = 1;
+/// Exit code for failures to fetch data from the RPC node.
+const EXIT_RPC_ERROR: u8 = 2;
+/// Exit code for failures to re-execute the block transactions with blockifier.
+const EXIT_REEXECUTION_ERROR: u8 = 3;
+/// Exit code for failures while running the Starknet OS, or when its output does not match
+/// the state update of the block.
+const EXIT_OS_ERROR: u8 = 4;
+
+#[derive(Parser, Debug)]
+#[command(about = "Runs the Starknet OS on a block and writes the resulting Cairo PIE")]
+struct Args {
+    /// Number of the block to prove.
+    #[arg(long, required_unless_present = "witness")]
+    block_number: Option<u64>,
+
+    /// Base URL of the RPC node, without the `/rpc/<version>` suffix.
+    #[arg(long, required_unless_present_any = ["witness", "replay_rpc", "starknet_rpc_url"])]
+    rpc_provider: Option<String>,
+
+    /// Full URL of the Starknet RPC endpoint, for nodes that do not serve it under
+    /// `<rpc-provider>/rpc/<rpc-spec-version>`.
+    #[arg(long, conflicts_with_all = ["witness", "replay_rpc"])]
+    starknet_rpc_url: Option<String>,
+
+    /// Full URL of the Pathfinder endpoint, `<rpc-provider>/rpc/pathfinder/v0.1` by default.
+    #[arg(long, conflicts_with_all = ["witness", "replay_rpc"])]
+    pathfinder_rpc_url: Option<String>,
+
+    /// Full URL of the RPC v0.8 endpoint used for storage proofs, `<rpc-provider>/rpc/v0_8`
+    /// by default.
+    #[arg(long, conflicts_with_all = ["witness", "replay_rpc"])]
+    storage_proof_rpc_url: Option<String>,
+
+    /// Version of the Starknet RPC spec in the path of the endpoint.
+    #[arg(long, default_value = DEFAULT_SPEC_VERSION)]
+    rpc_spec_version: String,
+
+    /// Header sent with every RPC request, as `Name: value`. Can be repeated.
+    #[arg(long = "rpc-header", value_name = "HEADER")]
+    rpc_headers: Vec<String>,
+
+    /// PEM file of an additional root certificate to trust for the RPC node.
+    #[arg(long)]
+    rpc_ca_cert: Option<PathBuf>,
+
+    /// Accept any TLS certificate from the RPC node. Only meant for local development.
+    #[arg(long)]
+    rpc_accept_invalid_certs: bool,
+
+    /// Save every RPC request and response to this file.
+    #[arg(long, conflicts_with_all = ["witness", "replay_rpc"])]
+    record_rpc: Option<PathBuf>,
+
+    /// Serve RPC requests from a file written with `--record-rpc` instead of the RPC node.
+    #[arg(long, conflicts_with_all = ["witness", "rpc_provider"])]
+    replay_rpc: Option<PathBuf>,
+
+    /// Number of times a failed RPC request is retried, with exponential backoff.
+    #[arg(long, default_value_t = 5)]
+    max_retries: u32,
+
+    /// Timeout of each RPC request, in seconds.
+    #[arg(long, default_value_t = 60)]
+    request_timeout: u64,
+
+    /// Maximum number of requests sent to the RPC node per second.
+    #[arg(long)]
+    max_requests_per_second: Option<u32>,
+
+    /// Cache the immutable RPC responses (classes, blocks accepted on L1, proofs...) in this
+    /// directory, so that proving the same block again does not fetch them again.
+    #[arg(long, conflicts_with_all = ["witness", "replay_rpc"])]
+    rpc_cache_dir: Option<PathBuf>,
+
+    /// Size limit of the RPC cache in MiB, the least recently used responses are evicted
+    /// beyond it.
+    #[arg(long, default_value_t = DEFAULT_MAX_CACHE_SIZE >> 20)]
+    rpc_cache_max_size: u64,
+
+    /// RPC methods used to fetch proofs: `pathfinder` or `storage-proof` (RPC v0.8).
+    /// Detected from the node if not set.
+    #[arg(long, conflicts_with = "witness")]
+    proof_api: Option<ProofApi>,
+
+    /// Network of the block: `mainnet`, `sepolia` or the path to a YAML/JSON network profile.
+    #[arg(long, default_value = "mainnet")]
+    network: String,
+
+    /// Prove the block recorded in this witness file instead of fetching it from the RPC node.
+    #[arg(long, conflicts_with_all = ["block_number", "rpc_provider"])]
+    witness: Option<PathBuf>,
+
+    /// Write the witness of the block to this file before running the OS.
+    #[arg(long, conflicts_with = "witness")]
+    dump_witness: Option<PathBuf>,
+
+    /// Write the differences between the re-execution and the RPC node traces and receipts
+    /// to this JSON file.
+    #[arg(long)]
+    divergence_report: Option<PathBuf>,
+
+    /// Stop before running the OS if the re-execution diverges from the RPC node.
+    #[arg(long)]
+    fail_on_divergence: bool,
+
+    /// Path to the compiled Starknet OS program. Defaults to the OS embedded in the binary when
+    /// built with the `embedded-os` feature.
+    #[arg(long)]
+    compiled_os: Option<PathBuf>,
+
+    /// Cairo layout used to run the OS, or `auto` to use the smallest of `starknet`,
+    /// `starknet_with_keccak` and `dynamic` that fits the block.
+    #[arg(long, default_value = "starknet_with_keccak")]
+    layout: LayoutSelection,
+
+    /// Output the full state diff instead of the compressed one.
+    #[arg(long)]
+    full_output: bool,
+
+    /// Where to write the Cairo PIE zip.
+    #[arg(long, default_value = "cairo_pie.zip")]
+    pie_output: PathBuf,
+
+    /// Where to write the JSON dump of the OS output.
+    #[arg(long, default_value = "os_output.json")]
+    os_output: PathBuf,
+
+    /// Write the steps and builtins used by each transaction and call of the block to this
+    /// JSON file.
+    #[arg(long)]
+    resource_report: Option<PathBuf>,
+
+    /// Write the steps used by each transaction and call of the block to this file, as folded
+    /// stacks for flamegraph tools.
+    #[arg(long)]
+    resource_flamegraph: Option<PathBuf>,
+}
+
+fn exit_code(error: &ProveBlockError) -> u8 {
+    match error {
+        ProveBlockError::RpcError(_)
+        | ProveBlockError::RpcClientError(_)
+        | ProveBlockError::PendingBlock(_) => EXIT_RPC_ERROR,
+        ProveBlockError::ReExecutionError(_)
+        | ProveBlockError::ToBlockifierError(_)
+        | ProveBlockError::TransactionExecutionFailed { .. } => EXIT_REEXECUTION_ERROR,
+        ProveBlockError::ArcaneError(_)
+        | ProveBlockError::OsOutputDecodingError(_)
+        | ProveBlockError::OsOutputMismatch(_) => EXIT_OS_ERROR,
+        _ => EXIT_FAILURE,
+    }
+}
+
+fn rpc_client(args: &Args) -> Result<RpcClient, RpcClientBuildError> {
+    if let Some(path) = &args.replay_rpc {
+        return Ok(RpcClient::replay(Arc::new(RpcRecording::load(path)?)));
+    }
+
+    let transport_mode = match &args.record_rpc {
+        Some(_) => TransportMode::Record(Arc::new(RpcRecording::new())),
+        None => TransportMode::Live,
+    };
+
+    let mut headers = HeaderMap::new();
+    for header in &args.rpc_headers {
+        let invalid_header = || RpcClientBuildError::InvalidHeader(header.clone());
+        let (name, value) = header.split_once(':').ok_or_else(invalid_header)?;
+        let name = HeaderName::from_bytes(name.trim().as_bytes()).map_err(|_| invalid_header())?;
+        let mut value = HeaderValue::from_str(value.trim()).map_err(|_| invalid_header())?;
+        value.set_sensitive(true);
+        headers.insert(name, value);
+    }
+
+    let mut root_certificates = vec![];
+    if let Some(path) = &args.rpc_ca_cert {
+        let pem = std::fs::read(path).map_err(TransportError::from)?;
+        root_certificates.push(Certificate::from_pem(&pem).map_err(TransportError::from)?);
+    }
+
+    let config = TransportConfig {
+        retry_policy: RetryPolicy {
+            max_retries: args.max_retries,
+            ..RetryPolicy::default()
+        },
+        timeout: Some(Duration::from_secs(args.request_timeout)),
+        headers,
+        tls: TlsConfig {
+            root_certificates,
+            accept_invalid_certs: args.rpc_accept_invalid_certs,
+        },
+        rate_limiter: args
+            .max_requests_per_second
+            .map(|max_requests_per_second| Arc::new(RateLimiter::new(max_requests_per_second))),
+        disk_cache: match &args.rpc_cache_dir {
+            Some(directory) => Some(Arc::new(DiskCache::open(
+                DiskCacheConfig::new(directory).with_max_size(args.rpc_cache_max_size << 20),
+            )?)),
+            None => None,
+        },
+    };
+
+    let mut builder = RpcClient::builder()
+        .spec_version(&args.rpc_spec_version)
+        .transport_mode(transport_mode)
+        .config(config);
+    if let Some(rpc_provider) = &args.rpc_provider {
+        builder = builder.base_url(rpc_provider);
+    }
+    if let Some(url) = &args.starknet_rpc_url {
+        builder = builder.starknet_url(url);
+    }
+    if let Some(url) = &args.pathfinder_rpc_url {
+        builder = builder.pathfinder_url(url);
+    }
+    if let Some(url) = &args.storage_proof_rpc_url {
+        builder = builder.storage_proof_url(url);
+    }
+
+    builder.build()
+}
+
+/// Reads the compiled OS from `path`, or falls back to the embedded OS.
+fn read_compiled_os(path: Option<&Path>) -> Result<Cow<'static, [u8]>, String> {
+    match path {
+        Some(path) => std::fs::read(path)
+            .map(Cow::Owned)
+            .map_err(|e| format!("Could not read compiled OS {}: {}", path.display(), e)),
+        #[cfg(feature = "embedded-os")]
+        None => Ok(Cow::Borrowed(arcane_os::program::EMBEDDED_COMPILED_OS)),
+        #[cfg(not(feature = "embedded-os"))]
+        None => Err("--compiled-os is required without the embedded-os feature".to_string()),
+    }
+}
+
+#[tokio::main]
+async fn main() -> ExitCode {
+    env_logger::init();
+    let args = Args::parse();
+
+    let compiled_os = match read_compiled_os(args.compiled_os.as_deref()) {
+        Ok(compiled_os) => compiled_os,
+        Err(e) => {
+            log::error!("{}", e);
+            return ExitCode::from(EXIT_FAILURE);
+        }
+    };
+
+    let witness = match &args.witness {
+        Some(path) => BlockWitness::load(path),
+        None => {
+            let provider = match rpc_client(&args) {
+                Ok(provider) => provider,
+                Err(e) => {
+                    log::error!("Could not create the RPC client: {}", e);
+                    return ExitCode::from(EXIT_FAILURE);
+                }
+            };
+            let network = match NetworkProfile::from_name_or_file(&args.network) {
+                Ok(network) => network,
+                Err(e) => {
+                    log::error!("Could not load network profile {}: {}", args.network, e);
+                    return ExitCode::from(EXIT_FAILURE);
+                }
+            };
+            let witness = collect_block_witness(
+                &provider,
+                &network,
+                args.block_number.unwrap(),
+                args.proof_api,
+            )
+            .await;
+            if let (Some(path), Some(recording)) = (&args.record_rpc, provider.recording()) {
+                if let Err(e) = recording.save(path) {
+                    log::error!("Could not write RPC recording to {}: {}", path.display(), e);
+                    return ExitCode::from(EXIT_FAILURE);
+                }
+                log::info!("RPC recording written to {}", path.display());
+            }
+            witness
+        }
+    };
+    let witness = match witness {
+        Ok(witness) => witness,
+        Err(e) => {
+            log::error!("Failed to get block witness: {}", e);
+            return ExitCode::from(exit_code(&e));
+        }
+    };
+    let block_number = witness.block_number();
+
+    if let Some(path) = &args.dump_witness {
+        if let Err(e) = witness.dump(path) {
+            log::error!("Could not write block witness to {}: {}", path.display(), e);
+            return ExitCode::from(EXIT_FAILURE);
+        }
+        log::info!("Block witness written to {}", path.display());
+    }
+
+    let os_input = match build_os_input_from_witness(&witness, args.full_output) {
+        Ok(os_input) => os_input,
+        Err(e) => {
+            log::error!(
+                "Failed to build the OS input of block {}: {}",
+                block_number,
+                e
+            );
+            return ExitCode::from(exit_code(&e));
+        }
+    };
+
+    let divergences = &os_input.divergences;
+    if let Some(path) = &args.divergence_report {
+        let write_report = File::create(path)
+            .map_err(|e| e.to_string())
+            .and_then(|file| {
+                serde_json::to_writer_pretty(file, divergences).map_err(|e| e.to_string())
+            });
+        if let Err(e) = write_report {
+            log::error!(
+                "Could not write divergence report to {}: {}",
+                path.display(),
+                e
+            );
+            return ExitCode::from(EXIT_FAILURE);
+        }
+        log::info!("Divergence report written to {}", path.display());
+    }
+    if args.fail_on_divergence && !divergences.is_empty() {
+        log::error!(
+            "Re-execution of block {} diverges from the RPC node in {} transactions",
+            block_number,
+            divergences.transactions.len()
+        );
+        return ExitCode::from(EXIT_REEXECUTION_ERROR);
+    }
+
+    let result = match prove_block_from_os_input(
+        &compiled_os,
+        &witness,
+        os_input,
+        args.layout,
+        args.full_output,
+    ) {
+        Ok(result) => result,
+        Err(e) => {
+            log::error!("Failed to prove block {}: {}", block_number, e);
+            return ExitCode::from(exit_code(&e));
+        }
+    };
+    log::info!(
+        "Ran the OS on block {} in the {} layout: {} steps, {} memory holes, {:?}",
+        block_number,
+        result.layout.to_str(),
+        result.execution_resources.n_steps,
+        result.execution_resources.n_memory_holes,
+        result.timings
+    );
+
+    if let Err(e) = encode_pie(result.pie, &args.pie_output) {
+        log::error!(
+            "Could not write Cairo PIE to {}: {}",
+            args.pie_output.display(),
+            e
+        );
+        return ExitCode::from(EXIT_FAILURE);
+    }
+    log::info!("Cairo PIE written to {}", args.pie_output.display());
+
+    let write_os_output = File::create(&args.os_output)
+        .map_err(|e| e.to_string())
+        .and_then(|file| {
+            serde_json::to_writer_pretty(file, &result.os_output).map_err(|e| e.to_string())
+        });
+    if let Err(e) = write_os_output {
+        log::error!(
+            "Could not write OS output to {}: {}",
+            args.os_output.display(),
+            e
+        );
+        return ExitCode::from(EXIT_FAILURE);
+    }
+    log::info!("OS output written to {}", args.os_output.display());
+
+    if let Some(path) = &args.resource_report {
+        let write_report = result
+            .resource_report
+            .to_json()
+            .map_err(|e| e.to_string())
+            .and_then(|report| std::fs::write(path, report).map_err(|e| e.to_string()));
+        if let Err(e) = write_report {
+            log::error!(
+                "Could not write resource report to {}: {}",
+                path.display(),
+                e
+            );
+            return ExitCode::from(EXIT_FAILURE);
+        }
+        log::info!("Resource report written to {}", path.display());
+    }
+
+    if let Some(path) = &args.resource_flamegraph {
+        let folded_stacks = result.resource_report.folded_stacks(ResourceWeight::Steps);
+        if let Err(e) = std::fs::write(path, folded_stacks) {
+            log::error!(
+                "Could not write resource flamegraph to {}: {}",
+                path.display(),
+                e
+            );
+            return ExitCode::from(EXIT_FAILURE);
+        }
+        log::info!("Resource flamegraph written to {}", path.display());
+    }
+
+    ExitCode::SUCCESS
+}
@@ -0,0 +1,412 @@
+use crate::witness::BlockWitness;
+use crate::ProveBlockError;
+use arcane_os::io::output::StarknetOsOutput;
+use num_bigint::BigUint;
+use serde::Serialize;
+use starknet::core::types::StateDiff;
+use starknet_types_core::felt::Felt;
+use std::collections::{BTreeMap, HashMap};
+
+/// Bound of the nonce in the contract header of the OS output.
+const NONCE_BOUND: u128 = 1 << 64;
+/// Bound of the number of storage updates in the contract header of the OS output.
+const N_UPDATES_BOUND: u128 = 1 << 64;
+
+/// The changes made to a contract, as output by the OS.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ContractOutput {
+    pub nonce: Felt,
+    /// New class hash, if the class of the contract changed.
+    pub class_hash: Option<Felt>,
+    /// New storage values by key.
+    pub storage: BTreeMap<Felt, Felt>,
+}
+
+/// The state diff of a block, as output by the OS.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct OsStateDiff {
+    pub contracts: BTreeMap<Felt, ContractOutput>,
+    /// Compiled class hash by class hash, for the Cairo 1 classes declared in the block.
+    pub declared_classes: BTreeMap<Felt, Felt>,
+}
+
+/// A difference between the state diff output by the OS and the state update of the node.
+///
+/// `expected` is the value of the node, `actual` the value of the OS. `None` means the
+/// value is absent from the state diff.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum StateDiffMismatch {
+    Nonce {
+        contract_address: Felt,
+        expected: Felt,
+        actual: Felt,
+    },
+    ClassHash {
+        contract_address: Felt,
+        expected: Option<Felt>,
+        actual: Option<Felt>,
+    },
+    Storage {
+        contract_address: Felt,
+        key: Felt,
+        expected: Option<Felt>,
+        actual: Option<Felt>,
+    },
+    DeclaredClass {
+        class_hash: Felt,
+        expected: Option<Felt>,
+        actual: Option<Felt>,
+    },
+}
+
+/// Reads the felts of an output section one by one.
+struct OutputReader<'a> {
+    section: &'static str,
+    felts: std::slice::Iter<'a, Felt>,
+}
+
+impl<'a> OutputReader<'a> {
+    fn new(section: &'static str, felts: &'a [Felt]) -> Self {
+        Self {
+            section,
+            felts: felts.iter(),
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.felts.len() == 0
+    }
+
+    fn read(&mut self) -> Result<Felt, ProveBlockError> {
+        self.felts.next().copied().ok_or_else(|| {
+            ProveBlockError::OsOutputDecodingError(format!("{} ended unexpectedly", self.section))
+        })
+    }
+}
+
+/// Splits `value` into `value // bound` and `value % bound`.
+fn unpack(value: BigUint, bound: u128) -> (BigUint, u128) {
+    let bound = BigUint::from(bound);
+    let low = u128::try_from(&value % &bound).expect("remainder is below a u128 bound");
+
+    (value / bound, low)
+}
+
+/// Decodes the contract and class changes of the OS output.
+///
+/// With `full_output`, the OS also outputs the previous nonce and class hash of each contract,
+/// the previous value of each storage key and the previous compiled class hash of each class.
+pub fn decode_state_diff(
+    os_output: &StarknetOsOutput,
+    full_output: bool,
+) -> Result<OsStateDiff, ProveBlockError> {
+    let mut state_diff = OsStateDiff::default();
+
+    let mut reader = OutputReader::new("contract state diff", &os_output.state_updates);
+    while !reader.is_empty() {
+        let contract_address = reader.read()?;
+
+        // Header: class_updated * 2^128 + nonce * 2^64 + n_updates
+        let (header, n_updates) = unpack(reader.read()?.to_biguint(), N_UPDATES_BOUND);
+        let (class_updated, nonce) = unpack(header, NONCE_BOUND);
+        let n_updates = n_updates as usize;
+
+        let class_hash = if full_output {
+            let _previous_nonce = reader.read()?;
+            let previous_class_hash = reader.read()?;
+            let class_hash = reader.read()?;
+            (previous_class_hash != class_hash).then_some(class_hash)
+        } else if class_updated == BigUint::from(1u8) {
+            Some(reader.read()?)
+        } else {
+            None
+        };
+
+        let mut storage = BTreeMap::new();
+        for _ in 0..n_updates {
+            let key = reader.read()?;
+            if full_output {
+                let previous_value = reader.read()?;
+                let value = reader.read()?;
+                // Full output lists every accessed key, including the unchanged ones
+                if previous_value != value {
+                    storage.insert(key, value);
+                }
+            } else {
+                storage.insert(key, reader.read()?);
+            }
+        }
+
+        state_diff.contracts.insert(
+            contract_address,
+            ContractOutput {
+                nonce: Felt::from(nonce),
+                class_hash,
+                storage,
+            },
+        );
+    }
+
+    let mut reader = OutputReader::new("class diff", &os_output.contract_class_diff);
+    while !reader.is_empty() {
+        let class_hash = reader.read()?;
+        if full_output {
+            let _previous_compiled_class_hash = reader.read()?;
+        }
+        let compiled_class_hash = reader.read()?;
+        state_diff
+            .declared_classes
+            .insert(class_hash, compiled_class_hash);
+    }
+
+    Ok(state_diff)
+}
+
+/// Compares the state diff of the OS output with the state update of the node.
+///
+/// The nonce of a contract whose nonce did not change is compared with its nonce at the
+/// previous block, when it is in the witness. Cairo 0 classes are not part of the OS output,
+/// so declared classes only cover Cairo 1 classes.
+pub fn compare_state_diffs(
+    os_state_diff: &OsStateDiff,
+    rpc_state_diff: &StateDiff,
+    previous_nonces: &HashMap<Felt, Felt>,
+) -> Vec<StateDiffMismatch> {
+    let mut mismatches = Vec::new();
+
+    let rpc_nonces: BTreeMap<_, _> = rpc_state_diff
+        .nonces
+        .iter()
+        .map(|nonce_update| (nonce_update.contract_address, nonce_update.nonce))
+        .collect();
+    let rpc_class_hashes: BTreeMap<_, _> = rpc_state_diff
+        .deployed_contracts
+        .iter()
+        .map(|deployed| (deployed.address, deployed.class_hash))
+        .chain(
+            rpc_state_diff
+                .replaced_classes
+                .iter()
+                .map(|replaced| (replaced.contract_address, replaced.class_hash)),
+        )
+        .collect();
+    let rpc_storage: BTreeMap<_, BTreeMap<_, _>> = rpc_state_diff
+        .storage_diffs
+        .iter()
+        .map(|contract_diff| {
+            let entries = contract_diff
+                .storage_entries
+                .iter()
+                .map(|entry| (entry.key, entry.value))
+                .collect();
+            (contract_diff.address, entries)
+        })
+        .collect();
+
+    let no_changes = ContractOutput::default();
+    let empty_storage = BTreeMap::new();
+    let mut contract_addresses: Vec<_> = os_state_diff
+        .contracts
+        .keys()
+        .chain(rpc_nonces.keys())
+        .chain(rpc_class_hashes.keys())
+        .chain(rpc_storage.keys())
+        .copied()
+        .collect();
+    contract_addresses.sort();
+    contract_addresses.dedup();
+
+    for contract_address in contract_addresses {
+        let os_contract = os_state_diff.contracts.get(&contract_address);
+        let contract = os_contract.unwrap_or(&no_changes);
+
+        let expected_nonce = rpc_nonces
+            .get(&contract_address)
+            .or_else(|| previous_nonces.get(&contract_address));
+        // A contract missing from the OS output keeps its nonce
+        let actual_nonce = match os_contract {
+            Some(contract) => Some(&contract.nonce),
+            None => previous_nonces.get(&contract_address),
+        };
+        if let (Some(expected), Some(actual)) = (expected_nonce, actual_nonce) {
+            if expected != actual {
+                mismatches.push(StateDiffMismatch::Nonce {
+                    contract_address,
+                    expected: *expected,
+                    actual: *actual,
+                });
+            }
+        }
+
+        let expected_class_hash = rpc_class_hashes.get(&contract_address).copied();
+        if expected_class_hash != contract.class_hash {
+            mismatches.push(StateDiffMismatch::ClassHash {
+                contract_address,
+                expected: expected_class_hash,
+                actual: contract.class_hash,
+            });
+        }
+
+        let expected_storage = rpc_storage.get(&contract_address).unwrap_or(&empty_storage);
+        let mut keys: Vec<_> = expected_storage
+            .keys()
+            .chain(contract.storage.keys())
+            .collect();
+        keys.sort();
+        keys.dedup();
+        for key in keys {
+            let expected = expected_storage.get(key).copied();
+            let actual = contract.storage.get(key).copied();
+            if expected != actual {
+                mismatches.push(StateDiffMismatch::Storage {
+                    contract_address,
+                    key: *key,
+                    expected,
+                    actual,
+                });
+            }
+        }
+    }
+
+    let rpc_declared_classes: BTreeMap<_, _> = rpc_state_diff
+        .declared_classes
+        .iter()
+        .map(|declared| (declared.class_hash, declared.compiled_class_hash))
+        .collect();
+    let mut class_hashes: Vec<_> = rpc_declared_classes
+        .keys()
+        .chain(os_state_diff.declared_classes.keys())
+        .collect();
+    class_hashes.sort();
+    class_hashes.dedup();
+    for class_hash in class_hashes {
+        let expected = rpc_declared_classes.get(class_hash).copied();
+        let actual = os_state_diff.declared_classes.get(class_hash).copied();
+        if expected != actual {
+            mismatches.push(StateDiffMismatch::DeclaredClass {
+                class_hash: *class_hash,
+                expected,
+                actual,
+            });
+        }
+    }
+
+    mismatches
+}
+
+/// Compares the OS output with the state update of the block in the witness.
+///
+/// Fails if the OS output cannot be decoded, or with every mismatch if it does not match
+/// the state update. The mismatches are also logged.
+pub fn verify_os_output(
+    os_output: &StarknetOsOutput,
+    witness: &BlockWitness,
+    full_output: bool,
+) -> Result<(), ProveBlockError> {
+    let os_state_diff = decode_state_diff(os_output, full_output)?;
+    let mismatches = compare_state_diffs(
+        &os_state_diff,
+        &witness.state_update.state_diff,
+        &witness.previous_state.nonces,
+    );
+    if mismatches.is_empty() {
+        return Ok(());
+    }
+
+    for mismatch in &mismatches {
+        log::error!(
+            "OS output of block {} does not match the state update: {:?}",
+            witness.block_number(),
+            mismatch
+        );
+    }
+    Err(ProveBlockError::OsOutputMismatch(mismatches))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use arcane_os::io::output::decode_output;
+    use serde::Deserialize;
+
+    /// OS output of a block in the 0.13.1 format, with the state diff of the same block.
+    #[derive(Deserialize)]
+    struct OsOutputFixture {
+        os_output: Vec<Felt>,
+        state_diff: StateDiff,
+    }
+
+    fn fixture() -> OsOutputFixture {
+        serde_json::from_str(include_str!("../resources/os_output_0_13_1.json")).unwrap()
+    }
+
+    #[test]
+    fn test_decode_state_diff() {
+        let fixture = fixture();
+        let os_output = decode_output(fixture.os_output).unwrap();
+        let state_diff = decode_state_diff(&os_output, false).unwrap();
+
+        assert_eq!(
+            state_diff.contracts[&Felt::ONE],
+            ContractOutput {
+                nonce: Felt::ZERO,
+                class_hash: None,
+                storage: BTreeMap::from([(Felt::from(0x3de), Felt::from(0x9999))]),
+            }
+        );
+        assert_eq!(
+            state_diff.contracts[&Felt::from(0x100)],
+            ContractOutput {
+                nonce: Felt::TWO,
+                class_hash: Some(Felt::from(0xc1a55)),
+                storage: BTreeMap::from([
+                    (Felt::from(0x10), Felt::from(0x64)),
+                    (Felt::from(0x11), Felt::from(0xc8)),
+                ]),
+            }
+        );
+        assert_eq!(
+            state_diff.declared_classes,
+            BTreeMap::from([(Felt::from(0xc1a55), Felt::from(0xc0ffee))])
+        );
+    }
+
+    #[test]
+    fn test_os_output_matches_state_diff() {
+        let fixture = fixture();
+        let os_output = decode_output(fixture.os_output).unwrap();
+        let os_state_diff = decode_state_diff(&os_output, false).unwrap();
+
+        let mismatches = compare_state_diffs(&os_state_diff, &fixture.state_diff, &HashMap::new());
+        assert_eq!(mismatches, vec![]);
+    }
+
+    #[test]
+    fn test_os_output_mismatches() {
+        let mut fixture = fixture();
+        fixture.state_diff.storage_diffs[1].storage_entries[0].value = Felt::from(0x65);
+        fixture.state_diff.nonces.clear();
+        let os_output = decode_output(fixture.os_output).unwrap();
+        let os_state_diff = decode_state_diff(&os_output, false).unwrap();
+
+        let previous_nonces = HashMap::from([(Felt::from(0x100), Felt::ONE)]);
+        let mismatches = compare_state_diffs(&os_state_diff, &fixture.state_diff, &previous_nonces);
+        assert_eq!(
+            mismatches,
+            vec![
+                StateDiffMismatch::Nonce {
+                    contract_address: Felt::from(0x100),
+                    expected: Felt::ONE,
+                    actual: Felt::TWO,
+                },
+                StateDiffMismatch::Storage {
+                    contract_address: Felt::from(0x100),
+                    key: Felt::from(0x10),
+                    expected: Some(Felt::from(0x65)),
+                    actual: Some(Felt::from(0x64)),
+                },
+            ]
+        );
+    }
+}
@@ -0,0 +1,141 @@
+use crate::cache::ProverCache;
+use crate::os_output::verify_os_output;
+use crate::rpc_utils::DEFAULT_MAX_CONCURRENT_REQUESTS;
+use crate::{build_os_input_with_cache, collect_block_witness_with_cache, ProveBlockError};
+use arcane_os::hints::SnosHintProcessorBuilder;
+use arcane_os::layout::LayoutSelection;
+use arcane_os::run_os;
+use arcane_os::run_result::OsRunResult;
+use async_stream::try_stream;
+use futures_util::Stream;
+use rpc_client::client::RpcClient;
+use rpc_client::proof_provider::ProofApi;
+use rpc_replay::network::NetworkProfile;
+use std::ops::Range;
+
+/// Proves blocks one after the other, sharing the RPC client and caches between blocks.
+///
+/// Block N-1 is fetched when proving block N, and consecutive blocks tend to use the same
+/// classes, so proving a range with the same prover saves a lot of RPC calls and compilations.
+pub struct BlockProver {
+    provider: RpcClient,
+    network: NetworkProfile,
+    compiled_os: Vec<u8>,
+    layout: LayoutSelection,
+    full_output: bool,
+    max_concurrent_requests: usize,
+    /// Detected on the first block if not set.
+    proof_api: Option<ProofApi>,
+    hints: SnosHintProcessorBuilder,
+    cache: ProverCache,
+}
+
+impl BlockProver {
+    pub fn new(
+        provider: RpcClient,
+        network: NetworkProfile,
+        compiled_os: Vec<u8>,
+        layout: LayoutSelection,
+        full_output: bool,
+    ) -> Self {
+        Self {
+            provider,
+            network,
+            compiled_os,
+            layout,
+            full_output,
+            max_concurrent_requests: DEFAULT_MAX_CONCURRENT_REQUESTS,
+            proof_api: None,
+            hints: SnosHintProcessorBuilder::default(),
+            cache: ProverCache::new(),
+        }
+    }
+
+    /// Sets how many proof requests can be in flight at the same time.
+    pub fn with_max_concurrent_requests(mut self, max_concurrent_requests: usize) -> Self {
+        self.max_concurrent_requests = max_concurrent_requests;
+        self
+    }
+
+    /// Sets the RPC methods used to fetch proofs instead of detecting them.
+    pub fn with_proof_api(mut self, proof_api: ProofApi) -> Self {
+        self.proof_api = Some(proof_api);
+        self
+    }
+
+    /// Runs the OS with custom hints, for modified versions of the OS.
+    pub fn with_hints(mut self, hints: SnosHintProcessorBuilder) -> Self {
+        self.hints = hints;
+        self
+    }
+
+    pub async fn prove_block(&mut self, block_number: u64) -> Result<OsRunResult, ProveBlockError> {
+        let proof_api = match self.proof_api {
+            Some(proof_api) => proof_api,
+            None => {
+                let proof_api = self.provider.detect_proof_api().await;
+                self.proof_api = Some(proof_api);
+                proof_api
+            }
+        };
+        let witness = collect_block_witness_with_cache(
+            &self.provider,
+            &self.network,
+            &mut self.cache,
+            block_number,
+            proof_api,
+            self.max_concurrent_requests,
+        )
+        .await?;
+        let os_input = build_os_input_with_cache(&witness, &self.cache, self.full_output)?;
+
+        let result = run_os(
+            &self.compiled_os,
+            self.layout,
+            os_input.os_input,
+            os_input.block_context,
+            os_input.execution_helper,
+            self.hints.clone().build(),
+        )?;
+        verify_os_output(&result.os_output, &witness, self.full_output)?;
+
+        Ok(result)
+    }
+
+    /// Proves each block of the range in order. The stream ends after the first error.
+    pub fn prove_blocks(
+        &mut self,
+        blocks: Range<u64>,
+    ) -> impl Stream<Item = Result<(u64, OsRunResult), ProveBlockError>> + '_ {
+        try_stream! {
+            for block_number in blocks {
+                let result = self.prove_block(block_number).await?;
+                yield (block_number, result);
+            }
+        }
+    }
+}
+
+/// Proves a range of blocks, see [`BlockProver`].
+pub fn prove_blocks(
+    complied_os: &[u8],
+    blocks: Range<u64>,
+    rpc_provider: &str,
+    network: &NetworkProfile,
+    layout: LayoutSelection,
+    full_output: bool,
+) -> impl Stream<Item = Result<(u64, OsRunResult), ProveBlockError>> {
+    let mut prover = BlockProver::new(
+        RpcClient::new(rpc_provider),
+        network.clone(),
+        complied_os.to_vec(),
+        layout,
+        full_output,
+    );
+
+    try_stream! {
+        for await result in prover.prove_blocks(blocks) {
+            yield result?;
+        }
+    }
+}
@@ -1,10 +1,10 @@
+use crate::ProveBlockError;
 use arcane_os::config::{DEFAULT_STORAGE_TREE_HEIGHT, STORED_BLOCK_HASH_BUFFER};
 use arcane_os::crypto::pedersen::PedersenHash;
 use arcane_os::starknet::starknet_storage::{
     CommitmentInfo, CommitmentInfoError, PerContractStorage,
 };
 use arcane_os::starkware_utils::commitment_tree::base_types::{Length, NodePath, TreeIndex};
-use arcane_os::starkware_utils::commitment_tree::error::TreeError;
 use arcane_os::starkware_utils::commitment_tree::inner_node_fact::InnerNodeFact;
 use arcane_os::starkware_utils::commitment_tree::patricia_tree::nodes::{
     BinaryNodeFact, EdgeNodeFact,
@@ -13,28 +13,27 @@ use arcane_os::storage::dict_storage::DictStorage;
 use arcane_os::storage::storage::{Fact, HashFunctionType};
 use blockifier::blockifier::block::{pre_process_block, BlockNumberHashPair};
 use blockifier::context::BlockContext;
-use blockifier::state::cached_state::CachedState;
+use blockifier::state::cached_state::{CachedState, CommitmentStateDiff, TransactionalState};
 use blockifier::state::state_api::StateReader;
 use blockifier::transaction::account_transaction::AccountTransaction;
 use blockifier::transaction::objects::TransactionExecutionInfo;
 use blockifier::transaction::transaction_execution::Transaction;
 use blockifier::transaction::transactions::ExecutableTransaction;
 use cairo_vm::Felt252;
-use rpc_client::client::RpcClient;
 use rpc_client::pathfinder::proofs::{ContractData, PathfinderProof, TrieNode};
-use starknet::providers::{Provider, ProviderError};
 use starknet_api::transaction::TransactionHash;
-use starknet_core::types::{BlockId, StarknetError};
 use starknet_types_core::felt::Felt;
 use std::collections::HashMap;
-use std::error::Error;
 
+/// Executes the transactions of a block on top of `state`.
+///
+/// Returns the execution info and the state diff of each transaction.
 pub fn reexecute_transactions_with_blockifier<S: StateReader>(
     state: &mut CachedState<S>,
     block_context: &BlockContext,
     buffer_block_hash: Felt,
     txs: Vec<Transaction>,
-) -> Result<Vec<TransactionExecutionInfo>, Box<dyn Error>> {
+) -> Result<(Vec<TransactionExecutionInfo>, Vec<CommitmentStateDiff>), ProveBlockError> {
     let current_block_number = block_context.block_info().block_number;
     let buffer_block_number_and_hash = if current_block_number.0 >= STORED_BLOCK_HASH_BUFFER {
         Some(BlockNumberHashPair {
@@ -46,43 +45,39 @@ pub fn reexecute_transactions_with_blockifier<S: StateReader>(
     } else {
         None
     };
-    pre_process_block(state, buffer_block_number_and_hash, current_block_number)?;
+    pre_process_block(state, buffer_block_number_and_hash, current_block_number)
+        .map_err(|e| ProveBlockError::ReExecutionError(Box::new(e)))?;
 
     let n_txs = txs.len();
-    let tx_execution_infos = txs
-        .into_iter()
-        .enumerate()
-        .map(|(index, tx)| {
-            let tx_hash = get_tx_hash(&tx);
-            let tx_result = tx.execute(state, block_context, true, true);
-            match tx_result {
-                Err(e) => {
-                    panic!(
-                        "Transaction {:x} ({}/{}) failed in blockifier: {}",
-                        tx_hash.0,
-                        index + 1,
-                        n_txs,
-                        e
-                    );
-                }
-                Ok(info) => {
-                    if info.is_reverted() {
-                        log::warn!(
-                            "Transaction {:x} ({}/{}) reverted: {:?}",
-                            tx_hash.0,
-                            index + 1,
-                            n_txs,
-                            info.revert_error
-                        );
-                        log::warn!("TransactionExecutionInfo: {:?}", info);
-                    }
-                    info
-                }
-            }
-        })
-        .collect();
+    let mut tx_execution_infos = Vec::with_capacity(n_txs);
+    let mut state_diffs = Vec::with_capacity(n_txs);
+    for (index, tx) in txs.into_iter().enumerate() {
+        let tx_hash = get_tx_hash(&tx);
+        // Each transaction runs in its own transactional state to get its own state diff
+        let mut tx_state = TransactionalState::create_transactional(state);
+        let info = tx
+            .execute(&mut tx_state, block_context, true, true)
+            .map_err(|error| ProveBlockError::TransactionExecutionFailed {
+                tx_hash: tx_hash.0,
+                index,
+                error,
+            })?;
+        if info.is_reverted() {
+            log::warn!(
+                "Transaction {:x} ({}/{}) reverted: {:?}",
+                tx_hash.0,
+                index + 1,
+                n_txs,
+                info.revert_error
+            );
+            log::warn!("TransactionExecutionInfo: {:?}", info);
+        }
+        state_diffs.push(tx_state.to_state_diff());
+        tx_state.commit();
+        tx_execution_infos.push(info);
+    }
 
-    Ok(tx_execution_infos)
+    Ok((tx_execution_infos, state_diffs))
 }
 
 fn get_tx_hash(tx: &Transaction) -> TransactionHash {
@@ -144,33 +139,48 @@ pub(crate) fn format_commitment_facts<H: HashFunctionType>(
     facts
 }
 
-pub(crate) struct ProverPerContractStorage {
-    rpc_client: RpcClient,
-    block_id: BlockId,
+pub struct ProverPerContractStorage {
     contract_address: Felt252,
     previous_tree_root: Felt252,
     storage_proof: PathfinderProof,
     previous_storage_proof: PathfinderProof,
+    /// Storage values at the previous block, updated with the writes of the OS. Every key
+    /// accessed by the block is checked to be present when the storage is created.
     ongoing_storage_changes: HashMap<TreeIndex, Felt252>,
 }
 
 impl ProverPerContractStorage {
     pub fn new(
-        rpc_client: RpcClient,
-        block_id: BlockId,
         contract_address: Felt252,
         previous_tree_root: Felt252,
         storage_proof: PathfinderProof,
         previous_storage_proof: PathfinderProof,
-    ) -> Result<Self, TreeError> {
+        previous_storage: &HashMap<Felt, Felt>,
+        accessed_keys: &[Felt],
+    ) -> Result<Self, ProveBlockError> {
+        // The OS reads the previous value of every key accessed by the block, fail now rather
+        // than in the middle of the OS run
+        if let Some(key) = accessed_keys
+            .iter()
+            .find(|key| !previous_storage.contains_key(key))
+        {
+            return Err(ProveBlockError::MissingStorageKey {
+                contract_address,
+                key: *key,
+            });
+        }
+
+        let ongoing_storage_changes = previous_storage
+            .iter()
+            .map(|(key, value)| (key.to_biguint(), Felt252::from(*value)))
+            .collect();
+
         Ok(Self {
-            rpc_client,
-            block_id,
             contract_address,
             previous_tree_root,
             storage_proof,
             previous_storage_proof,
-            ongoing_storage_changes: Default::default(),
+            ongoing_storage_changes,
         })
     }
 }
@@ -209,26 +219,16 @@ impl PerContractStorage for ProverPerContractStorage {
     }
 
     async fn read(&mut self, key: TreeIndex) -> Option<Felt252> {
-        if let Some(value) = self.ongoing_storage_changes.get(&key) {
-            Some(*value)
-        } else {
-            let key_felt = Felt252::from(key.clone());
-            let value = match self
-                .rpc_client
-                .starknet_rpc()
-                .get_storage_at(self.contract_address, key_felt, self.block_id)
-                .await
-            {
-                Ok(value) => Ok(Felt252::from(value)),
-                Err(ProviderError::StarknetError(StarknetError::ContractNotFound)) => {
-                    Ok(Felt252::ZERO)
-                }
-                Err(e) => Err(e),
-            }
-            .unwrap();
-            self.ongoing_storage_changes.insert(key, value);
-            Some(value)
+        let value = self.ongoing_storage_changes.get(&key).copied();
+        if value.is_none() {
+            log::warn!(
+                "Storage key 0x{:x} of contract 0x{:x} is missing from the block witness",
+                Felt252::from(key),
+                self.contract_address
+            );
         }
+
+        value
     }
 
     fn write(&mut self, key: TreeIndex, value: Felt252) {
@@ -1,118 +1,200 @@
-use crate::utils::get_all_accessed_keys;
+use crate::ProveBlockError;
 use arcane_os::config::DEFAULT_STORAGE_TREE_HEIGHT;
 use arcane_os::starkware_utils::commitment_tree::base_types::Height;
-use blockifier::transaction::objects::TransactionExecutionInfo;
 use cairo_vm::Felt252;
+use futures_util::{stream, StreamExt, TryStreamExt};
 use num_bigint::BigInt;
-use rpc_client::client::RpcClient;
-use rpc_client::pathfinder::client::ClientError;
+use rpc_client::batch::MAX_BATCH_SIZE;
 use rpc_client::pathfinder::proofs::{
     ContractData, EdgePath, PathfinderClassProof, PathfinderProof, ProofVerificationError, TrieNode,
 };
-use starknet_api::contract_address;
+use rpc_client::proof_provider::ProofProvider;
+use starknet::providers::ProviderError;
 use starknet_api::core::ContractAddress;
 use starknet_api::state::StorageKey;
+use starknet_core::types::StarknetError;
 use starknet_types_core::felt::Felt;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 
-pub(crate) async fn get_storage_proofs(
-    client: &RpcClient,
-    block_number: u64,
-    tx_execution_infos: &[TransactionExecutionInfo],
-    old_block_number: Felt,
-) -> Result<HashMap<Felt, PathfinderProof>, ClientError> {
-    let accessed_keys_by_address = {
-        let mut keys = get_all_accessed_keys(tx_execution_infos);
-        keys.entry(contract_address!("0x1"))
-            .or_default()
-            .insert(old_block_number.try_into().unwrap());
-        keys
-    };
-
-    let mut storage_proofs = HashMap::new();
-
-    log::info!("Contracts we're fetching proofs for:");
-    for (contract_address, storage_keys) in accessed_keys_by_address {
-        log::info!("    Fetching proof for {}", contract_address.to_string());
-        let contract_address_felt = *contract_address.key();
-        let storage_proof = get_storage_proof_for_contract(
-            client,
-            contract_address,
-            storage_keys.into_iter(),
-            block_number,
-        )
-        .await?;
-        storage_proofs.insert(contract_address_felt, storage_proof);
+/// Maps "ContractNotFound" errors to zero, which is what the OS expects for contracts
+/// that are not deployed (yet).
+pub(crate) fn zero_if_contract_not_found(
+    result: Result<Felt, ProviderError>,
+) -> Result<Felt, ProviderError> {
+    match result {
+        Ok(value) => Ok(value),
+        Err(ProviderError::StarknetError(StarknetError::ContractNotFound)) => Ok(Felt::ZERO),
+        Err(e) => Err(e),
     }
-
-    Ok(storage_proofs)
 }
 
-async fn get_storage_proof_for_contract<KeyIter: Iterator<Item = StorageKey>>(
-    rpc_client: &RpcClient,
-    contract_address: ContractAddress,
-    storage_keys: KeyIter,
-    block_number: u64,
-) -> Result<PathfinderProof, ClientError> {
-    let contract_address_felt = *contract_address.key();
-    let keys: Vec<_> = storage_keys.map(|storage_key| *storage_key.key()).collect();
+/// Default number of proof requests in flight at the same time.
+pub const DEFAULT_MAX_CONCURRENT_REQUESTS: usize = 16;
 
-    let mut storage_proof =
-        fetch_storage_proof_for_contract(rpc_client, contract_address_felt, &keys, block_number)
-            .await?;
+/// Storage proofs by contract address and class proofs by class hash, at a block.
+type BlockProofs = (
+    HashMap<Felt, PathfinderProof>,
+    HashMap<Felt252, PathfinderClassProof>,
+);
 
-    let contract_data = match &storage_proof.contract_data {
-        None => {
-            return Ok(storage_proof);
-        }
-        Some(contract_data) => contract_data,
-    };
-    let additional_keys = verify_storage_proof(contract_data, &keys);
+/// Fetches the storage proofs of the accessed keys of each contract and the proofs of
+/// `class_hashes` at a block, in the same requests when the provider allows it.
+pub(crate) async fn get_storage_and_class_proofs(
+    proof_provider: &dyn ProofProvider,
+    block_number: u64,
+    accessed_keys_by_address: &HashMap<ContractAddress, HashSet<StorageKey>>,
+    class_hashes: &[Felt],
+    max_concurrent_requests: usize,
+) -> Result<BlockProofs, ProveBlockError> {
+    log::info!(
+        "Fetching proofs for {} contracts and {} classes",
+        accessed_keys_by_address.len(),
+        class_hashes.len()
+    );
+    let keys_by_address: Vec<(Felt, Vec<Felt>)> = accessed_keys_by_address
+        .iter()
+        .map(|(contract_address, storage_keys)| {
+            let keys = storage_keys
+                .iter()
+                .map(|storage_key| *storage_key.key())
+                .collect();
+            (*contract_address.key(), keys)
+        })
+        .collect();
+
+    let (mut storage_proofs, class_proofs) = fetch_proofs(
+        proof_provider,
+        block_number,
+        &keys_by_address,
+        class_hashes,
+        max_concurrent_requests,
+    )
+    .await?;
 
     // Fetch additional proofs required to fill gaps in the storage trie that could make
     // the OS crash otherwise.
-    if !additional_keys.is_empty() {
-        let additional_proof = fetch_storage_proof_for_contract(
-            rpc_client,
-            contract_address_felt,
-            &additional_keys,
-            block_number,
-        )
-        .await?;
-
-        storage_proof = merge_storage_proofs(vec![storage_proof, additional_proof]);
+    let mut additional_keys_by_address = vec![];
+    for (contract_address, keys) in &keys_by_address {
+        let storage_proof = storage_proofs
+            .get(contract_address)
+            .ok_or(ProveBlockError::MissingStorageProof(*contract_address))?;
+        if let Some(contract_data) = &storage_proof.contract_data {
+            let additional_keys = verify_storage_proof(*contract_address, contract_data, keys)?;
+            if !additional_keys.is_empty() {
+                additional_keys_by_address.push((*contract_address, additional_keys));
+            }
+        }
+    }
+    let (additional_proofs, _) = fetch_proofs(
+        proof_provider,
+        block_number,
+        &additional_keys_by_address,
+        &[],
+        max_concurrent_requests,
+    )
+    .await?;
+    for (contract_address, additional_proof) in additional_proofs {
+        let storage_proof = storage_proofs
+            .remove(&contract_address)
+            .ok_or(ProveBlockError::MissingStorageProof(contract_address))?;
+        storage_proofs.insert(
+            contract_address,
+            merge_storage_proofs(vec![storage_proof, additional_proof]),
+        );
     }
 
-    Ok(storage_proof)
+    Ok((storage_proofs, class_proofs))
 }
 
-async fn fetch_storage_proof_for_contract(
-    rpc_client: &RpcClient,
-    contract_address: Felt,
-    keys: &[Felt],
+/// Fetches the proofs of the keys of each contract and the proofs of the classes, batching
+/// the requests of several contracts and classes together.
+async fn fetch_proofs(
+    proof_provider: &dyn ProofProvider,
     block_number: u64,
-) -> Result<PathfinderProof, ClientError> {
-    let storage_proof = if keys.is_empty() {
-        rpc_client
-            .pathfinder_rpc()
-            .get_proof(block_number, contract_address, &[])
-            .await?
-    } else {
-        // The endpoint is limited to 100 keys at most per call
-        const MAX_KEYS: usize = 100;
-        let mut chunked_storage_proofs = Vec::new();
-        for keys_chunk in keys.chunks(MAX_KEYS) {
-            chunked_storage_proofs.push(
-                rpc_client
-                    .pathfinder_rpc()
-                    .get_proof(block_number, contract_address, keys_chunk)
-                    .await?,
+    keys_by_address: &[(Felt, Vec<Felt>)],
+    class_hashes: &[Felt],
+    max_concurrent_requests: usize,
+) -> Result<BlockProofs, ProveBlockError> {
+    // The endpoint is limited to 100 keys at most per call
+    const MAX_KEYS: usize = 100;
+    let mut requests: Vec<(Felt, Vec<Felt>)> = vec![];
+    for (contract_address, keys) in keys_by_address {
+        if keys.is_empty() {
+            requests.push((*contract_address, vec![]));
+        } else {
+            requests.extend(
+                keys.chunks(MAX_KEYS)
+                    .map(|keys_chunk| (*contract_address, keys_chunk.to_vec())),
             );
         }
-        merge_storage_proofs(chunked_storage_proofs)
-    };
+    }
+
+    let contract_batches: Vec<&[(Felt, Vec<Felt>)]> = requests.chunks(MAX_BATCH_SIZE).collect();
+    let class_batches: Vec<&[Felt]> = class_hashes.chunks(MAX_BATCH_SIZE).collect();
+    let n_batches = contract_batches.len().max(class_batches.len());
+
+    let batches: Vec<_> = stream::iter(0..n_batches)
+        .map(|batch_index| {
+            let contracts = contract_batches
+                .get(batch_index)
+                .copied()
+                .unwrap_or_default();
+            let class_hashes = class_batches.get(batch_index).copied().unwrap_or_default();
+            async move {
+                let (proofs, class_proofs) = proof_provider
+                    .get_block_proofs(block_number, contracts, class_hashes)
+                    .await?;
+                if proofs.len() != contracts.len() {
+                    return Err(ProveBlockError::IncompleteWitness(format!(
+                        "got {} storage proofs for {} contracts",
+                        proofs.len(),
+                        contracts.len()
+                    )));
+                }
+                if class_proofs.len() != class_hashes.len() {
+                    return Err(ProveBlockError::IncompleteWitness(format!(
+                        "got {} class proofs for {} classes",
+                        class_proofs.len(),
+                        class_hashes.len()
+                    )));
+                }
+                let proofs = contracts
+                    .iter()
+                    .map(|(contract_address, _)| *contract_address)
+                    .zip(proofs)
+                    .collect::<Vec<_>>();
+                let class_proofs = class_hashes
+                    .iter()
+                    .map(|class_hash| Felt252::from(*class_hash))
+                    .zip(class_proofs)
+                    .collect::<Vec<_>>();
+                Ok::<_, ProveBlockError>((proofs, class_proofs))
+            }
+        })
+        // Keep the batches in order: the proofs of a contract split across several requests
+        // must be merged in the order of its keys
+        .buffered(max_concurrent_requests.max(1))
+        .try_collect()
+        .await?;
+
+    let mut chunked_storage_proofs: HashMap<Felt, Vec<PathfinderProof>> = HashMap::new();
+    let mut class_proofs = HashMap::new();
+    for (proofs, batch_class_proofs) in batches {
+        for (contract_address, proof) in proofs {
+            chunked_storage_proofs
+                .entry(contract_address)
+                .or_default()
+                .push(proof);
+        }
+        class_proofs.extend(batch_class_proofs);
+    }
+
+    let storage_proofs = chunked_storage_proofs
+        .into_iter()
+        .map(|(contract_address, proofs)| (contract_address, merge_storage_proofs(proofs)))
+        .collect();
 
-    Ok(storage_proof)
+    Ok((storage_proofs, class_proofs))
 }
 
 fn merge_storage_proofs(proofs: Vec<PathfinderProof>) -> PathfinderProof {
@@ -144,7 +226,20 @@ fn merge_storage_proofs(proofs: Vec<PathfinderProof>) -> PathfinderProof {
     }
 }
 
-fn verify_storage_proof(contract_data: &ContractData, keys: &[Felt]) -> Vec<Felt> {
+fn verify_storage_proof(
+    contract_address: Felt,
+    contract_data: &ContractData,
+    keys: &[Felt],
+) -> Result<Vec<Felt>, ProveBlockError> {
+    if contract_data.storage_proofs.len() != keys.len() {
+        return Err(ProveBlockError::IncompleteWitness(format!(
+            "got {} storage proofs for {} keys of contract 0x{:x}",
+            contract_data.storage_proofs.len(),
+            keys.len(),
+            contract_address
+        )));
+    }
+
     let mut additional_keys = vec![];
     if let Err(errors) = contract_data.verify(keys) {
         for error in errors {
@@ -162,14 +257,18 @@ fn verify_storage_proof(contract_data: &ContractData, keys: &[Felt]) -> Vec<Felt
                         }
                     }
                 }
-                _ => {
-                    panic!("Proof verification failed: {}", error);
+                ProofVerificationError::InvalidChildNodeHash { .. }
+                | ProofVerificationError::ConversionError => {
+                    return Err(ProveBlockError::ProofVerificationFailed {
+                        key: contract_address,
+                        error: error.to_string(),
+                    });
                 }
             }
         }
     }
 
-    additional_keys
+    Ok(additional_keys)
 }
 
 fn get_key_following_edge(key: Felt, height: Height, edge_path: &EdgePath) -> Felt {
@@ -183,20 +282,162 @@ fn get_key_following_edge(key: Felt, height: Height, edge_path: &EdgePath) -> Fe
     Felt::from(new_key)
 }
 
-pub(crate) async fn get_class_proofs(
-    rpc_client: &RpcClient,
-    block_number: u64,
-    class_hashes: &[&Felt],
-) -> Result<HashMap<Felt252, PathfinderClassProof>, ClientError> {
-    let mut proofs: HashMap<Felt252, PathfinderClassProof> =
-        HashMap::with_capacity(class_hashes.len());
-    for class_hash in class_hashes {
-        let proof = rpc_client
-            .pathfinder_rpc()
-            .get_class_proof(block_number, class_hash)
-            .await?;
-        proofs.insert(Felt252::from(**class_hash), proof);
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use async_trait::async_trait;
+    use rpc_client::pathfinder::client::ClientError;
+    use std::sync::Mutex;
+
+    /// Returns a storage proof of a single node holding the key, for each key, and a class
+    /// proof whose commitment is the class hash, for each class. Larger batches take longer
+    /// to complete, so the batches complete in reverse order.
+    #[derive(Default)]
+    struct KeyEchoProofProvider {
+        /// Number of contracts and classes of each call to `get_block_proofs`.
+        calls: Mutex<Vec<(usize, usize)>>,
+    }
+
+    fn key_proof(contract_address: Felt, keys: &[Felt]) -> PathfinderProof {
+        PathfinderProof {
+            state_commitment: None,
+            class_commitment: None,
+            contract_proof: vec![],
+            contract_data: Some(ContractData {
+                root: contract_address,
+                storage_proofs: keys
+                    .iter()
+                    .map(|key| {
+                        vec![TrieNode::Binary {
+                            left: *key,
+                            right: *key,
+                        }]
+                    })
+                    .collect(),
+            }),
+        }
+    }
+
+    fn class_proof(class_hash: Felt) -> PathfinderClassProof {
+        PathfinderClassProof {
+            class_commitment: class_hash,
+            class_proof: vec![],
+        }
+    }
+
+    #[async_trait]
+    impl ProofProvider for KeyEchoProofProvider {
+        async fn get_proof(
+            &self,
+            _block_number: u64,
+            contract_address: Felt,
+            keys: &[Felt],
+        ) -> Result<PathfinderProof, ClientError> {
+            Ok(key_proof(contract_address, keys))
+        }
+
+        async fn get_proofs(
+            &self,
+            _block_number: u64,
+            contracts: &[(Felt, Vec<Felt>)],
+        ) -> Result<Vec<PathfinderProof>, ClientError> {
+            Ok(contracts
+                .iter()
+                .map(|(contract_address, keys)| key_proof(*contract_address, keys))
+                .collect())
+        }
+
+        async fn get_class_proof(
+            &self,
+            _block_number: u64,
+            class_hash: Felt,
+        ) -> Result<PathfinderClassProof, ClientError> {
+            Ok(class_proof(class_hash))
+        }
+
+        async fn get_class_proofs(
+            &self,
+            _block_number: u64,
+            class_hashes: &[Felt],
+        ) -> Result<Vec<PathfinderClassProof>, ClientError> {
+            Ok(class_hashes.iter().copied().map(class_proof).collect())
+        }
+
+        async fn get_block_proofs(
+            &self,
+            block_number: u64,
+            contracts: &[(Felt, Vec<Felt>)],
+            class_hashes: &[Felt],
+        ) -> Result<(Vec<PathfinderProof>, Vec<PathfinderClassProof>), ClientError> {
+            self.calls
+                .lock()
+                .unwrap()
+                .push((contracts.len(), class_hashes.len()));
+            for _ in 0..contracts.len() + class_hashes.len() {
+                tokio::task::yield_now().await;
+            }
+            Ok((
+                self.get_proofs(block_number, contracts).await?,
+                self.get_class_proofs(block_number, class_hashes).await?,
+            ))
+        }
     }
 
-    Ok(proofs)
+    #[tokio::test]
+    async fn test_fetch_proofs_keeps_the_order_of_the_keys() {
+        // 99 contracts with one key, then a contract with 150 keys: its first 100 keys are
+        // in the first batch and the other 50 in the second one, which completes first.
+        let mut keys_by_address: Vec<(Felt, Vec<Felt>)> = (1..100u64)
+            .map(|contract_address| (Felt::from(contract_address), vec![Felt::ONE]))
+            .collect();
+        let contract_address = Felt::from(0x1000);
+        let keys: Vec<Felt> = (0..150u64).map(Felt::from).collect();
+        keys_by_address.push((contract_address, keys.clone()));
+
+        let proof_provider = KeyEchoProofProvider::default();
+        let (storage_proofs, _) = fetch_proofs(&proof_provider, 1, &keys_by_address, &[], 2)
+            .await
+            .unwrap();
+
+        assert_eq!(storage_proofs.len(), 100);
+        let contract_data = storage_proofs[&contract_address]
+            .contract_data
+            .as_ref()
+            .unwrap();
+        let proof_keys: Vec<Felt> = contract_data
+            .storage_proofs
+            .iter()
+            .map(|proof| match proof[..] {
+                [TrieNode::Binary { left, .. }] => left,
+                _ => panic!("unexpected proof {proof:?}"),
+            })
+            .collect();
+        assert_eq!(proof_keys, keys);
+    }
+
+    #[tokio::test]
+    async fn test_fetch_proofs_requests_classes_with_contracts() {
+        let keys_by_address = vec![
+            (Felt::from(0x100), vec![Felt::ONE]),
+            (Felt::from(0x101), vec![]),
+        ];
+        let class_hashes: Vec<Felt> = (0..150u64).map(|i| Felt::from(0x1000 + i)).collect();
+
+        let proof_provider = KeyEchoProofProvider::default();
+        let (storage_proofs, class_proofs) =
+            fetch_proofs(&proof_provider, 1, &keys_by_address, &class_hashes, 2)
+                .await
+                .unwrap();
+
+        // The contracts fit in the first request, along with the first 100 classes
+        assert_eq!(
+            *proof_provider.calls.lock().unwrap(),
+            vec![(2, 100), (0, 50)]
+        );
+        assert_eq!(storage_proofs.len(), 2);
+        assert_eq!(class_proofs.len(), class_hashes.len());
+        for class_hash in class_hashes {
+            assert_eq!(class_proofs[&class_hash].class_commitment, class_hash);
+        }
+    }
 }
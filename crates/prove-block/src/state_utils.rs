@@ -1,4 +1,7 @@
+use crate::cache::ProverCache;
+use crate::rpc_utils::zero_if_contract_not_found;
 use crate::utils::get_subcalled_contracts_from_tx_traces;
+use crate::witness::BlockWitness;
 use crate::ProveBlockError;
 use arcane_os_type::casm_contract_class::GenericCasmContractClass;
 use arcane_os_type::class_hash_utils::ContractClassComponentHashes;
@@ -7,10 +10,11 @@ use arcane_os_type::deprecated_compiled_class::GenericDeprecatedCompiledClass;
 use arcane_os_type::sierra_contract_class::GenericSierraContractClass;
 use cairo_vm::Felt252;
 use rpc_client::client::RpcClient;
-use starknet::providers::jsonrpc::HttpTransport;
-use starknet::providers::{JsonRpcClient, Provider, ProviderError};
+use rpc_replay::transaction::declared_class_hash;
+use starknet::providers::Provider;
 use starknet_core::types::{
-    BlockId, MaybePendingStateUpdate, StarknetError, StateDiff, TransactionTraceWithHash,
+    BlockId, MaybePendingBlockWithReceipts, MaybePendingStateUpdate, StateDiff, StateUpdate,
+    TransactionReceipt, TransactionTraceWithHash,
 };
 use starknet_types_core::felt::Felt;
 use std::collections::{HashMap, HashSet};
@@ -23,172 +27,234 @@ pub struct FormattedStateUpdate {
     pub declared_class_hash_component_hashes: HashMap<Felt, ContractClassComponentHashes>,
 }
 
-pub(crate) async fn get_formatted_state_update(
+pub(crate) async fn get_state_update_and_traces(
     provider: &RpcClient,
     block_id: BlockId,
-    previous_block_id: BlockId,
-) -> Result<(FormattedStateUpdate, Vec<TransactionTraceWithHash>), ProveBlockError> {
-    let state_update = match provider.get_state_update(block_id).await? {
+) -> Result<(StateUpdate, Vec<TransactionTraceWithHash>), ProveBlockError> {
+    let state_update = match provider.starknet_rpc().get_state_update(block_id).await? {
         MaybePendingStateUpdate::Update(update) => update,
         MaybePendingStateUpdate::PendingUpdate(_) => {
-            panic!("Block is still pending!")
+            return Err(ProveBlockError::PendingBlock(block_id));
         }
     };
-    let state_diff = state_update.state_diff;
-    let traces = provider.trace_block_transactions(block_id).await?;
-
-    let (accessed_addresses, accessed_classes) = get_subcalled_contracts_from_tx_traces(&traces);
+    let traces = provider
+        .starknet_rpc()
+        .trace_block_transactions(block_id)
+        .await?;
 
-    let declared_classes: HashSet<_> = state_diff
-        .declared_classes
-        .iter()
-        .map(|declared_item| Felt252::from(declared_item.class_hash))
-        .collect();
+    Ok((state_update, traces))
+}
 
-    let mut class_hash_to_compiled_class_hash: HashMap<Felt252, Felt252> = HashMap::new();
-    let (
-        compiled_contract_classes,
-        deprecated_compiled_contract_classes,
-        declared_class_hash_component_hashes,
-    ) = build_compiled_class_and_maybe_update_class_hash_to_compiled_class_hash(
-        provider,
-        previous_block_id,
-        block_id,
-        &accessed_addresses,
-        &declared_classes,
-        &accessed_classes,
-        &mut class_hash_to_compiled_class_hash,
-    )
-    .await?;
-
-    format_declared_classes(&state_diff, &mut class_hash_to_compiled_class_hash);
-
-    Ok((
-        FormattedStateUpdate {
-            class_hash_to_compiled_class_hash,
-            compiled_classes: compiled_contract_classes,
-            deprecated_compiled_classes: deprecated_compiled_contract_classes,
-            declared_class_hash_component_hashes,
-        },
-        traces,
-    ))
+pub(crate) async fn get_receipts(
+    provider: &RpcClient,
+    block_id: BlockId,
+) -> Result<Vec<TransactionReceipt>, ProveBlockError> {
+    match provider
+        .starknet_rpc()
+        .get_block_with_receipts(block_id)
+        .await?
+    {
+        MaybePendingBlockWithReceipts::Block(block) => Ok(block
+            .transactions
+            .into_iter()
+            .map(|tx| tx.receipt)
+            .collect()),
+        MaybePendingBlockWithReceipts::PendingBlock(_) => {
+            Err(ProveBlockError::PendingBlock(block_id))
+        }
+    }
 }
 
-async fn build_compiled_class_and_maybe_update_class_hash_to_compiled_class_hash(
+/// Fetches the classes of every contract and class touched by the block and records them
+/// in the witness, along with the class hashes of the accessed contracts.
+pub(crate) async fn fetch_classes(
     provider: &RpcClient,
+    cache: &mut ProverCache,
+    witness: &mut BlockWitness,
     previous_block_id: BlockId,
     block_id: BlockId,
-    accessed_addresses: &HashSet<Felt252>,
-    declared_classes: &HashSet<Felt252>,
-    accessed_classes: &HashSet<Felt252>,
-    class_hash_to_compiled_class_hash: &mut HashMap<Felt252, Felt252>,
-) -> Result<
-    (
-        HashMap<Felt252, GenericCasmContractClass>,
-        HashMap<Felt252, GenericDeprecatedCompiledClass>,
-        HashMap<Felt252, ContractClassComponentHashes>,
-    ),
-    ProveBlockError,
-> {
-    let mut compiled_contract_classes: HashMap<Felt252, GenericCasmContractClass> = HashMap::new();
-    let mut deprecated_compiled_contract_classes: HashMap<Felt252, GenericDeprecatedCompiledClass> =
-        HashMap::new();
+) -> Result<(), ProveBlockError> {
+    let (accessed_addresses, accessed_classes) =
+        get_subcalled_contracts_from_tx_traces(&witness.traces);
 
     for contract_address in accessed_addresses {
         // In case there is a class change, we need to get the compiled class for
         // the block to prove and for the previous block as they may differ.
         // Note that we must also consider the case where the contract was deployed in the current
         // block, so we can ignore "ContractNotFound" failures.
-        if let Err(e) = add_compiled_class_from_contract_to_os_input(
-            provider,
-            *contract_address,
-            previous_block_id,
-            class_hash_to_compiled_class_hash,
-            &mut compiled_contract_classes,
-            &mut deprecated_compiled_contract_classes,
-        )
-        .await
-        {
-            match e {
-                ProveBlockError::RpcError(ProviderError::StarknetError(
-                    StarknetError::ContractNotFound,
-                )) => {}
-                _ => return Err(e),
-            }
+        let previous_class_hash = zero_if_contract_not_found(
+            provider
+                .starknet_rpc()
+                .get_class_hash_at(previous_block_id, contract_address)
+                .await,
+        )?;
+        witness
+            .previous_state
+            .class_hashes
+            .insert(contract_address, previous_class_hash);
+        if previous_class_hash != Felt::ZERO {
+            fetch_class(
+                provider,
+                cache,
+                witness,
+                previous_block_id,
+                previous_class_hash,
+            )
+            .await?;
         }
 
-        add_compiled_class_from_contract_to_os_input(
-            provider,
-            *contract_address,
-            block_id,
-            class_hash_to_compiled_class_hash,
-            &mut compiled_contract_classes,
-            &mut deprecated_compiled_contract_classes,
-        )
-        .await?;
-    }
-
-    for class_hash in accessed_classes {
-        let contract_class = provider
+        let class_hash = provider
             .starknet_rpc()
-            .get_class(block_id, class_hash)
+            .get_class_hash_at(block_id, contract_address)
             .await?;
+        witness.class_hashes.insert(contract_address, class_hash);
+        fetch_class(provider, cache, witness, block_id, class_hash).await?;
+    }
+
+    // Classes declared in this block are needed both to build the OS input and to
+    // convert the declare transactions.
+    let declared_classes = witness
+        .state_update
+        .state_diff
+        .declared_classes
+        .iter()
+        .map(|declared_item| declared_item.class_hash);
+    let declare_tx_classes = witness
+        .block
+        .transactions
+        .iter()
+        .filter_map(declared_class_hash);
+    let class_hashes: Vec<_> = accessed_classes
+        .into_iter()
+        .chain(declared_classes)
+        .chain(declare_tx_classes)
+        .collect();
+
+    for class_hash in class_hashes {
+        fetch_class(provider, cache, witness, block_id, class_hash).await?;
+    }
+
+    Ok(())
+}
+
+pub(crate) async fn fetch_class(
+    provider: &RpcClient,
+    cache: &mut ProverCache,
+    witness: &mut BlockWitness,
+    block_id: BlockId,
+    class_hash: Felt,
+) -> Result<(), ProveBlockError> {
+    if witness.classes.contains_key(&class_hash) {
+        return Ok(());
+    }
+
+    let contract_class = match cache.get_class(&class_hash) {
+        Some(contract_class) => contract_class.clone(),
+        None => {
+            let contract_class = provider
+                .starknet_rpc()
+                .get_class(block_id, class_hash)
+                .await?;
+            cache.insert_class(class_hash, contract_class.clone());
+            contract_class
+        }
+    };
+    witness.classes.insert(class_hash, contract_class);
+
+    Ok(())
+}
+
+/// Compiles the classes recorded in the witness into the format expected by the OS.
+pub(crate) fn format_state_update(
+    witness: &BlockWitness,
+    cache: &ProverCache,
+) -> Result<FormattedStateUpdate, ProveBlockError> {
+    let mut class_hash_to_compiled_class_hash: HashMap<Felt252, Felt252> = HashMap::new();
+    let mut compiled_contract_classes: HashMap<Felt252, GenericCasmContractClass> = HashMap::new();
+    let mut deprecated_compiled_contract_classes: HashMap<Felt252, GenericDeprecatedCompiledClass> =
+        HashMap::new();
+
+    for class_hash in get_accessed_class_hashes(witness) {
+        let compiled_class =
+            cache.get_compiled_class(class_hash, witness.get_class(class_hash)?)?;
         add_compiled_class_to_os_input(
-            *Felt252::from(class_hash),
-            contract_class,
-            class_hash_to_compiled_class_hash,
+            class_hash,
+            compiled_class,
+            &mut class_hash_to_compiled_class_hash,
             &mut compiled_contract_classes,
             &mut deprecated_compiled_contract_classes,
         )?;
     }
 
+    let state_diff = &witness.state_update.state_diff;
     let mut declared_class_hash_to_component_hashes = HashMap::new();
-    for class_hash in declared_classes {
-        let contract_class = provider
-            .starknet_rpc()
-            .get_class(block_id, class_hash)
-            .await?;
+    for declared_item in state_diff.declared_classes.iter() {
+        let class_hash = declared_item.class_hash;
         if let starknet::core::types::ContractClass::Sierra(flattened_sierra_class) =
-            &contract_class
+            witness.get_class(class_hash)?
         {
             let component_hashes =
                 ContractClassComponentHashes::from(flattened_sierra_class.clone());
-            declared_class_hash_to_component_hashes.insert(*class_hash, component_hashes);
+            declared_class_hash_to_component_hashes.insert(class_hash, component_hashes);
         }
     }
 
-    Ok((
-        compiled_contract_classes,
-        deprecated_compiled_contract_classes,
-        declared_class_hash_to_component_hashes,
-    ))
+    format_declared_classes(state_diff, &mut class_hash_to_compiled_class_hash);
+
+    Ok(FormattedStateUpdate {
+        class_hash_to_compiled_class_hash,
+        compiled_classes: compiled_contract_classes,
+        deprecated_compiled_classes: deprecated_compiled_contract_classes,
+        declared_class_hash_component_hashes: declared_class_hash_to_component_hashes,
+    })
 }
 
-async fn add_compiled_class_from_contract_to_os_input(
-    provider: &RpcClient,
-    contract_address: Felt252,
-    block_id: BlockId,
-    class_hash_to_compiled_class_hash: &mut HashMap<Felt252, Felt252>,
-    compiled_contract_classes: &mut HashMap<Felt252, GenericCasmContractClass>,
-    deprecated_compiled_contract_classes: &mut HashMap<Felt252, GenericDeprecatedCompiledClass>,
-) -> Result<(), ProveBlockError> {
-    let class_hash = provider
-        .get_class_hash_at(block_id, contract_address)
-        .await?;
-    let contract_class = provider.get_class(block_id, class_hash).await?;
+/// Returns the hashes of the classes that end up in `class_hash_to_compiled_class_hash`,
+/// i.e. the classes we need class proofs for.
+pub(crate) fn get_class_hashes_to_prove(witness: &BlockWitness) -> Vec<Felt> {
+    let mut class_hashes: HashSet<Felt> = get_accessed_class_hashes(witness)
+        .into_iter()
+        .filter(|class_hash| {
+            matches!(
+                witness.classes.get(class_hash),
+                Some(starknet::core::types::ContractClass::Sierra(_))
+            )
+        })
+        .collect();
+    class_hashes.extend(
+        witness
+            .state_update
+            .state_diff
+            .declared_classes
+            .iter()
+            .map(|declared_item| declared_item.class_hash),
+    );
 
-    add_compiled_class_to_os_input(
-        class_hash,
-        contract_class,
-        class_hash_to_compiled_class_hash,
-        compiled_contract_classes,
-        deprecated_compiled_contract_classes,
-    )
+    class_hashes.into_iter().collect()
+}
+
+/// Returns the classes of the contracts accessed in the block, at the previous block and
+/// at the block itself, along with the classes called directly (library calls).
+fn get_accessed_class_hashes(witness: &BlockWitness) -> HashSet<Felt> {
+    let (accessed_addresses, mut class_hashes) =
+        get_subcalled_contracts_from_tx_traces(&witness.traces);
+
+    for contract_address in accessed_addresses {
+        for class_hashes_at_block in [&witness.previous_state.class_hashes, &witness.class_hashes] {
+            if let Some(class_hash) = class_hashes_at_block.get(&contract_address) {
+                if *class_hash != Felt::ZERO {
+                    class_hashes.insert(*class_hash);
+                }
+            }
+        }
+    }
+
+    class_hashes
 }
 
 fn add_compiled_class_to_os_input(
     class_hash: Felt,
-    contract_class: starknet::core::types::ContractClass,
+    compiled_class: GenericCompiledClass,
     class_hash_to_compiled_class_hash: &mut HashMap<Felt252, Felt252>,
     compiled_contract_classes: &mut HashMap<Felt252, GenericCasmContractClass>,
     deprecated_compiled_contract_classes: &mut HashMap<Felt252, GenericDeprecatedCompiledClass>,
@@ -197,7 +263,6 @@ fn add_compiled_class_to_os_input(
         return Ok(());
     }
 
-    let compiled_class = compile_contract_class(contract_class)?;
     let compiled_class_hash = compiled_class.class_hash()?;
 
     if matches!(&compiled_class, GenericCompiledClass::Cairo0(_)) {
@@ -222,7 +287,7 @@ fn add_compiled_class_to_os_input(
     Ok(())
 }
 
-fn compile_contract_class(
+pub(crate) fn compile_contract_class(
     contract_class: starknet::core::types::ContractClass,
 ) -> Result<GenericCompiledClass, ProveBlockError> {
     let compiled_class = match contract_class {
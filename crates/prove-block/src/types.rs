@@ -84,7 +84,9 @@ fn invoke_tx_v0_to_internal_tx(tx: InvokeTransactionV0) -> InternalTransaction {
                 .collect(),
         ),
         contract_address: Some(Felt252::from(tx.contract_address)),
+        sender_address: Some(Felt252::from(tx.contract_address)),
         entry_point_selector: Some(Felt252::from(tx.entry_point_selector)),
+        entry_point_type: Some("EXTERNAL".to_string()),
         calldata: Some(
             tx.calldata
                 .into_iter()
@@ -155,6 +157,8 @@ fn declare_v0_to_internal_tx(input: DeclareTransactionV0) -> InternalTransaction
         sender_address: Some(Felt252::from(input.sender_address)),
         max_fee: Some(Felt252::from(input.max_fee)),
         signature: Some(input.signature.into_iter().map(Felt252::from).collect()),
+        // Declare V0 transactions do not have a nonce, the OS expects zero
+        nonce: Some(Felt252::ZERO),
         class_hash: Some(Felt252::from(input.class_hash)),
         r#type: "DECLARE".to_string(),
         version: Some(Felt252::ZERO),
@@ -4,7 +4,7 @@ use cairo_vm::Felt252;
 use starknet_api::core::ContractAddress;
 use starknet_api::state::StorageKey;
 use starknet_core::types::{
-    ExecuteInvocation, FunctionInvocation, TransactionTrace, TransactionTraceWithHash,
+    ExecuteInvocation, FunctionInvocation, StateDiff, TransactionTrace, TransactionTraceWithHash,
 };
 use std::collections::{HashMap, HashSet};
 
@@ -100,6 +100,41 @@ fn process_function_invocations(
     }
 }
 
+/// Estimates the keys accessed by a block before re-executing it, from the RPC traces and
+/// state diff: every written key, and every called contract. The result has the same shape
+/// as [`get_all_accessed_keys`], and can be used to prefetch the state.
+pub(crate) fn get_accessed_keys_from_traces(
+    traces: &[TransactionTraceWithHash],
+    state_diff: &StateDiff,
+) -> HashMap<ContractAddress, HashSet<StorageKey>> {
+    let mut accessed_keys_by_address: HashMap<ContractAddress, HashSet<StorageKey>> =
+        HashMap::new();
+
+    let (contracts_subcalled, _) = get_subcalled_contracts_from_tx_traces(traces);
+    for contract_address in contracts_subcalled {
+        if let Ok(contract_address) = ContractAddress::try_from(contract_address) {
+            accessed_keys_by_address
+                .entry(contract_address)
+                .or_default();
+        }
+    }
+    for contract_diff in &state_diff.storage_diffs {
+        let Ok(contract_address) = ContractAddress::try_from(contract_diff.address) else {
+            continue;
+        };
+        let storage_keys = contract_diff
+            .storage_entries
+            .iter()
+            .filter_map(|entry| StorageKey::try_from(entry.key).ok());
+        accessed_keys_by_address
+            .entry(contract_address)
+            .or_default()
+            .extend(storage_keys);
+    }
+
+    accessed_keys_by_address
+}
+
 pub(crate) fn get_all_accessed_keys(
     tx_execution_infos: &[TransactionExecutionInfo],
 ) -> HashMap<ContractAddress, HashSet<StorageKey>> {
@@ -0,0 +1,305 @@
+use crate::cache::ProverCache;
+use crate::ProveBlockError;
+use blockifier::execution::contract_class::ContractClass;
+use blockifier::state::errors::StateError;
+use blockifier::state::state_api::{StateReader, StateResult};
+use rpc_client::pathfinder::proofs::{PathfinderClassProof, PathfinderProof};
+use rpc_replay::network::NetworkProfile;
+use serde::{Deserialize, Serialize};
+use starknet::core::types::{
+    BlockWithTxs, StateUpdate, TransactionReceipt, TransactionTraceWithHash,
+};
+use starknet_api::core::{ClassHash, CompiledClassHash, ContractAddress, Nonce};
+use starknet_api::state::StorageKey;
+use starknet_types_core::felt::Felt;
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
+use std::fs::File;
+use std::io::{BufReader, BufWriter};
+use std::path::Path;
+
+/// Version of the witness format, bumped on every incompatible change.
+pub const BLOCK_WITNESS_VERSION: u32 = 3;
+
+/// Every piece of data `prove_block` pulls from the RPC node for a block.
+///
+/// A witness is enough to rebuild the OS input of the block without network access,
+/// see `build_os_input_from_witness`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BlockWitness {
+    pub version: u32,
+    pub network: NetworkProfile,
+    pub block: BlockWithTxs,
+    pub previous_block_hash: Felt,
+    pub old_block_number: u64,
+    pub old_block_hash: Felt,
+    pub state_update: StateUpdate,
+    pub traces: Vec<TransactionTraceWithHash>,
+    /// Receipts of the transactions, used to cross-check the re-execution.
+    pub receipts: Vec<TransactionReceipt>,
+    /// Contract classes, by class hash.
+    pub classes: HashMap<Felt, starknet::core::types::ContractClass>,
+    /// Class hashes of the accessed contracts at the block itself.
+    pub class_hashes: HashMap<Felt, Felt>,
+    /// State of the previous block, as read during re-execution and OS input building.
+    pub previous_state: StateWitness,
+    pub storage_proofs: HashMap<Felt, PathfinderProof>,
+    pub previous_storage_proofs: HashMap<Felt, PathfinderProof>,
+    pub class_proofs: HashMap<Felt, PathfinderClassProof>,
+    pub previous_class_proofs: HashMap<Felt, PathfinderClassProof>,
+}
+
+/// Values read from the state of a block.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct StateWitness {
+    /// Class hash by contract address. Undeployed contracts have a zero class hash.
+    pub class_hashes: HashMap<Felt, Felt>,
+    pub nonces: HashMap<Felt, Felt>,
+    /// Storage values by contract address and storage key.
+    pub storage: HashMap<Felt, HashMap<Felt, Felt>>,
+    pub compiled_class_hashes: HashMap<Felt, Felt>,
+}
+
+impl StateWitness {
+    pub fn extend(&mut self, other: StateWitness) {
+        self.class_hashes.extend(other.class_hashes);
+        self.nonces.extend(other.nonces);
+        for (contract_address, storage) in other.storage {
+            self.storage
+                .entry(contract_address)
+                .or_default()
+                .extend(storage);
+        }
+        self.compiled_class_hashes
+            .extend(other.compiled_class_hashes);
+    }
+
+    pub fn get_storage_at(&self, contract_address: Felt, key: Felt) -> Option<Felt> {
+        self.storage
+            .get(&contract_address)
+            .and_then(|storage| storage.get(&key))
+            .copied()
+    }
+}
+
+impl BlockWitness {
+    pub fn load(path: &Path) -> Result<Self, ProveBlockError> {
+        let file = File::open(path)?;
+        let witness: Self = serde_json::from_reader(BufReader::new(file))?;
+        if witness.version != BLOCK_WITNESS_VERSION {
+            return Err(ProveBlockError::UnsupportedWitnessVersion(witness.version));
+        }
+
+        Ok(witness)
+    }
+
+    pub fn dump(&self, path: &Path) -> Result<(), ProveBlockError> {
+        let file = File::create(path)?;
+        serde_json::to_writer(BufWriter::new(file), self)?;
+
+        Ok(())
+    }
+
+    pub fn block_number(&self) -> u64 {
+        self.block.block_number
+    }
+
+    pub(crate) fn get_class(
+        &self,
+        class_hash: Felt,
+    ) -> Result<&starknet::core::types::ContractClass, ProveBlockError> {
+        self.classes.get(&class_hash).ok_or_else(|| {
+            ProveBlockError::IncompleteWitness(format!("missing class 0x{:x}", class_hash))
+        })
+    }
+}
+
+fn to_state_err<E: ToString>(e: E) -> StateError {
+    StateError::StateReadError(e.to_string())
+}
+
+fn get_blockifier_class(
+    cache: &ProverCache,
+    class_hash: Felt,
+    contract_class: &starknet::core::types::ContractClass,
+) -> StateResult<ContractClass> {
+    cache
+        .get_compiled_class(class_hash, contract_class)
+        .map_err(to_state_err)?
+        .get_blockifier_contract_class()
+        .map_err(to_state_err)
+}
+
+fn missing_from_witness(what: String) -> StateError {
+    StateError::StateReadError(format!("{} is missing from the block witness", what))
+}
+
+/// Serves the state of the previous block from a witness, for offline re-execution.
+pub(crate) struct WitnessStateReader<'a> {
+    witness: &'a BlockWitness,
+    cache: &'a ProverCache,
+}
+
+impl<'a> WitnessStateReader<'a> {
+    pub fn new(witness: &'a BlockWitness, cache: &'a ProverCache) -> Self {
+        Self { witness, cache }
+    }
+}
+
+impl StateReader for WitnessStateReader<'_> {
+    fn get_storage_at(
+        &self,
+        contract_address: ContractAddress,
+        key: StorageKey,
+    ) -> StateResult<Felt> {
+        self.witness
+            .previous_state
+            .get_storage_at(*contract_address.key(), *key.key())
+            .ok_or_else(|| {
+                missing_from_witness(format!(
+                    "storage key 0x{:x} of contract 0x{:x}",
+                    key.key(),
+                    contract_address.key()
+                ))
+            })
+    }
+
+    fn get_nonce_at(&self, contract_address: ContractAddress) -> StateResult<Nonce> {
+        self.witness
+            .previous_state
+            .nonces
+            .get(contract_address.key())
+            .map(|nonce| Nonce(*nonce))
+            .ok_or_else(|| {
+                missing_from_witness(format!("nonce of contract 0x{:x}", contract_address.key()))
+            })
+    }
+
+    fn get_class_hash_at(&self, contract_address: ContractAddress) -> StateResult<ClassHash> {
+        self.witness
+            .previous_state
+            .class_hashes
+            .get(contract_address.key())
+            .map(|class_hash| ClassHash(*class_hash))
+            .ok_or_else(|| {
+                missing_from_witness(format!(
+                    "class hash of contract 0x{:x}",
+                    contract_address.key()
+                ))
+            })
+    }
+
+    fn get_compiled_contract_class(&self, class_hash: ClassHash) -> StateResult<ContractClass> {
+        let contract_class = self
+            .witness
+            .classes
+            .get(&class_hash.0)
+            .ok_or(StateError::UndeclaredClassHash(class_hash))?;
+
+        get_blockifier_class(self.cache, class_hash.0, contract_class)
+    }
+
+    fn get_compiled_class_hash(&self, class_hash: ClassHash) -> StateResult<CompiledClassHash> {
+        self.witness
+            .previous_state
+            .compiled_class_hashes
+            .get(&class_hash.0)
+            .map(|compiled_class_hash| CompiledClassHash(*compiled_class_hash))
+            .ok_or_else(|| {
+                missing_from_witness(format!("compiled class hash of class 0x{:x}", class_hash.0))
+            })
+    }
+}
+
+/// Wraps a state reader and records every value it returns, so that the re-execution
+/// can be replayed from a witness.
+///
+/// Classes that were already fetched are served from `classes` instead of the wrapped reader.
+pub(crate) struct RecordingStateReader<'a, S: StateReader> {
+    state_reader: S,
+    classes: &'a HashMap<Felt, starknet::core::types::ContractClass>,
+    cache: &'a ProverCache,
+    recorded_state: RefCell<StateWitness>,
+    requested_classes: RefCell<HashSet<Felt>>,
+}
+
+impl<'a, S: StateReader> RecordingStateReader<'a, S> {
+    pub fn new(
+        state_reader: S,
+        classes: &'a HashMap<Felt, starknet::core::types::ContractClass>,
+        cache: &'a ProverCache,
+    ) -> Self {
+        Self {
+            state_reader,
+            classes,
+            cache,
+            recorded_state: Default::default(),
+            requested_classes: Default::default(),
+        }
+    }
+
+    /// Returns the recorded state and the hashes of the classes requested by the executor.
+    pub fn into_recording(self) -> (StateWitness, HashSet<Felt>) {
+        (
+            self.recorded_state.into_inner(),
+            self.requested_classes.into_inner(),
+        )
+    }
+}
+
+impl<S: StateReader> StateReader for RecordingStateReader<'_, S> {
+    fn get_storage_at(
+        &self,
+        contract_address: ContractAddress,
+        key: StorageKey,
+    ) -> StateResult<Felt> {
+        let value = self.state_reader.get_storage_at(contract_address, key)?;
+        self.recorded_state
+            .borrow_mut()
+            .storage
+            .entry(*contract_address.key())
+            .or_default()
+            .insert(*key.key(), value);
+
+        Ok(value)
+    }
+
+    fn get_nonce_at(&self, contract_address: ContractAddress) -> StateResult<Nonce> {
+        let nonce = self.state_reader.get_nonce_at(contract_address)?;
+        self.recorded_state
+            .borrow_mut()
+            .nonces
+            .insert(*contract_address.key(), nonce.0);
+
+        Ok(nonce)
+    }
+
+    fn get_class_hash_at(&self, contract_address: ContractAddress) -> StateResult<ClassHash> {
+        let class_hash = self.state_reader.get_class_hash_at(contract_address)?;
+        self.recorded_state
+            .borrow_mut()
+            .class_hashes
+            .insert(*contract_address.key(), class_hash.0);
+
+        Ok(class_hash)
+    }
+
+    fn get_compiled_contract_class(&self, class_hash: ClassHash) -> StateResult<ContractClass> {
+        if let Some(contract_class) = self.classes.get(&class_hash.0) {
+            return get_blockifier_class(self.cache, class_hash.0, contract_class);
+        }
+
+        self.requested_classes.borrow_mut().insert(class_hash.0);
+        self.state_reader.get_compiled_contract_class(class_hash)
+    }
+
+    fn get_compiled_class_hash(&self, class_hash: ClassHash) -> StateResult<CompiledClassHash> {
+        let compiled_class_hash = self.state_reader.get_compiled_class_hash(class_hash)?;
+        self.recorded_state
+            .borrow_mut()
+            .compiled_class_hashes
+            .insert(class_hash.0, compiled_class_hash.0);
+
+        Ok(compiled_class_hash)
+    }
+}
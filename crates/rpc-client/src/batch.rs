@@ -0,0 +1,47 @@
+use serde::de::DeserializeOwned;
+use starknet::core::types::StarknetError;
+use starknet::providers::jsonrpc::{JsonRpcClientError, JsonRpcError, JsonRpcResponse};
+use starknet::providers::ProviderError;
+
+use crate::transport::{RpcTransport, TransportError};
+
+/// Maximum number of requests sent in a single batch, nodes reject larger batches.
+pub const MAX_BATCH_SIZE: usize = 100;
+
+/// Calls `method` once per params, in batches of at most [`MAX_BATCH_SIZE`] requests.
+///
+/// Returns the result of each call in the order of `params`. A call can fail without
+/// failing the others.
+pub(crate) async fn call_batched<T: DeserializeOwned>(
+    transport: &RpcTransport,
+    method: &str,
+    params: Vec<serde_json::Value>,
+) -> Result<Vec<Result<T, JsonRpcError>>, TransportError> {
+    let mut results = Vec::with_capacity(params.len());
+    for params_chunk in params.chunks(MAX_BATCH_SIZE) {
+        let requests = params_chunk
+            .iter()
+            .map(|params| (method.to_string(), params.clone()))
+            .collect();
+        for response in transport.send_batch(requests).await? {
+            let result = match serde_json::from_value::<JsonRpcResponse<T>>(response)? {
+                JsonRpcResponse::Success { result, .. } => Ok(result),
+                JsonRpcResponse::Error { error, .. } => Err(error),
+            };
+            results.push(result);
+        }
+    }
+
+    Ok(results)
+}
+
+/// Converts an error of the Starknet RPC the same way starknet-rs does, so that callers can
+/// match on `ProviderError::StarknetError`.
+pub(crate) fn to_provider_error(error: JsonRpcError) -> ProviderError {
+    match StarknetError::try_from(&error) {
+        Ok(starknet_error) => ProviderError::StarknetError(starknet_error),
+        Err(_) => ProviderError::Other(Box::new(
+            JsonRpcClientError::<TransportError>::JsonRpcError(error),
+        )),
+    }
+}
@@ -1,52 +1,432 @@
 use std::sync::Arc;
+use std::time::Duration;
 
-use reqwest::Url;
-use starknet::providers::jsonrpc::HttpTransport;
-use starknet::providers::JsonRpcClient;
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue, AUTHORIZATION};
+use reqwest::{Certificate, Url};
+use serde_json::json;
+use starknet::core::types::BlockId;
+use starknet::providers::{JsonRpcClient, ProviderError};
+use starknet_types_core::felt::Felt;
 
-use crate::pathfinder::client::PathfinderRpcClient;
+use crate::batch::{call_batched, to_provider_error};
+use crate::disk_cache::DiskCache;
+use crate::pathfinder::client::{PathfinderRpcClient, PATHFINDER_ENDPOINT};
+use crate::proof_provider::{ProofApi, ProofProvider};
+use crate::retry::{RateLimiter, RetryPolicy};
+use crate::storage_proof::{StorageProofRpcClient, STORAGE_PROOF_ENDPOINT};
+use crate::transport::{
+    RpcRecording, RpcTransport, TransportConfig, TransportError, TransportMode,
+};
+
+/// Version of the Starknet RPC spec implemented by starknet-rs, as it appears in the path
+/// of the endpoint.
+pub const DEFAULT_SPEC_VERSION: &str = "v0_7";
+
+#[derive(Debug, thiserror::Error)]
+pub enum RpcClientBuildError {
+    #[error("No URL for the {0} endpoint: set a base URL or the URL of the endpoint")]
+    MissingUrl(&'static str),
+    #[error("Invalid URL {url}: {error}")]
+    InvalidUrl { url: String, error: String },
+    #[error("Invalid header {0}")]
+    InvalidHeader(String),
+    #[error("Could not build the HTTP client: {0}")]
+    TransportError(#[from] TransportError),
+}
 
 struct RpcClientInner {
     /// starknet-rs client, used to access data from endpoints defined in the Starknet RPC spec.
-    starknet_client: JsonRpcClient<HttpTransport>,
+    starknet_client: JsonRpcClient<RpcTransport>,
+    /// Transport of the starknet-rs client, for batch requests which starknet-rs does not support.
+    starknet_transport: RpcTransport,
     /// A Pathfinder-specific client to access endpoints not covered by starknet-rs.
     pathfinder_client: PathfinderRpcClient,
+    /// Client for the proof method of the RPC spec v0.8, not covered by starknet-rs.
+    storage_proof_client: StorageProofRpcClient,
+    transport_mode: TransportMode,
 }
 
-impl RpcClientInner {
-    fn new(base_url: &str) -> Self {
-        let starknet_rpc_url = format!("{}/rpc/v0_7", base_url);
-        let provider = JsonRpcClient::new(HttpTransport::new(
-            Url::parse(starknet_rpc_url.as_str()).unwrap_or_else(|e| {
-                panic!("Could not parse provider URL ({}): {}", starknet_rpc_url, e)
-            }),
-        ));
-        let pathfinder_client = PathfinderRpcClient::new(base_url);
+/// Builds an [`RpcClient`] for nodes that need more than a base URL: a custom path layout,
+/// authentication headers or TLS settings.
+///
+/// Each endpoint uses its own URL if set, and the base URL followed by the default path of
+/// the endpoint otherwise:
+/// * Starknet RPC: `<base URL>/rpc/<spec version>`
+/// * Pathfinder: `<base URL>/rpc/pathfinder/v0.1`
+/// * Storage proofs (RPC v0.8): `<base URL>/rpc/v0_8`
+#[derive(Debug, Clone)]
+pub struct RpcClientBuilder {
+    base_url: Option<String>,
+    starknet_url: Option<String>,
+    pathfinder_url: Option<String>,
+    storage_proof_url: Option<String>,
+    spec_version: String,
+    transport_mode: TransportMode,
+    config: TransportConfig,
+    invalid_headers: Vec<String>,
+}
 
+impl Default for RpcClientBuilder {
+    fn default() -> Self {
         Self {
-            starknet_client: provider,
-            pathfinder_client,
+            base_url: None,
+            starknet_url: None,
+            pathfinder_url: None,
+            storage_proof_url: None,
+            spec_version: DEFAULT_SPEC_VERSION.to_string(),
+            transport_mode: TransportMode::Live,
+            config: TransportConfig::default(),
+            invalid_headers: vec![],
         }
     }
 }
 
+impl RpcClientBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Base URL of the node, without the `/rpc/<version>` suffix.
+    pub fn base_url(mut self, base_url: &str) -> Self {
+        self.base_url = Some(base_url.trim_end_matches('/').to_string());
+        self
+    }
+
+    /// Full URL of the Starknet RPC endpoint.
+    pub fn starknet_url(mut self, url: &str) -> Self {
+        self.starknet_url = Some(url.to_string());
+        self
+    }
+
+    /// Full URL of the Pathfinder-specific endpoint.
+    pub fn pathfinder_url(mut self, url: &str) -> Self {
+        self.pathfinder_url = Some(url.to_string());
+        self
+    }
+
+    /// Full URL of the RPC v0.8 endpoint, used for `starknet_getStorageProof`.
+    pub fn storage_proof_url(mut self, url: &str) -> Self {
+        self.storage_proof_url = Some(url.to_string());
+        self
+    }
+
+    /// Version of the Starknet RPC spec in the path of the endpoint, e.g. `v0_7`.
+    pub fn spec_version(mut self, spec_version: &str) -> Self {
+        self.spec_version = spec_version.to_string();
+        self
+    }
+
+    /// Records or replays the requests made to the node, see [`TransportMode`].
+    pub fn transport_mode(mut self, transport_mode: TransportMode) -> Self {
+        self.transport_mode = transport_mode;
+        self
+    }
+
+    /// Replaces the whole transport config. Headers and TLS options set before are lost.
+    pub fn config(mut self, config: TransportConfig) -> Self {
+        self.config = config;
+        self
+    }
+
+    pub fn retry_policy(mut self, retry_policy: RetryPolicy) -> Self {
+        self.config.retry_policy = retry_policy;
+        self
+    }
+
+    pub fn timeout(mut self, timeout: Option<Duration>) -> Self {
+        self.config.timeout = timeout;
+        self
+    }
+
+    pub fn rate_limiter(mut self, rate_limiter: Arc<RateLimiter>) -> Self {
+        self.config.rate_limiter = Some(rate_limiter);
+        self
+    }
+
+    pub fn disk_cache(mut self, disk_cache: Arc<DiskCache>) -> Self {
+        self.config.disk_cache = Some(disk_cache);
+        self
+    }
+
+    /// Sends a header with every request. Invalid names or values are reported by
+    /// [`RpcClientBuilder::build`].
+    pub fn header(mut self, name: &str, value: &str) -> Self {
+        match (
+            HeaderName::from_bytes(name.as_bytes()),
+            HeaderValue::from_str(value),
+        ) {
+            (Ok(name), Ok(mut value)) => {
+                value.set_sensitive(true);
+                self.config.headers.insert(name, value);
+            }
+            _ => self.invalid_headers.push(name.to_string()),
+        }
+        self
+    }
+
+    pub fn headers(mut self, headers: HeaderMap) -> Self {
+        self.config.headers.extend(headers);
+        self
+    }
+
+    /// Sends `Authorization: Bearer <token>` with every request.
+    pub fn bearer_token(self, token: &str) -> Self {
+        self.header(AUTHORIZATION.as_str(), &format!("Bearer {token}"))
+    }
+
+    /// Trusts an additional root certificate, e.g. for a node behind a private CA.
+    pub fn add_root_certificate(mut self, certificate: Certificate) -> Self {
+        self.config.tls.root_certificates.push(certificate);
+        self
+    }
+
+    /// Accepts any server certificate. Only meant for local development.
+    pub fn danger_accept_invalid_certs(mut self, accept_invalid_certs: bool) -> Self {
+        self.config.tls.accept_invalid_certs = accept_invalid_certs;
+        self
+    }
+
+    pub fn build(self) -> Result<RpcClient, RpcClientBuildError> {
+        if let Some(name) = self.invalid_headers.first() {
+            return Err(RpcClientBuildError::InvalidHeader(name.clone()));
+        }
+
+        let starknet_endpoint = format!("rpc/{}", self.spec_version);
+        let starknet_transport = self.transport(
+            "Starknet RPC",
+            self.starknet_url.as_deref(),
+            &starknet_endpoint,
+        )?;
+        let pathfinder_transport = self.transport(
+            "Pathfinder",
+            self.pathfinder_url.as_deref(),
+            PATHFINDER_ENDPOINT,
+        )?;
+        let storage_proof_transport = self.transport(
+            "storage proof",
+            self.storage_proof_url.as_deref(),
+            STORAGE_PROOF_ENDPOINT,
+        )?;
+
+        let inner = RpcClientInner {
+            starknet_client: JsonRpcClient::new(starknet_transport.clone()),
+            starknet_transport,
+            pathfinder_client: PathfinderRpcClient::from_transport(pathfinder_transport),
+            storage_proof_client: StorageProofRpcClient::from_transport(storage_proof_transport),
+            transport_mode: self.transport_mode,
+        };
+
+        Ok(RpcClient {
+            inner: Arc::new(inner),
+        })
+    }
+
+    fn transport(
+        &self,
+        name: &'static str,
+        url: Option<&str>,
+        endpoint: &str,
+    ) -> Result<RpcTransport, RpcClientBuildError> {
+        let url = match (url, &self.base_url) {
+            (Some(url), _) => url.to_string(),
+            (None, Some(base_url)) => format!("{base_url}/{endpoint}"),
+            // Replayed requests never reach the node, any URL will do
+            (None, None) if matches!(self.transport_mode, TransportMode::Replay(_)) => {
+                String::new()
+            }
+            (None, None) => return Err(RpcClientBuildError::MissingUrl(name)),
+        };
+        if !matches!(self.transport_mode, TransportMode::Replay(_)) {
+            Url::parse(&url).map_err(|error| RpcClientBuildError::InvalidUrl {
+                url: url.clone(),
+                error: error.to_string(),
+            })?;
+        }
+
+        Ok(RpcTransport::with_url(
+            &url,
+            endpoint,
+            self.transport_mode.clone(),
+            self.config.clone(),
+        )?)
+    }
+}
+
 #[derive(Clone)]
 pub struct RpcClient {
     inner: Arc<RpcClientInner>,
 }
 
 impl RpcClient {
+    /// Creates a client for a node serving the default endpoints under `base_url`.
+    /// Use [`RpcClient::builder`] for anything else.
+    ///
+    /// Panics if `base_url` is not a valid URL, use [`RpcClientBuilder::build`] to get an
+    /// error instead.
     pub fn new(base_url: &str) -> Self {
-        Self {
-            inner: Arc::new(RpcClientInner::new(base_url)),
-        }
+        Self::with_transport_mode(base_url, TransportMode::Live)
+    }
+
+    /// Creates a client that records or replays the requests made to the node,
+    /// see [`TransportMode`].
+    pub fn with_transport_mode(base_url: &str, transport_mode: TransportMode) -> Self {
+        Self::with_config(base_url, transport_mode, TransportConfig::default())
+    }
+
+    /// Creates a client with custom retries, timeout and rate limit.
+    ///
+    /// The rate limiter of the config, if any, is shared by the Starknet and Pathfinder
+    /// endpoints.
+    pub fn with_config(
+        base_url: &str,
+        transport_mode: TransportMode,
+        config: TransportConfig,
+    ) -> Self {
+        RpcClientBuilder::new()
+            .base_url(base_url)
+            .transport_mode(transport_mode)
+            .config(config)
+            .build()
+            .unwrap_or_else(|e| panic!("Could not create the RPC client: {e}"))
     }
 
-    pub fn starknet_rpc(&self) -> &JsonRpcClient<HttpTransport> {
+    pub fn builder() -> RpcClientBuilder {
+        RpcClientBuilder::new()
+    }
+
+    /// Creates a client that serves every request from a recording.
+    pub fn replay(recording: Arc<RpcRecording>) -> Self {
+        Self::with_transport_mode("", TransportMode::Replay(recording))
+    }
+
+    pub fn starknet_rpc(&self) -> &JsonRpcClient<RpcTransport> {
         &self.inner.starknet_client
     }
 
     pub fn pathfinder_rpc(&self) -> &PathfinderRpcClient {
         &self.inner.pathfinder_client
     }
+
+    pub fn storage_proof_rpc(&self) -> &StorageProofRpcClient {
+        &self.inner.storage_proof_client
+    }
+
+    pub fn proof_provider(&self, proof_api: ProofApi) -> &dyn ProofProvider {
+        match proof_api {
+            ProofApi::Pathfinder => &self.inner.pathfinder_client,
+            ProofApi::StorageProof => &self.inner.storage_proof_client,
+        }
+    }
+
+    /// Picks `starknet_getStorageProof` if the node implements the RPC spec v0.8 or later,
+    /// and the Pathfinder methods otherwise.
+    pub async fn detect_proof_api(&self) -> ProofApi {
+        match self.inner.storage_proof_client.spec_version().await {
+            Ok(version) if supports_storage_proof(&version) => ProofApi::StorageProof,
+            Ok(version) => {
+                log::info!("RPC spec {version} has no storage proof method, using Pathfinder");
+                ProofApi::Pathfinder
+            }
+            Err(e) => {
+                log::info!("Could not get the RPC spec version, using Pathfinder: {e}");
+                ProofApi::Pathfinder
+            }
+        }
+    }
+
+    /// Reads several storage slots, given as `(contract_address, key)`, in batch requests.
+    ///
+    /// Each read can fail on its own, e.g. with `ContractNotFound`.
+    pub async fn get_storage_at_batch(
+        &self,
+        block_id: BlockId,
+        storage_slots: &[(Felt, Felt)],
+    ) -> Result<Vec<Result<Felt, ProviderError>>, TransportError> {
+        let params = storage_slots
+            .iter()
+            .map(|(contract_address, key)| {
+                json!({ "contract_address": contract_address, "key": key, "block_id": block_id })
+            })
+            .collect();
+
+        self.call_starknet_batched("starknet_getStorageAt", params)
+            .await
+    }
+
+    /// Gets the nonces of several contracts in batch requests.
+    pub async fn get_nonce_batch(
+        &self,
+        block_id: BlockId,
+        contract_addresses: &[Felt],
+    ) -> Result<Vec<Result<Felt, ProviderError>>, TransportError> {
+        let params = contract_addresses
+            .iter()
+            .map(|contract_address| {
+                json!({ "block_id": block_id, "contract_address": contract_address })
+            })
+            .collect();
+
+        self.call_starknet_batched("starknet_getNonce", params)
+            .await
+    }
+
+    /// Gets the class hashes of several contracts in batch requests.
+    pub async fn get_class_hash_at_batch(
+        &self,
+        block_id: BlockId,
+        contract_addresses: &[Felt],
+    ) -> Result<Vec<Result<Felt, ProviderError>>, TransportError> {
+        let params = contract_addresses
+            .iter()
+            .map(|contract_address| {
+                json!({ "block_id": block_id, "contract_address": contract_address })
+            })
+            .collect();
+
+        self.call_starknet_batched("starknet_getClassHashAt", params)
+            .await
+    }
+
+    async fn call_starknet_batched(
+        &self,
+        method: &str,
+        params: Vec<serde_json::Value>,
+    ) -> Result<Vec<Result<Felt, ProviderError>>, TransportError> {
+        let results = call_batched(&self.inner.starknet_transport, method, params).await?;
+
+        Ok(results
+            .into_iter()
+            .map(|result| result.map_err(to_provider_error))
+            .collect())
+    }
+
+    /// The recording the client writes to or reads from, if any.
+    pub fn recording(&self) -> Option<&Arc<RpcRecording>> {
+        self.inner.transport_mode.recording()
+    }
+}
+
+fn supports_storage_proof(spec_version: &str) -> bool {
+    let mut parts = spec_version.split('.').map(|part| part.parse::<u64>());
+    match (parts.next(), parts.next()) {
+        (Some(Ok(major)), Some(Ok(minor))) => major > 0 || minor >= 8,
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_supports_storage_proof() {
+        assert!(supports_storage_proof("0.8.0"));
+        assert!(supports_storage_proof("0.8.1-rc.1"));
+        assert!(supports_storage_proof("0.9"));
+        assert!(supports_storage_proof("1.0.0"));
+        assert!(!supports_storage_proof("0.7.1"));
+        assert!(!supports_storage_proof("0.6.0"));
+        assert!(!supports_storage_proof("v0_8"));
+        assert!(!supports_storage_proof(""));
+    }
 }
@@ -0,0 +1,560 @@
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::{BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::SystemTime;
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::transport::TransportError;
+
+/// Default size limit of the cache, 4 GiB.
+pub const DEFAULT_MAX_CACHE_SIZE: u64 = 4 << 30;
+
+/// Location and size limit of a [`DiskCache`].
+#[derive(Debug, Clone)]
+pub struct DiskCacheConfig {
+    pub directory: PathBuf,
+    /// Total size of the cached responses in bytes. The least recently used ones are evicted
+    /// when it is exceeded.
+    pub max_size: u64,
+}
+
+impl DiskCacheConfig {
+    pub fn new(directory: impl Into<PathBuf>) -> Self {
+        Self {
+            directory: directory.into(),
+            max_size: DEFAULT_MAX_CACHE_SIZE,
+        }
+    }
+
+    pub fn with_max_size(mut self, max_size: u64) -> Self {
+        self.max_size = max_size;
+        self
+    }
+}
+
+/// A cached response, with its request to detect hash collisions.
+#[derive(Debug, Serialize, Deserialize)]
+struct CacheEntry {
+    endpoint: String,
+    method: String,
+    params: serde_json::Value,
+    response: serde_json::Value,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct EntryInfo {
+    size: u64,
+    last_used: SystemTime,
+}
+
+#[derive(Debug, Default)]
+struct CacheIndex {
+    entries: HashMap<String, EntryInfo>,
+    total_size: u64,
+    /// Latest block known to be accepted on L1, learnt from the block responses.
+    l1_accepted_block: Option<u64>,
+}
+
+/// Content-addressed cache of JSON-RPC responses on disk.
+///
+/// Each response is stored in its own file, named after the SHA-256 of the endpoint, method
+/// and params of the request. Only responses that can never change are cached, see
+/// [`is_cacheable`]: the cache learns which blocks are final from the status of the blocks
+/// that go through it, or from [`DiskCache::set_l1_accepted_block`]. The cache can be shared
+/// by several processes: files are written atomically and the cache directory is the only
+/// shared state.
+#[derive(Debug)]
+pub struct DiskCache {
+    config: DiskCacheConfig,
+    index: Mutex<CacheIndex>,
+}
+
+impl DiskCache {
+    /// Opens the cache at the configured location, creating the directory if needed.
+    pub fn open(config: DiskCacheConfig) -> Result<Self, TransportError> {
+        fs::create_dir_all(&config.directory)?;
+
+        let mut index = CacheIndex::default();
+        for shard in fs::read_dir(&config.directory)? {
+            let shard = shard?;
+            if !shard.file_type()?.is_dir() {
+                continue;
+            }
+            for file in fs::read_dir(shard.path())? {
+                let file = file?;
+                let path = file.path();
+                if path
+                    .extension()
+                    .is_some_and(|extension| extension == "json")
+                {
+                    let metadata = file.metadata()?;
+                    let key = path
+                        .file_stem()
+                        .and_then(|stem| stem.to_str())
+                        .unwrap_or_default()
+                        .to_string();
+                    index.total_size += metadata.len();
+                    index.entries.insert(
+                        key,
+                        EntryInfo {
+                            size: metadata.len(),
+                            last_used: metadata.modified()?,
+                        },
+                    );
+                }
+            }
+        }
+        log::debug!(
+            "Opened RPC cache at {} with {} entries ({} bytes)",
+            config.directory.display(),
+            index.entries.len(),
+            index.total_size
+        );
+
+        Ok(Self {
+            config,
+            index: Mutex::new(index),
+        })
+    }
+
+    pub fn directory(&self) -> &Path {
+        &self.config.directory
+    }
+
+    /// Number of cached responses.
+    pub fn len(&self) -> usize {
+        self.index.lock().unwrap().entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Total size of the cached responses in bytes.
+    pub fn size(&self) -> u64 {
+        self.index.lock().unwrap().total_size
+    }
+
+    /// Latest block known to be accepted on L1. Blocks after it can still be reorged, so the
+    /// responses about them are not cached.
+    pub fn l1_accepted_block(&self) -> Option<u64> {
+        self.index.lock().unwrap().l1_accepted_block
+    }
+
+    /// Marks the blocks up to `block_number` as final.
+    pub fn set_l1_accepted_block(&self, block_number: u64) {
+        let mut index = self.index.lock().unwrap();
+        index.l1_accepted_block = index.l1_accepted_block.max(Some(block_number));
+    }
+
+    /// Marks the block of a block response as final if it is accepted on L1.
+    fn observe_block_status(&self, response: &serde_json::Value) {
+        let result = &response["result"];
+        if result["status"] == L1_ACCEPTED_STATUS {
+            if let Some(block_number) = result["block_number"].as_u64() {
+                self.set_l1_accepted_block(block_number);
+            }
+        }
+    }
+
+    /// Returns the cached response to a request, if any.
+    ///
+    /// Unreadable entries are treated as missing, they are overwritten by the next response.
+    pub fn get(
+        &self,
+        endpoint: &str,
+        method: &str,
+        params: &serde_json::Value,
+    ) -> Option<serde_json::Value> {
+        let key = cache_key(endpoint, method, params);
+        let path = self.entry_path(&key);
+        let file = File::open(&path).ok()?;
+        let entry: CacheEntry = match serde_json::from_reader(BufReader::new(&file)) {
+            Ok(entry) => entry,
+            Err(e) => {
+                log::warn!("Ignoring corrupted cache entry {}: {}", path.display(), e);
+                return None;
+            }
+        };
+        if entry.endpoint != endpoint || entry.method != method || &entry.params != params {
+            return None;
+        }
+        self.observe_block_status(&entry.response);
+
+        // The modification time of the file tracks its last use, so that the eviction order
+        // survives restarts
+        let now = SystemTime::now();
+        let _ = file.set_modified(now);
+        if let Some(info) = self.index.lock().unwrap().entries.get_mut(&key) {
+            info.last_used = now;
+        }
+
+        Some(entry.response)
+    }
+
+    /// Caches the response to a request, if it can never change.
+    pub fn insert(
+        &self,
+        endpoint: &str,
+        method: &str,
+        params: &serde_json::Value,
+        response: &serde_json::Value,
+    ) {
+        self.observe_block_status(response);
+        if !is_cacheable(method, params, response, self.l1_accepted_block()) {
+            return;
+        }
+
+        let key = cache_key(endpoint, method, params);
+        let entry = CacheEntry {
+            endpoint: endpoint.to_string(),
+            method: method.to_string(),
+            params: params.clone(),
+            response: response.clone(),
+        };
+        // A cache that cannot be written only costs performance
+        match self.write_entry(&key, &entry) {
+            Ok(size) => self.add_to_index(key, size),
+            Err(e) => log::warn!("Could not cache the response to {}: {}", method, e),
+        }
+    }
+
+    fn entry_path(&self, key: &str) -> PathBuf {
+        self.config
+            .directory
+            .join(&key[..2])
+            .join(format!("{key}.json"))
+    }
+
+    /// Writes an entry to a temporary file first, so that other processes never read a
+    /// partial entry.
+    fn write_entry(&self, key: &str, entry: &CacheEntry) -> Result<u64, TransportError> {
+        let path = self.entry_path(key);
+        if let Some(shard) = path.parent() {
+            fs::create_dir_all(shard)?;
+        }
+        let tmp_path = path.with_extension(format!("tmp-{}", std::process::id()));
+        let mut writer = BufWriter::new(File::create(&tmp_path)?);
+        serde_json::to_writer(&mut writer, entry)?;
+        writer.flush()?;
+        drop(writer);
+        fs::rename(&tmp_path, &path)?;
+
+        Ok(fs::metadata(&path)?.len())
+    }
+
+    fn add_to_index(&self, key: String, size: u64) {
+        let mut index = self.index.lock().unwrap();
+        let info = EntryInfo {
+            size,
+            last_used: SystemTime::now(),
+        };
+        if let Some(previous) = index.entries.insert(key, info) {
+            index.total_size -= previous.size;
+        }
+        index.total_size += size;
+
+        if index.total_size > self.config.max_size {
+            self.evict(&mut index);
+        }
+    }
+
+    /// Removes the least recently used entries until the cache fits in its size limit.
+    fn evict(&self, index: &mut CacheIndex) {
+        let mut entries: Vec<_> = index
+            .entries
+            .iter()
+            .map(|(key, info)| (info.last_used, key.clone()))
+            .collect();
+        entries.sort();
+
+        for (_, key) in entries {
+            if index.total_size <= self.config.max_size {
+                break;
+            }
+            if let Err(e) = fs::remove_file(self.entry_path(&key)) {
+                // Already evicted by another process
+                if e.kind() != std::io::ErrorKind::NotFound {
+                    log::warn!("Could not evict cache entry {}: {}", key, e);
+                    continue;
+                }
+            }
+            if let Some(info) = index.entries.remove(&key) {
+                index.total_size -= info.size;
+            }
+        }
+    }
+}
+
+fn cache_key(endpoint: &str, method: &str, params: &serde_json::Value) -> String {
+    let mut hasher = Sha256::new();
+    for part in [endpoint, method, &params.to_string()] {
+        hasher.update((part.len() as u64).to_be_bytes());
+        hasher.update(part.as_bytes());
+    }
+
+    hex::encode(hasher.finalize())
+}
+
+/// Methods whose response only depends on a class hash.
+const CLASS_METHODS: [&str; 2] = ["starknet_getClass", "starknet_getCompiledClass"];
+
+/// Status of the blocks, and finality status of the transactions, that cannot be reorged.
+const L1_ACCEPTED_STATUS: &str = "ACCEPTED_ON_L1";
+
+/// Whether a response can be cached forever.
+///
+/// Only successful responses are cached. Classes are identified by their hash, so they are
+/// cached whatever the block. Other responses are cached when the request targets a block
+/// by hash, or by number up to `l1_accepted_block`: blocks that are not accepted on L1 yet
+/// can be reorged, and the `latest` and `pending` tags, and requests without a block such
+/// as transaction receipts, can return different data over time. Responses that carry a
+/// status other than accepted on L1 are never cached.
+pub fn is_cacheable(
+    method: &str,
+    params: &serde_json::Value,
+    response: &serde_json::Value,
+    l1_accepted_block: Option<u64>,
+) -> bool {
+    let Some(result) = response.get("result") else {
+        return false;
+    };
+    if response.get("error").is_some() {
+        return false;
+    }
+
+    if CLASS_METHODS.contains(&method) {
+        return true;
+    }
+
+    let is_final = ["status", "finality_status"]
+        .iter()
+        .filter_map(|field| result.get(*field))
+        .all(|status| status == L1_ACCEPTED_STATUS);
+    if !is_final {
+        return false;
+    }
+
+    let Some(block_id) = params.get("block_id") else {
+        return false;
+    };
+    if block_id.get("block_hash").is_some() {
+        return true;
+    }
+    match (block_id["block_number"].as_u64(), l1_accepted_block) {
+        (Some(block_number), Some(l1_accepted_block)) => block_number <= l1_accepted_block,
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn result(result: serde_json::Value) -> serde_json::Value {
+        json!({"jsonrpc": "2.0", "id": 0, "result": result})
+    }
+
+    fn at_block(block_number: u64) -> serde_json::Value {
+        json!({"block_id": {"block_number": block_number}})
+    }
+
+    /// A cache in a fresh directory, removed when the test ends.
+    struct TestCache {
+        cache: DiskCache,
+    }
+
+    impl TestCache {
+        fn open(name: &str, max_size: u64) -> Self {
+            let directory =
+                std::env::temp_dir().join(format!("rpc-disk-cache-{}-{name}", std::process::id()));
+            let _ = fs::remove_dir_all(&directory);
+            let config = DiskCacheConfig::new(directory).with_max_size(max_size);
+            Self {
+                cache: DiskCache::open(config).unwrap(),
+            }
+        }
+    }
+
+    impl Drop for TestCache {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(self.cache.directory());
+        }
+    }
+
+    fn insert_class(cache: &DiskCache, class_hash: &str) {
+        cache.insert(
+            "rpc/v0_7",
+            "starknet_getClass",
+            &json!({"block_id": "latest", "class_hash": class_hash}),
+            &result(json!({"abi": "[]"})),
+        );
+    }
+
+    fn get_class(cache: &DiskCache, class_hash: &str) -> Option<serde_json::Value> {
+        cache.get(
+            "rpc/v0_7",
+            "starknet_getClass",
+            &json!({"block_id": "latest", "class_hash": class_hash}),
+        )
+    }
+
+    #[test]
+    fn test_is_cacheable() {
+        let storage = result(json!("0x1"));
+        let error =
+            json!({"jsonrpc": "2.0", "id": 0, "error": {"code": 24, "message": "Block not found"}});
+
+        assert!(is_cacheable(
+            "starknet_getClass",
+            &json!({"block_id": "latest"}),
+            &storage,
+            None
+        ));
+        assert!(!is_cacheable(
+            "starknet_getClass",
+            &json!({"block_id": "latest"}),
+            &error,
+            None
+        ));
+
+        let by_hash = json!({"block_id": {"block_hash": "0x123"}});
+        assert!(is_cacheable(
+            "starknet_getStorageAt",
+            &by_hash,
+            &storage,
+            None
+        ));
+
+        assert!(is_cacheable(
+            "starknet_getStorageAt",
+            &at_block(10),
+            &storage,
+            Some(10)
+        ));
+        assert!(!is_cacheable(
+            "starknet_getStorageAt",
+            &at_block(11),
+            &storage,
+            Some(10)
+        ));
+        assert!(!is_cacheable(
+            "starknet_getStorageAt",
+            &at_block(10),
+            &storage,
+            None
+        ));
+        assert!(!is_cacheable(
+            "starknet_getStorageAt",
+            &json!({"block_id": "latest"}),
+            &storage,
+            Some(10)
+        ));
+        assert!(!is_cacheable(
+            "starknet_getTransactionReceipt",
+            &json!({"transaction_hash": "0x1"}),
+            &storage,
+            Some(10)
+        ));
+
+        let l2_block = result(json!({"status": "ACCEPTED_ON_L2", "block_number": 10}));
+        let l1_block = result(json!({"status": "ACCEPTED_ON_L1", "block_number": 10}));
+        assert!(!is_cacheable(
+            "starknet_getBlockWithTxs",
+            &by_hash,
+            &l2_block,
+            None
+        ));
+        assert!(is_cacheable(
+            "starknet_getBlockWithTxs",
+            &by_hash,
+            &l1_block,
+            None
+        ));
+    }
+
+    #[test]
+    fn test_learns_l1_accepted_block() {
+        let test_cache = TestCache::open("l1-accepted", DEFAULT_MAX_CACHE_SIZE);
+        let cache = &test_cache.cache;
+        let storage = result(json!("0x1"));
+
+        cache.insert("rpc/v0_7", "starknet_getStorageAt", &at_block(10), &storage);
+        assert!(cache.is_empty());
+
+        let l2_block = result(json!({"status": "ACCEPTED_ON_L2", "block_number": 12}));
+        cache.insert(
+            "rpc/v0_7",
+            "starknet_getBlockWithTxs",
+            &at_block(12),
+            &l2_block,
+        );
+        assert_eq!(cache.l1_accepted_block(), None);
+
+        let l1_block = result(json!({"status": "ACCEPTED_ON_L1", "block_number": 11}));
+        cache.insert(
+            "rpc/v0_7",
+            "starknet_getBlockWithTxs",
+            &at_block(11),
+            &l1_block,
+        );
+        assert_eq!(cache.l1_accepted_block(), Some(11));
+
+        cache.insert("rpc/v0_7", "starknet_getStorageAt", &at_block(10), &storage);
+        cache.insert("rpc/v0_7", "starknet_getStorageAt", &at_block(12), &storage);
+        assert_eq!(cache.len(), 2);
+        assert_eq!(
+            cache.get("rpc/v0_7", "starknet_getStorageAt", &at_block(10)),
+            Some(storage)
+        );
+        assert_eq!(
+            cache.get("rpc/v0_7", "starknet_getStorageAt", &at_block(12)),
+            None
+        );
+    }
+
+    #[test]
+    fn test_eviction() {
+        let test_cache = TestCache::open("eviction", DEFAULT_MAX_CACHE_SIZE);
+        insert_class(&test_cache.cache, "0x1");
+        let entry_size = test_cache.cache.size();
+        drop(test_cache);
+
+        let test_cache = TestCache::open("eviction", 2 * entry_size + entry_size / 2);
+        let cache = &test_cache.cache;
+        insert_class(cache, "0x1");
+        insert_class(cache, "0x2");
+        // 0x2 becomes the least recently used entry
+        assert!(get_class(cache, "0x1").is_some());
+        insert_class(cache, "0x3");
+
+        assert_eq!(cache.len(), 2);
+        assert_eq!(cache.size(), 2 * entry_size);
+        assert!(get_class(cache, "0x1").is_some());
+        assert!(get_class(cache, "0x2").is_none());
+        assert!(get_class(cache, "0x3").is_some());
+    }
+
+    #[test]
+    fn test_index_is_rebuilt_on_open() {
+        let test_cache = TestCache::open("index", DEFAULT_MAX_CACHE_SIZE);
+        insert_class(&test_cache.cache, "0x1");
+        insert_class(&test_cache.cache, "0x2");
+        insert_class(&test_cache.cache, "0x2");
+
+        let reopened = DiskCache::open(test_cache.cache.config.clone()).unwrap();
+        assert_eq!(reopened.len(), 2);
+        assert_eq!(reopened.size(), test_cache.cache.size());
+        assert_eq!(
+            get_class(&reopened, "0x2"),
+            Some(result(json!({"abi": "[]"})))
+        );
+        assert_eq!(get_class(&reopened, "0x4"), None);
+    }
+}
@@ -1,2 +1,10 @@
+pub mod batch;
 pub mod client;
+pub mod disk_cache;
 pub mod pathfinder;
+pub mod proof_provider;
+pub mod retry;
+pub mod storage_proof;
+#[cfg(test)]
+mod test_utils;
+pub mod transport;
@@ -1,82 +1,78 @@
-use reqwest::{Response, StatusCode};
 use serde::de::DeserializeOwned;
 use serde::Deserialize;
 use serde_json::json;
+use starknet::providers::jsonrpc::JsonRpcError;
 use starknet_types_core::felt::Felt;
 
+use crate::batch::call_batched;
+
 use crate::pathfinder::proofs::{PathfinderClassProof, PathfinderProof};
+use crate::transport::{RpcTransport, TransportConfig, TransportError, TransportMode};
 
 #[derive(Debug, thiserror::Error)]
 pub enum ClientError {
     #[error("Encountered a request error: {0}")]
     ReqwestError(#[from] reqwest::Error),
+    #[error("Encountered a transport error: {0}")]
+    TransportError(#[from] TransportError),
+    #[error("Received a JSON-RPC error: {0}")]
+    JsonRpcError(#[from] JsonRpcError),
     #[error("Encountered a custom error: {0}")]
     CustomError(String),
 }
 
-fn jsonrpc_request(method: &str, params: serde_json::Value) -> serde_json::Value {
-    json!({
-        "jsonrpc": "2.0",
-        "id": "0",
-        "method": method,
-        "params": params,
-    })
-}
-
-async fn post_jsonrpc_request<T: DeserializeOwned>(
-    client: &reqwest::Client,
-    rpc_provider: &str,
+pub(crate) async fn post_jsonrpc_request<T: DeserializeOwned>(
+    transport: &RpcTransport,
     method: &str,
     params: serde_json::Value,
 ) -> Result<T, ClientError> {
-    let request = jsonrpc_request(method, params);
-    let response = client
-        .post(format!("{}/rpc/pathfinder/v0.1", rpc_provider))
-        .json(&request)
-        .send()
-        .await?;
+    let response = transport.send(method, params).await?;
 
     #[derive(Deserialize)]
     struct TransactionReceiptResponse<T> {
         result: T,
     }
 
-    let response: TransactionReceiptResponse<T> = handle_error(response).await?;
+    let response: TransactionReceiptResponse<T> = serde_json::from_value(response)
+        .map_err(|e| ClientError::CustomError(format!("Could not decode response: {e}")))?;
 
     Ok(response.result)
 }
 
-async fn handle_error<T: DeserializeOwned>(response: Response) -> Result<T, ClientError> {
-    match response.status() {
-        StatusCode::OK => Ok(response.json().await?),
-        s => {
-            let error = response.text().await?;
-            Err(ClientError::CustomError(format!(
-                "Received response: {s:?} Error: {error}"
-            )))
-        }
-    }
-}
+/// Path of the Pathfinder-specific endpoint, relative to the base URL of the node.
+pub const PATHFINDER_ENDPOINT: &str = "rpc/pathfinder/v0.1";
 
 pub struct PathfinderRpcClient {
-    /// A raw client to access endpoints not covered by starknet-rs.
-    http_client: reqwest::Client,
-    /// The base URL of the RPC client
-    rpc_base_url: String,
+    /// A raw transport to access endpoints not covered by starknet-rs.
+    transport: RpcTransport,
 }
 
 impl PathfinderRpcClient {
-    pub fn new(base_url: &str) -> Self {
-        let starknet_rpc_url = format!("{}/rpc/v0_7", base_url);
-        log::info!("Starknet RPC URL: {}", starknet_rpc_url);
-        let http_client = reqwest::ClientBuilder::new()
-            .build()
-            .unwrap_or_else(|e| panic!("Could not build reqwest client: {e}"));
-
-        Self {
-            http_client,
-            rpc_base_url: base_url.to_string(),
-        }
+    pub fn new(base_url: &str) -> Result<Self, TransportError> {
+        Self::with_transport_mode(base_url, TransportMode::Live)
+    }
+
+    pub fn with_transport_mode(
+        base_url: &str,
+        mode: TransportMode,
+    ) -> Result<Self, TransportError> {
+        Self::with_config(base_url, mode, TransportConfig::default())
+    }
+
+    pub fn with_config(
+        base_url: &str,
+        mode: TransportMode,
+        config: TransportConfig,
+    ) -> Result<Self, TransportError> {
+        let transport = RpcTransport::with_config(base_url, PATHFINDER_ENDPOINT, mode, config)?;
+
+        Ok(Self::from_transport(transport))
+    }
+
+    pub(crate) fn from_transport(transport: RpcTransport) -> Self {
+        log::info!("Pathfinder RPC URL: {}", transport.url());
+
+        Self { transport }
     }
 
     pub async fn get_proof(
@@ -86,14 +82,34 @@ impl PathfinderRpcClient {
         keys: &[Felt],
     ) -> Result<PathfinderProof, ClientError> {
         post_jsonrpc_request(
-            &self.http_client,
-            &self.rpc_base_url,
+            &self.transport,
             "pathfinder_getProof",
             json!({ "block_id": { "block_number": block_number }, "contract_address": contract_address, "keys": keys }),
         )
             .await
     }
 
+    /// Gets the proofs of several contracts, given as `(contract_address, keys)`,
+    /// in batch requests.
+    pub async fn get_proofs(
+        &self,
+        block_number: u64,
+        contracts: &[(Felt, Vec<Felt>)],
+    ) -> Result<Vec<PathfinderProof>, ClientError> {
+        let params = contracts
+            .iter()
+            .map(|(contract_address, keys)| {
+                json!({ "block_id": { "block_number": block_number }, "contract_address": contract_address, "keys": keys })
+            })
+            .collect();
+        let results = call_batched(&self.transport, "pathfinder_getProof", params).await?;
+
+        results
+            .into_iter()
+            .map(|result| result.map_err(ClientError::from))
+            .collect()
+    }
+
     pub async fn get_class_proof(
         &self,
         block_number: u64,
@@ -101,11 +117,30 @@ impl PathfinderRpcClient {
     ) -> Result<PathfinderClassProof, ClientError> {
         log::debug!("querying pathfinder_getClassProof for {:x}", class_hash);
         post_jsonrpc_request(
-            &self.http_client,
-            &self.rpc_base_url,
+            &self.transport,
             "pathfinder_getClassProof",
             json!({ "block_id": { "block_number": block_number }, "class_hash": class_hash }),
         )
         .await
     }
+
+    /// Gets the proofs of several classes in batch requests.
+    pub async fn get_class_proofs(
+        &self,
+        block_number: u64,
+        class_hashes: &[Felt],
+    ) -> Result<Vec<PathfinderClassProof>, ClientError> {
+        let params = class_hashes
+            .iter()
+            .map(|class_hash| {
+                json!({ "block_id": { "block_number": block_number }, "class_hash": class_hash })
+            })
+            .collect();
+        let results = call_batched(&self.transport, "pathfinder_getClassProof", params).await?;
+
+        results
+            .into_iter()
+            .map(|result| result.map_err(ClientError::from))
+            .collect()
+    }
 }
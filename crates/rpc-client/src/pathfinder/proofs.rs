@@ -7,10 +7,10 @@ use arcane_os::starkware_utils::commitment_tree::patricia_tree::nodes::{
 };
 use arcane_os::storage::dict_storage::DictStorage;
 use arcane_os::storage::storage::{Fact, HashFunctionType};
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 use starknet::core::types::Felt;
 
-#[derive(Debug, Clone, Deserialize, PartialEq, Eq)]
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq, Eq)]
 pub enum TrieNode {
     #[serde(rename = "binary")]
     Binary { left: Felt, right: Felt },
@@ -44,7 +44,7 @@ impl TrieNode {
     }
 }
 
-#[derive(Debug, Clone, Deserialize, Default)]
+#[derive(Debug, Clone, Deserialize, Serialize, Default)]
 pub struct ContractData {
     /// Root of the Contract state tree
     pub root: Felt,
@@ -89,7 +89,7 @@ impl ContractData {
     }
 }
 
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct PathfinderProof {
     pub state_commitment: Option<Felt>,
     pub class_commitment: Option<Felt>,
@@ -98,7 +98,7 @@ pub struct PathfinderProof {
 }
 
 #[allow(dead_code)]
-#[derive(Clone, Deserialize)]
+#[derive(Clone, Deserialize, Serialize)]
 pub struct PathfinderClassProof {
     pub class_commitment: Felt,
     pub class_proof: Vec<TrieNode>,
@@ -112,7 +112,7 @@ impl PathfinderClassProof {
 }
 
 // Types defined for Deserialize functionality
-#[derive(Debug, Clone, Deserialize, PartialEq, Eq)]
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq, Eq)]
 pub struct EdgePath {
     pub len: u64,
     pub value: Felt,
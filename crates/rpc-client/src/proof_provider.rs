@@ -0,0 +1,131 @@
+use std::fmt;
+use std::str::FromStr;
+
+use async_trait::async_trait;
+use starknet_types_core::felt::Felt;
+
+use crate::pathfinder::client::{ClientError, PathfinderRpcClient};
+use crate::pathfinder::proofs::{PathfinderClassProof, PathfinderProof};
+
+/// A source of Merkle proofs for the state of a block.
+///
+/// Proofs always use the Pathfinder format, whatever the node they come from.
+#[async_trait]
+pub trait ProofProvider: Send + Sync {
+    /// Returns the proof of a contract in the contract tree, and the proofs of `keys` in the
+    /// storage tree of the contract.
+    async fn get_proof(
+        &self,
+        block_number: u64,
+        contract_address: Felt,
+        keys: &[Felt],
+    ) -> Result<PathfinderProof, ClientError>;
+
+    /// Returns the proofs of several contracts, given as `(contract_address, keys)`, in order.
+    ///
+    /// The proofs are fetched in as few requests as the provider allows.
+    async fn get_proofs(
+        &self,
+        block_number: u64,
+        contracts: &[(Felt, Vec<Felt>)],
+    ) -> Result<Vec<PathfinderProof>, ClientError>;
+
+    /// Returns the proof of a class in the class tree.
+    async fn get_class_proof(
+        &self,
+        block_number: u64,
+        class_hash: Felt,
+    ) -> Result<PathfinderClassProof, ClientError>;
+
+    /// Returns the proofs of several classes, in order.
+    ///
+    /// The proofs are fetched in as few requests as the provider allows.
+    async fn get_class_proofs(
+        &self,
+        block_number: u64,
+        class_hashes: &[Felt],
+    ) -> Result<Vec<PathfinderClassProof>, ClientError>;
+
+    /// Returns the proofs of several contracts and classes at the same block, in order.
+    ///
+    /// Providers that prove contracts and classes with the same method fetch them together.
+    async fn get_block_proofs(
+        &self,
+        block_number: u64,
+        contracts: &[(Felt, Vec<Felt>)],
+        class_hashes: &[Felt],
+    ) -> Result<(Vec<PathfinderProof>, Vec<PathfinderClassProof>), ClientError> {
+        let proofs = self.get_proofs(block_number, contracts).await?;
+        let class_proofs = self.get_class_proofs(block_number, class_hashes).await?;
+
+        Ok((proofs, class_proofs))
+    }
+}
+
+#[async_trait]
+impl ProofProvider for PathfinderRpcClient {
+    async fn get_proof(
+        &self,
+        block_number: u64,
+        contract_address: Felt,
+        keys: &[Felt],
+    ) -> Result<PathfinderProof, ClientError> {
+        PathfinderRpcClient::get_proof(self, block_number, contract_address, keys).await
+    }
+
+    async fn get_proofs(
+        &self,
+        block_number: u64,
+        contracts: &[(Felt, Vec<Felt>)],
+    ) -> Result<Vec<PathfinderProof>, ClientError> {
+        PathfinderRpcClient::get_proofs(self, block_number, contracts).await
+    }
+
+    async fn get_class_proof(
+        &self,
+        block_number: u64,
+        class_hash: Felt,
+    ) -> Result<PathfinderClassProof, ClientError> {
+        PathfinderRpcClient::get_class_proof(self, block_number, &&class_hash).await
+    }
+
+    async fn get_class_proofs(
+        &self,
+        block_number: u64,
+        class_hashes: &[Felt],
+    ) -> Result<Vec<PathfinderClassProof>, ClientError> {
+        PathfinderRpcClient::get_class_proofs(self, block_number, class_hashes).await
+    }
+}
+
+/// The RPC methods used to fetch proofs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProofApi {
+    /// `pathfinder_getProof` and `pathfinder_getClassProof`, only served by Pathfinder.
+    Pathfinder,
+    /// `starknet_getStorageProof`, part of the RPC spec since v0.8.
+    StorageProof,
+}
+
+impl fmt::Display for ProofApi {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ProofApi::Pathfinder => write!(f, "pathfinder"),
+            ProofApi::StorageProof => write!(f, "storage-proof"),
+        }
+    }
+}
+
+impl FromStr for ProofApi {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "pathfinder" => Ok(ProofApi::Pathfinder),
+            "storage-proof" => Ok(ProofApi::StorageProof),
+            _ => Err(format!(
+                "unknown proof API {s}, expected pathfinder or storage-proof"
+            )),
+        }
+    }
+}
@@ -0,0 +1,205 @@
+use std::time::{Duration, Instant};
+
+use rand::Rng;
+use reqwest::StatusCode;
+use tokio::sync::Mutex;
+
+/// How failed requests are retried.
+///
+/// The delay before retry `n` is `initial_backoff * 2^n`, capped to `max_backoff`. With
+/// `jitter`, a random delay between zero and the computed one is used instead, so that
+/// concurrent requests do not all hit the node again at the same time.
+#[derive(Debug, Clone)]
+pub struct RetryPolicy {
+    pub max_retries: u32,
+    pub initial_backoff: Duration,
+    pub max_backoff: Duration,
+    pub jitter: bool,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_retries: 5,
+            initial_backoff: Duration::from_millis(500),
+            max_backoff: Duration::from_secs(30),
+            jitter: true,
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Never retries.
+    pub fn none() -> Self {
+        Self {
+            max_retries: 0,
+            ..Self::default()
+        }
+    }
+
+    /// Delay before the retry number `retry`, starting from 0.
+    pub fn backoff(&self, retry: u32) -> Duration {
+        let backoff = self
+            .initial_backoff
+            .saturating_mul(2u32.saturating_pow(retry))
+            .min(self.max_backoff);
+        if self.jitter {
+            backoff.mul_f64(rand::thread_rng().gen())
+        } else {
+            backoff
+        }
+    }
+}
+
+/// Whether a request may be sent again: methods that change the state of the chain are not.
+pub fn is_idempotent(method: &str) -> bool {
+    !method.starts_with("starknet_add")
+}
+
+/// Whether an HTTP status is worth a retry: rate limiting and unavailable upstream servers.
+pub fn is_retryable_status(status: StatusCode) -> bool {
+    matches!(
+        status,
+        StatusCode::TOO_MANY_REQUESTS
+            | StatusCode::BAD_GATEWAY
+            | StatusCode::SERVICE_UNAVAILABLE
+            | StatusCode::GATEWAY_TIMEOUT
+    )
+}
+
+/// Whether a request error is worth a retry: timeouts, connection failures and connections
+/// reset while sending the request or reading the response.
+///
+/// Only idempotent requests are retried, see [`is_idempotent`].
+pub fn is_retryable_error(error: &reqwest::Error) -> bool {
+    error.is_timeout() || error.is_connect() || error.is_request() || error.is_body()
+}
+
+/// JSON-RPC error codes used by nodes and hosted providers to signal rate limiting.
+const RATE_LIMIT_ERROR_CODES: [i64; 2] = [429, -32005];
+
+/// Whether a JSON-RPC response, or any response of a batch, is a rate-limit error.
+///
+/// Some providers rate limit with a JSON-RPC error in an HTTP 200 response rather than
+/// with a 429 status.
+pub fn is_rate_limit_response(response: &serde_json::Value) -> bool {
+    match response {
+        serde_json::Value::Array(responses) => responses.iter().any(is_rate_limit_response),
+        response => {
+            let error = &response["error"];
+            let code_matches = error["code"]
+                .as_i64()
+                .is_some_and(|code| RATE_LIMIT_ERROR_CODES.contains(&code));
+            let message_matches = error["message"].as_str().is_some_and(|message| {
+                let message = message.to_lowercase();
+                message.contains("rate limit") || message.contains("too many requests")
+            });
+            code_matches || message_matches
+        }
+    }
+}
+
+/// Spaces requests out to stay below a number of requests per second.
+///
+/// Share one limiter between transports to limit the requests made to a node as a whole.
+#[derive(Debug)]
+pub struct RateLimiter {
+    interval: Duration,
+    next_request: Mutex<Instant>,
+}
+
+impl RateLimiter {
+    pub fn new(max_requests_per_second: u32) -> Self {
+        Self {
+            interval: Duration::from_secs(1) / max_requests_per_second.max(1),
+            next_request: Mutex::new(Instant::now()),
+        }
+    }
+
+    /// Waits until the next request can be sent.
+    pub async fn acquire(&self) {
+        let request_time = {
+            let mut next_request = self.next_request.lock().await;
+            let request_time = (*next_request).max(Instant::now());
+            *next_request = request_time + self.interval;
+            request_time
+        };
+
+        tokio::time::sleep_until(request_time.into()).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn policy(jitter: bool) -> RetryPolicy {
+        RetryPolicy {
+            max_retries: 10,
+            initial_backoff: Duration::from_millis(100),
+            max_backoff: Duration::from_secs(1),
+            jitter,
+        }
+    }
+
+    #[test]
+    fn test_backoff() {
+        let policy = policy(false);
+        assert_eq!(policy.backoff(0), Duration::from_millis(100));
+        assert_eq!(policy.backoff(1), Duration::from_millis(200));
+        assert_eq!(policy.backoff(3), Duration::from_millis(800));
+        assert_eq!(policy.backoff(4), Duration::from_secs(1));
+        assert_eq!(policy.backoff(u32::MAX), Duration::from_secs(1));
+    }
+
+    #[test]
+    fn test_backoff_with_jitter() {
+        let policy = policy(true);
+        for retry in 0..10 {
+            assert!(policy.backoff(retry) <= policy(false).backoff(retry));
+        }
+    }
+
+    #[test]
+    fn test_is_rate_limit_response() {
+        let rate_limited =
+            json!({"jsonrpc": "2.0", "id": 0, "error": {"code": 429, "message": "slow down"}});
+        let limit_exceeded = json!({"jsonrpc": "2.0", "id": 1, "error": {"code": -32005, "message": "limit exceeded"}});
+        let by_message = json!({"jsonrpc": "2.0", "id": 2, "error": {"code": -32000, "message": "Rate limit reached"}});
+        let other_error =
+            json!({"jsonrpc": "2.0", "id": 3, "error": {"code": 24, "message": "Block not found"}});
+        let result = json!({"jsonrpc": "2.0", "id": 4, "result": 1234});
+
+        assert!(is_rate_limit_response(&rate_limited));
+        assert!(is_rate_limit_response(&limit_exceeded));
+        assert!(is_rate_limit_response(&by_message));
+        assert!(!is_rate_limit_response(&other_error));
+        assert!(!is_rate_limit_response(&result));
+        assert!(is_rate_limit_response(&json!([
+            result.clone(),
+            rate_limited
+        ])));
+        assert!(!is_rate_limit_response(&json!([result, other_error])));
+    }
+
+    #[test]
+    fn test_is_idempotent() {
+        assert!(is_idempotent("starknet_getBlockWithTxs"));
+        assert!(!is_idempotent("starknet_addInvokeTransaction"));
+    }
+
+    #[tokio::test]
+    async fn test_rate_limiter() {
+        let rate_limiter = RateLimiter::new(20);
+
+        let start = Instant::now();
+        for _ in 0..5 {
+            rate_limiter.acquire().await;
+        }
+
+        // The first request is sent right away, the next ones 50ms apart
+        assert!(start.elapsed() >= Duration::from_millis(200));
+    }
+}
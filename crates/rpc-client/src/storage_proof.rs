@@ -0,0 +1,585 @@
+use std::collections::{HashMap, HashSet};
+
+use async_trait::async_trait;
+use serde::Deserialize;
+use serde_json::json;
+use starknet_types_core::felt::Felt;
+
+use crate::pathfinder::client::{post_jsonrpc_request, ClientError};
+use crate::pathfinder::proofs::{
+    ContractData, EdgePath, PathfinderClassProof, PathfinderProof, TrieNode,
+};
+use crate::proof_provider::ProofProvider;
+use crate::transport::{RpcTransport, TransportConfig, TransportError, TransportMode};
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(untagged)]
+enum MerkleNode {
+    Binary {
+        left: Felt,
+        right: Felt,
+    },
+    Edge {
+        path: Felt,
+        length: u64,
+        child: Felt,
+    },
+}
+
+impl From<MerkleNode> for TrieNode {
+    fn from(node: MerkleNode) -> Self {
+        match node {
+            MerkleNode::Binary { left, right } => TrieNode::Binary { left, right },
+            MerkleNode::Edge {
+                path,
+                length,
+                child,
+            } => TrieNode::Edge {
+                child,
+                path: EdgePath {
+                    len: length,
+                    value: path,
+                },
+            },
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct NodeHashToNode {
+    node_hash: Felt,
+    node: MerkleNode,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct ContractLeafData {
+    class_hash: Felt,
+    /// Not returned by every implementation, the root is found from the nodes otherwise.
+    #[serde(default)]
+    storage_root: Option<Felt>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct ContractsProof {
+    nodes: Vec<NodeHashToNode>,
+    contract_leaves_data: Vec<ContractLeafData>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct GlobalRoots {
+    contracts_tree_root: Felt,
+    classes_tree_root: Felt,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct StorageProof {
+    classes_proof: Vec<NodeHashToNode>,
+    contracts_proof: ContractsProof,
+    contracts_storage_proofs: Vec<Vec<NodeHashToNode>>,
+    global_roots: GlobalRoots,
+}
+
+fn to_node_map(nodes: Vec<NodeHashToNode>) -> HashMap<Felt, TrieNode> {
+    nodes
+        .into_iter()
+        .map(|node| (node.node_hash, node.node.into()))
+        .collect()
+}
+
+/// Follows `key` from `root` through the nodes of a proof, and returns the nodes on the way
+/// ordered from the root, as Pathfinder does.
+fn proof_path(nodes: &HashMap<Felt, TrieNode>, root: Felt, key: Felt) -> Vec<TrieNode> {
+    let bits = key.to_bits_be();
+    let mut path = vec![];
+    let mut node_hash = root;
+
+    // The tree height is 251, so the first 5 bits are ignored.
+    let mut index = 5;
+
+    while let Some(node) = nodes.get(&node_hash) {
+        path.push(node.clone());
+        match node {
+            TrieNode::Binary { left, right } => {
+                node_hash = if bits[index] { *right } else { *left };
+                index += 1;
+            }
+            TrieNode::Edge { child, path } => {
+                let path_len = path.len as usize;
+                if index + path_len > bits.len() {
+                    break;
+                }
+                let path_bits = path.value.to_bits_be();
+                // A diverging edge proves that the key is not in the tree
+                if path_bits[path_bits.len() - path_len..] != bits[index..index + path_len] {
+                    break;
+                }
+                node_hash = *child;
+                index += path_len;
+            }
+        }
+        if index >= bits.len() {
+            break;
+        }
+    }
+
+    path
+}
+
+/// Returns the only node that is not the child of another node, or zero for an empty proof.
+fn find_root(nodes: &HashMap<Felt, TrieNode>) -> Felt {
+    let children: HashSet<Felt> = nodes
+        .values()
+        .flat_map(|node| match node {
+            TrieNode::Binary { left, right } => vec![*left, *right],
+            TrieNode::Edge { child, .. } => vec![*child],
+        })
+        .collect();
+
+    nodes
+        .keys()
+        .find(|node_hash| !children.contains(node_hash))
+        .copied()
+        .unwrap_or(Felt::ZERO)
+}
+
+/// Path of the RPC spec v0.8 endpoint, relative to the base URL of the node.
+pub const STORAGE_PROOF_ENDPOINT: &str = "rpc/v0_8";
+
+/// Client for the `starknet_getStorageProof` method of the RPC spec v0.8.
+///
+/// The method returns the nodes of the proofs in no particular order, they are converted
+/// to the Pathfinder format so that both can be used interchangeably.
+pub struct StorageProofRpcClient {
+    transport: RpcTransport,
+}
+
+impl StorageProofRpcClient {
+    pub fn new(base_url: &str) -> Result<Self, TransportError> {
+        Self::with_transport_mode(base_url, TransportMode::Live)
+    }
+
+    pub fn with_transport_mode(
+        base_url: &str,
+        mode: TransportMode,
+    ) -> Result<Self, TransportError> {
+        Self::with_config(base_url, mode, TransportConfig::default())
+    }
+
+    pub fn with_config(
+        base_url: &str,
+        mode: TransportMode,
+        config: TransportConfig,
+    ) -> Result<Self, TransportError> {
+        let transport = RpcTransport::with_config(base_url, STORAGE_PROOF_ENDPOINT, mode, config)?;
+
+        Ok(Self::from_transport(transport))
+    }
+
+    pub(crate) fn from_transport(transport: RpcTransport) -> Self {
+        Self { transport }
+    }
+
+    /// Version of the RPC spec implemented by the endpoint.
+    pub async fn spec_version(&self) -> Result<String, ClientError> {
+        post_jsonrpc_request(&self.transport, "starknet_specVersion", json!([])).await
+    }
+
+    async fn get_storage_proof(
+        &self,
+        block_number: u64,
+        mut params: serde_json::Value,
+    ) -> Result<StorageProof, ClientError> {
+        params["block_id"] = json!({ "block_number": block_number });
+        post_jsonrpc_request(&self.transport, "starknet_getStorageProof", params).await
+    }
+}
+
+/// Converts the proof of the contract at `contract_address` to the Pathfinder format.
+///
+/// `storage_nodes` are the nodes of the storage proofs of the contract, and `keys` the keys
+/// to prove.
+fn to_pathfinder_proof(
+    contract_nodes: &HashMap<Felt, TrieNode>,
+    global_roots: &GlobalRoots,
+    contract_address: Felt,
+    leaf: &ContractLeafData,
+    storage_nodes: &HashMap<Felt, TrieNode>,
+    keys: &[Felt],
+) -> PathfinderProof {
+    let contract_proof = proof_path(
+        contract_nodes,
+        global_roots.contracts_tree_root,
+        contract_address,
+    );
+
+    // Pathfinder has no contract data for contracts that are not deployed
+    let contract_data = (leaf.class_hash != Felt::ZERO).then(|| {
+        let root = leaf
+            .storage_root
+            .unwrap_or_else(|| find_root(storage_nodes));
+        let storage_proofs = keys
+            .iter()
+            .map(|key| proof_path(storage_nodes, root, *key))
+            .collect();
+        ContractData {
+            root,
+            storage_proofs,
+        }
+    });
+
+    PathfinderProof {
+        // Not part of the response, and not needed to build the OS input
+        state_commitment: None,
+        class_commitment: Some(global_roots.classes_tree_root),
+        contract_proof,
+        contract_data,
+    }
+}
+
+fn invalid_response(message: String) -> ClientError {
+    ClientError::CustomError(format!(
+        "Invalid starknet_getStorageProof response: {message}"
+    ))
+}
+
+#[async_trait]
+impl ProofProvider for StorageProofRpcClient {
+    async fn get_proof(
+        &self,
+        block_number: u64,
+        contract_address: Felt,
+        keys: &[Felt],
+    ) -> Result<PathfinderProof, ClientError> {
+        let mut proofs = self
+            .get_proofs(block_number, &[(contract_address, keys.to_vec())])
+            .await?;
+
+        Ok(proofs.remove(0))
+    }
+
+    async fn get_proofs(
+        &self,
+        block_number: u64,
+        contracts: &[(Felt, Vec<Felt>)],
+    ) -> Result<Vec<PathfinderProof>, ClientError> {
+        let (proofs, _) = self.get_block_proofs(block_number, contracts, &[]).await?;
+
+        Ok(proofs)
+    }
+
+    async fn get_class_proof(
+        &self,
+        block_number: u64,
+        class_hash: Felt,
+    ) -> Result<PathfinderClassProof, ClientError> {
+        let mut proofs = self.get_class_proofs(block_number, &[class_hash]).await?;
+
+        Ok(proofs.remove(0))
+    }
+
+    async fn get_class_proofs(
+        &self,
+        block_number: u64,
+        class_hashes: &[Felt],
+    ) -> Result<Vec<PathfinderClassProof>, ClientError> {
+        let (_, class_proofs) = self
+            .get_block_proofs(block_number, &[], class_hashes)
+            .await?;
+
+        Ok(class_proofs)
+    }
+
+    /// Fetches the proofs of all the contracts and classes in a single request. A contract
+    /// can appear several times with different keys, its keys are then requested together.
+    async fn get_block_proofs(
+        &self,
+        block_number: u64,
+        contracts: &[(Felt, Vec<Felt>)],
+        class_hashes: &[Felt],
+    ) -> Result<(Vec<PathfinderProof>, Vec<PathfinderClassProof>), ClientError> {
+        if contracts.is_empty() && class_hashes.is_empty() {
+            return Ok((vec![], vec![]));
+        }
+
+        let mut contract_addresses: Vec<Felt> = vec![];
+        let mut keys_by_address: HashMap<Felt, Vec<Felt>> = HashMap::new();
+        for (contract_address, keys) in contracts {
+            keys_by_address
+                .entry(*contract_address)
+                .or_insert_with(|| {
+                    contract_addresses.push(*contract_address);
+                    vec![]
+                })
+                .extend(keys);
+        }
+        let contracts_storage_keys: Vec<_> = contract_addresses
+            .iter()
+            .filter(|contract_address| !keys_by_address[*contract_address].is_empty())
+            .map(|contract_address| {
+                json!({ "contract_address": contract_address, "storage_keys": keys_by_address[contract_address] })
+            })
+            .collect();
+
+        let mut params = json!({});
+        if !class_hashes.is_empty() {
+            params["class_hashes"] = json!(class_hashes);
+        }
+        if !contract_addresses.is_empty() {
+            params["contract_addresses"] = json!(contract_addresses);
+            params["contracts_storage_keys"] = json!(contracts_storage_keys);
+        }
+        log::debug!(
+            "querying starknet_getStorageProof for {} contracts and {} classes",
+            contract_addresses.len(),
+            class_hashes.len()
+        );
+        let proof = self.get_storage_proof(block_number, params).await?;
+
+        let class_nodes = to_node_map(proof.classes_proof);
+        let class_commitment = proof.global_roots.classes_tree_root;
+        let class_proofs = class_hashes
+            .iter()
+            .map(|class_hash| PathfinderClassProof {
+                class_commitment,
+                class_proof: proof_path(&class_nodes, class_commitment, *class_hash),
+            })
+            .collect();
+
+        if contracts.is_empty() {
+            return Ok((vec![], class_proofs));
+        }
+
+        // Leaves and storage proofs come in the order of the request
+        if proof.contracts_proof.contract_leaves_data.len() != contract_addresses.len() {
+            return Err(invalid_response(format!(
+                "{} contract leaves for {} contracts",
+                proof.contracts_proof.contract_leaves_data.len(),
+                contract_addresses.len()
+            )));
+        }
+        if proof.contracts_storage_proofs.len() != contracts_storage_keys.len() {
+            return Err(invalid_response(format!(
+                "{} storage proofs for {} contracts with keys",
+                proof.contracts_storage_proofs.len(),
+                contracts_storage_keys.len()
+            )));
+        }
+
+        let contract_nodes = to_node_map(proof.contracts_proof.nodes);
+        let mut storage_proofs = proof.contracts_storage_proofs.into_iter();
+        let contract_states: HashMap<Felt, (ContractLeafData, HashMap<Felt, TrieNode>)> =
+            contract_addresses
+                .iter()
+                .zip(proof.contracts_proof.contract_leaves_data)
+                .map(|(contract_address, leaf)| {
+                    let storage_nodes = match keys_by_address[contract_address].is_empty() {
+                        true => HashMap::new(),
+                        false => to_node_map(storage_proofs.next().unwrap_or_default()),
+                    };
+                    (*contract_address, (leaf, storage_nodes))
+                })
+                .collect();
+
+        let proofs = contracts
+            .iter()
+            .map(|(contract_address, keys)| {
+                let (leaf, storage_nodes) = &contract_states[contract_address];
+                to_pathfinder_proof(
+                    &contract_nodes,
+                    &proof.global_roots,
+                    *contract_address,
+                    leaf,
+                    storage_nodes,
+                    keys,
+                )
+            })
+            .collect();
+
+        Ok((proofs, class_proofs))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use arcane_os::crypto::pedersen::PedersenHash;
+    use arcane_os::crypto::poseidon::PoseidonHash;
+
+    use super::*;
+    use crate::pathfinder::proofs::{verify_proof, ProofVerificationError};
+    use crate::test_utils::MockNode;
+
+    /// Proof of two contracts, 0x100 with the storage `{0x10: 0x64, 0x11: 0xc8}` and 0x101
+    /// with an empty storage, and of the class 0x200.
+    const STORAGE_PROOF_RESPONSE: &str = include_str!("../resources/storage_proof_v0_8.json");
+
+    fn felt(value: u64) -> Felt {
+        Felt::from(value)
+    }
+
+    fn response() -> serde_json::Value {
+        serde_json::from_str(STORAGE_PROOF_RESPONSE).unwrap()
+    }
+
+    fn storage_proof() -> StorageProof {
+        serde_json::from_value(response()["result"].clone()).unwrap()
+    }
+
+    #[test]
+    fn test_proof_path() {
+        let proof = storage_proof();
+
+        let contract_nodes = to_node_map(proof.contracts_proof.nodes);
+        let contracts_root = proof.global_roots.contracts_tree_root;
+        for contract_address in [felt(0x100), felt(0x101)] {
+            let path = proof_path(&contract_nodes, contracts_root, contract_address);
+            assert_eq!(path.len(), 2);
+            verify_proof::<PedersenHash>(contract_address, contracts_root, &path).unwrap();
+        }
+
+        let storage_nodes = to_node_map(proof.contracts_storage_proofs[0].clone());
+        let storage_root = proof.contracts_proof.contract_leaves_data[0]
+            .storage_root
+            .unwrap();
+        for key in [felt(0x10), felt(0x11)] {
+            let path = proof_path(&storage_nodes, storage_root, key);
+            assert_eq!(path.len(), 2);
+            verify_proof::<PedersenHash>(key, storage_root, &path).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_proof_path_stops_at_diverging_edge() {
+        let proof = storage_proof();
+        let storage_nodes = to_node_map(proof.contracts_storage_proofs[0].clone());
+        let storage_root = proof.contracts_proof.contract_leaves_data[0]
+            .storage_root
+            .unwrap();
+
+        let path = proof_path(&storage_nodes, storage_root, felt(0x12));
+        assert_eq!(path.len(), 1);
+        assert!(matches!(
+            verify_proof::<PedersenHash>(felt(0x12), storage_root, &path),
+            Err(ProofVerificationError::NonExistenceProof { .. })
+        ));
+    }
+
+    #[test]
+    fn test_class_proof_path() {
+        let proof = storage_proof();
+        let class_nodes = to_node_map(proof.classes_proof);
+        let classes_root = proof.global_roots.classes_tree_root;
+
+        let path = proof_path(&class_nodes, classes_root, felt(0x200));
+        assert_eq!(path.len(), 1);
+        verify_proof::<PoseidonHash>(felt(0x200), classes_root, &path).unwrap();
+
+        // The path of a class that is not in the tree ends at the diverging edge
+        let path = proof_path(&class_nodes, classes_root, felt(0x300));
+        assert_eq!(path.len(), 1);
+        assert!(verify_proof::<PoseidonHash>(felt(0x300), classes_root, &path).is_err());
+    }
+
+    #[test]
+    fn test_find_root() {
+        let proof = storage_proof();
+
+        let storage_nodes = to_node_map(proof.contracts_storage_proofs[0].clone());
+        assert_eq!(
+            find_root(&storage_nodes),
+            proof.contracts_proof.contract_leaves_data[0]
+                .storage_root
+                .unwrap()
+        );
+        let contract_nodes = to_node_map(proof.contracts_proof.nodes);
+        assert_eq!(
+            find_root(&contract_nodes),
+            proof.global_roots.contracts_tree_root
+        );
+        assert_eq!(find_root(&HashMap::new()), Felt::ZERO);
+    }
+
+    #[tokio::test]
+    async fn test_get_proofs_in_one_request() {
+        let node = MockNode::start(vec![(200, response())]).await;
+        let client = StorageProofRpcClient::new(node.url()).unwrap();
+
+        let contracts = [
+            (felt(0x100), vec![felt(0x10)]),
+            (felt(0x101), vec![]),
+            (felt(0x100), vec![felt(0x11)]),
+        ];
+        let proofs = client.get_proofs(42, &contracts).await.unwrap();
+
+        let requests = node.requests();
+        assert_eq!(requests.len(), 1);
+        assert_eq!(
+            requests[0]["params"],
+            json!({
+                "block_id": { "block_number": 42 },
+                "contract_addresses": ["0x100", "0x101"],
+                "contracts_storage_keys": [{ "contract_address": "0x100", "storage_keys": ["0x10", "0x11"] }],
+            })
+        );
+
+        assert_eq!(proofs.len(), 3);
+        for (proof, (_, keys)) in proofs.iter().zip(&contracts) {
+            let contract_data = proof.contract_data.as_ref().unwrap();
+            assert_eq!(contract_data.storage_proofs.len(), keys.len());
+            contract_data.verify(keys).unwrap();
+        }
+        assert_eq!(proofs[1].contract_data.as_ref().unwrap().root, Felt::ZERO);
+    }
+
+    #[tokio::test]
+    async fn test_get_class_proofs_in_one_request() {
+        let node = MockNode::start(vec![(200, response())]).await;
+        let client = StorageProofRpcClient::new(node.url()).unwrap();
+
+        let proofs = client
+            .get_class_proofs(42, &[felt(0x200), felt(0x300)])
+            .await
+            .unwrap();
+
+        assert_eq!(node.requests().len(), 1);
+        assert_eq!(proofs.len(), 2);
+        proofs[0].verify(felt(0x200)).unwrap();
+        assert!(proofs[1].verify(felt(0x300)).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_get_block_proofs_in_one_request() {
+        let node = MockNode::start(vec![(200, response())]).await;
+        let client = StorageProofRpcClient::new(node.url()).unwrap();
+
+        let contracts = [
+            (felt(0x100), vec![felt(0x10), felt(0x11)]),
+            (felt(0x101), vec![]),
+        ];
+        let (proofs, class_proofs) = client
+            .get_block_proofs(42, &contracts, &[felt(0x200)])
+            .await
+            .unwrap();
+
+        let requests = node.requests();
+        assert_eq!(requests.len(), 1);
+        assert_eq!(
+            requests[0]["params"],
+            json!({
+                "block_id": { "block_number": 42 },
+                "class_hashes": ["0x200"],
+                "contract_addresses": ["0x100", "0x101"],
+                "contracts_storage_keys": [{ "contract_address": "0x100", "storage_keys": ["0x10", "0x11"] }],
+            })
+        );
+
+        assert_eq!(proofs.len(), 2);
+        proofs[0]
+            .contract_data
+            .as_ref()
+            .unwrap()
+            .verify(&contracts[0].1)
+            .unwrap();
+        assert_eq!(class_proofs.len(), 1);
+        class_proofs[0].verify(felt(0x200)).unwrap();
+    }
+}
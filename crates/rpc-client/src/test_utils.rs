@@ -0,0 +1,134 @@
+//! A local HTTP server standing in for a JSON-RPC node in tests.
+
+use std::sync::{Arc, Mutex};
+
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+
+/// Answers the n-th request it receives with the n-th response, and repeats the last one once
+/// they are all used.
+pub(crate) struct MockNode {
+    url: String,
+    requests: Arc<Mutex<Vec<serde_json::Value>>>,
+}
+
+impl MockNode {
+    /// Starts a node answering with `(HTTP status, body)` responses.
+    pub(crate) async fn start(responses: Vec<(u16, serde_json::Value)>) -> Self {
+        Self::start_with_dropped_responses(0, responses).await
+    }
+
+    /// Starts a node that closes the connection in the middle of its response to each of the
+    /// first `n_dropped` requests, like a connection reset, then answers with `responses`.
+    pub(crate) async fn start_with_dropped_responses(
+        n_dropped: usize,
+        responses: Vec<(u16, serde_json::Value)>,
+    ) -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let requests = Arc::new(Mutex::new(Vec::new()));
+
+        let node_requests = requests.clone();
+        tokio::spawn(async move {
+            loop {
+                let Ok((stream, _)) = listener.accept().await else {
+                    return;
+                };
+                tokio::spawn(serve(
+                    stream,
+                    node_requests.clone(),
+                    n_dropped,
+                    responses.clone(),
+                ));
+            }
+        });
+
+        Self { url, requests }
+    }
+
+    pub(crate) fn url(&self) -> &str {
+        &self.url
+    }
+
+    /// Bodies of the requests received so far.
+    pub(crate) fn requests(&self) -> Vec<serde_json::Value> {
+        self.requests.lock().unwrap().clone()
+    }
+}
+
+/// Answers the requests of a connection until the client closes it.
+async fn serve(
+    mut stream: TcpStream,
+    requests: Arc<Mutex<Vec<serde_json::Value>>>,
+    n_dropped: usize,
+    responses: Vec<(u16, serde_json::Value)>,
+) {
+    let mut buffer = Vec::new();
+    loop {
+        let Some(body) = read_request(&mut stream, &mut buffer).await else {
+            return;
+        };
+        let (request_index, (status, response)) = {
+            let mut requests = requests.lock().unwrap();
+            requests.push(serde_json::from_slice(&body).unwrap_or_default());
+            let request_index = requests.len() - 1;
+            let response_index = request_index.saturating_sub(n_dropped);
+            (
+                request_index,
+                responses[response_index.min(responses.len() - 1)].clone(),
+            )
+        };
+
+        let response = response.to_string();
+        let http_response = format!(
+            "HTTP/1.1 {status} Mock\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\r\n{response}",
+            response.len()
+        );
+        if request_index < n_dropped {
+            // Send half of the body, then close the connection
+            let truncated_length = http_response.len() - response.len() / 2;
+            let _ = stream
+                .write_all(&http_response.as_bytes()[..truncated_length])
+                .await;
+            return;
+        }
+        if stream.write_all(http_response.as_bytes()).await.is_err() {
+            return;
+        }
+    }
+}
+
+/// Reads the next HTTP request of a connection and returns its body.
+async fn read_request(stream: &mut TcpStream, buffer: &mut Vec<u8>) -> Option<Vec<u8>> {
+    let header_end = loop {
+        if let Some(position) = buffer.windows(4).position(|window| window == b"\r\n\r\n") {
+            break position + 4;
+        }
+        read_more(stream, buffer).await?;
+    };
+
+    let headers = String::from_utf8_lossy(&buffer[..header_end]).to_lowercase();
+    let content_length: usize = headers
+        .lines()
+        .find_map(|line| line.strip_prefix("content-length:"))
+        .and_then(|length| length.trim().parse().ok())
+        .unwrap_or(0);
+    while buffer.len() < header_end + content_length {
+        read_more(stream, buffer).await?;
+    }
+
+    let body = buffer[header_end..header_end + content_length].to_vec();
+    buffer.drain(..header_end + content_length);
+    Some(body)
+}
+
+async fn read_more(stream: &mut TcpStream, buffer: &mut Vec<u8>) -> Option<()> {
+    let mut chunk = [0; 4096];
+    match stream.read(&mut chunk).await {
+        Ok(0) | Err(_) => None,
+        Ok(n) => {
+            buffer.extend_from_slice(&chunk[..n]);
+            Some(())
+        }
+    }
+}
@@ -0,0 +1,718 @@
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{BufReader, BufWriter};
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use async_trait::async_trait;
+use reqwest::header::HeaderMap;
+use reqwest::{Certificate, StatusCode};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use starknet::providers::jsonrpc::{JsonRpcMethod, JsonRpcResponse, JsonRpcTransport};
+
+use crate::disk_cache::DiskCache;
+use crate::retry::{
+    is_idempotent, is_rate_limit_response, is_retryable_error, is_retryable_status, RateLimiter,
+    RetryPolicy,
+};
+
+#[derive(Debug, thiserror::Error)]
+pub enum TransportError {
+    #[error("Encountered a request error: {0}")]
+    ReqwestError(#[from] reqwest::Error),
+    #[error("Encountered a serialization error: {0}")]
+    SerdeError(#[from] serde_json::Error),
+    #[error("Encountered an IO error: {0}")]
+    IoError(#[from] std::io::Error),
+    #[error("Received response: {status:?} Error: {error}")]
+    HttpError { status: StatusCode, error: String },
+    #[error("No recorded response for {method} with params {params}")]
+    MissingRecording { method: String, params: String },
+    #[error("Invalid response to a batch request: {0}")]
+    InvalidBatchResponse(String),
+    #[error("Rate limited by the node: {0}")]
+    RateLimited(String),
+    #[error("{method} failed after {retries} retries: {error}")]
+    RetriesExhausted {
+        method: String,
+        retries: u32,
+        #[source]
+        error: Box<TransportError>,
+    },
+}
+
+impl TransportError {
+    /// Whether the request may succeed if sent again.
+    fn is_retryable(&self) -> bool {
+        match self {
+            TransportError::ReqwestError(error) => is_retryable_error(error),
+            TransportError::HttpError { status, .. } => is_retryable_status(*status),
+            TransportError::RateLimited(_) => true,
+            _ => false,
+        }
+    }
+}
+
+/// A request/response pair, as saved on disk.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct RecordedRequest {
+    endpoint: String,
+    method: String,
+    params: serde_json::Value,
+    response: serde_json::Value,
+}
+
+/// JSON-RPC responses by endpoint, method and params.
+///
+/// Entries are kept sorted so that recording the same block twice produces the same file.
+#[derive(Debug, Default)]
+pub struct RpcRecording {
+    entries: Mutex<BTreeMap<(String, String, String), RecordedRequest>>,
+}
+
+impl RpcRecording {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn load(path: &Path) -> Result<Self, TransportError> {
+        let file = File::open(path)?;
+        let requests: Vec<RecordedRequest> = serde_json::from_reader(BufReader::new(file))?;
+        let entries = requests
+            .into_iter()
+            .map(|request| {
+                let key = (
+                    request.endpoint.clone(),
+                    request.method.clone(),
+                    request.params.to_string(),
+                );
+                (key, request)
+            })
+            .collect();
+
+        Ok(Self {
+            entries: Mutex::new(entries),
+        })
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), TransportError> {
+        let entries = self.entries.lock().unwrap();
+        let requests: Vec<_> = entries.values().collect();
+        let file = File::create(path)?;
+        serde_json::to_writer(BufWriter::new(file), &requests)?;
+
+        Ok(())
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.lock().unwrap().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn get(
+        &self,
+        endpoint: &str,
+        method: &str,
+        params: &serde_json::Value,
+    ) -> Option<serde_json::Value> {
+        let key = (endpoint.to_string(), method.to_string(), params.to_string());
+        self.entries
+            .lock()
+            .unwrap()
+            .get(&key)
+            .map(|request| request.response.clone())
+    }
+
+    fn insert(
+        &self,
+        endpoint: &str,
+        method: &str,
+        params: serde_json::Value,
+        response: serde_json::Value,
+    ) {
+        let key = (endpoint.to_string(), method.to_string(), params.to_string());
+        let request = RecordedRequest {
+            endpoint: endpoint.to_string(),
+            method: method.to_string(),
+            params,
+            response,
+        };
+        self.entries.lock().unwrap().insert(key, request);
+    }
+}
+
+/// How a transport handles requests.
+#[derive(Debug, Clone, Default)]
+pub enum TransportMode {
+    /// Requests are sent to the node.
+    #[default]
+    Live,
+    /// Requests are sent to the node and every response is added to the recording.
+    Record(Arc<RpcRecording>),
+    /// Requests are answered from the recording, nothing is sent to the node.
+    Replay(Arc<RpcRecording>),
+}
+
+impl TransportMode {
+    pub fn recording(&self) -> Option<&Arc<RpcRecording>> {
+        match self {
+            TransportMode::Live => None,
+            TransportMode::Record(recording) | TransportMode::Replay(recording) => Some(recording),
+        }
+    }
+}
+
+/// TLS settings of a transport, on top of the system root certificates.
+#[derive(Debug, Clone, Default)]
+pub struct TlsConfig {
+    /// Additional trusted root certificates, e.g. for a node behind a private CA.
+    pub root_certificates: Vec<Certificate>,
+    /// Accepts any server certificate. Only meant for local development.
+    pub accept_invalid_certs: bool,
+}
+
+/// Network settings of a transport.
+#[derive(Debug, Clone)]
+pub struct TransportConfig {
+    pub retry_policy: RetryPolicy,
+    /// Timeout of each HTTP request, retries excluded.
+    pub timeout: Option<Duration>,
+    /// Headers sent with every request, e.g. the API key of a hosted node.
+    pub headers: HeaderMap,
+    pub tls: TlsConfig,
+    /// Limits the rate of the requests, shared by every transport holding it.
+    pub rate_limiter: Option<Arc<RateLimiter>>,
+    /// Serves immutable data from disk instead of the node, when set.
+    pub disk_cache: Option<Arc<DiskCache>>,
+}
+
+impl Default for TransportConfig {
+    fn default() -> Self {
+        Self {
+            retry_policy: RetryPolicy::default(),
+            timeout: Some(Duration::from_secs(60)),
+            headers: HeaderMap::new(),
+            tls: TlsConfig::default(),
+            rate_limiter: None,
+            disk_cache: None,
+        }
+    }
+}
+
+/// JSON-RPC transport shared by the starknet-rs and the Pathfinder clients.
+#[derive(Debug, Clone)]
+pub struct RpcTransport {
+    http_client: reqwest::Client,
+    url: String,
+    /// Name of the endpoint, the path relative to the base URL by default, e.g. `rpc/v0_7`.
+    /// Recordings are keyed on it rather than on the full URL so they can be replayed anywhere.
+    endpoint: String,
+    mode: TransportMode,
+    retry_policy: RetryPolicy,
+    rate_limiter: Option<Arc<RateLimiter>>,
+    disk_cache: Option<Arc<DiskCache>>,
+}
+
+impl RpcTransport {
+    pub fn new(
+        base_url: &str,
+        endpoint: &str,
+        mode: TransportMode,
+    ) -> Result<Self, TransportError> {
+        Self::with_config(base_url, endpoint, mode, TransportConfig::default())
+    }
+
+    /// Fails if the TLS settings cannot be loaded.
+    pub fn with_config(
+        base_url: &str,
+        endpoint: &str,
+        mode: TransportMode,
+        config: TransportConfig,
+    ) -> Result<Self, TransportError> {
+        let url = format!("{}/{}", base_url, endpoint);
+        Self::with_url(&url, endpoint, mode, config)
+    }
+
+    /// Creates a transport that sends requests to `url`, for nodes whose endpoints do not
+    /// follow the `<base URL>/<endpoint>` layout. `endpoint` only names the endpoint in
+    /// recordings and in the disk cache.
+    ///
+    /// Fails if the TLS settings cannot be loaded.
+    pub fn with_url(
+        url: &str,
+        endpoint: &str,
+        mode: TransportMode,
+        config: TransportConfig,
+    ) -> Result<Self, TransportError> {
+        let mut builder = reqwest::ClientBuilder::new()
+            .default_headers(config.headers)
+            .danger_accept_invalid_certs(config.tls.accept_invalid_certs);
+        for certificate in config.tls.root_certificates {
+            builder = builder.add_root_certificate(certificate);
+        }
+        if let Some(timeout) = config.timeout {
+            builder = builder.timeout(timeout);
+        }
+        let http_client = builder.build()?;
+
+        Ok(Self {
+            http_client,
+            url: url.to_string(),
+            endpoint: endpoint.to_string(),
+            mode,
+            retry_policy: config.retry_policy,
+            rate_limiter: config.rate_limiter,
+            disk_cache: config.disk_cache,
+        })
+    }
+
+    pub fn url(&self) -> String {
+        self.url.clone()
+    }
+
+    /// Sends a JSON-RPC request and returns the raw response body.
+    pub async fn send(
+        &self,
+        method: &str,
+        params: serde_json::Value,
+    ) -> Result<serde_json::Value, TransportError> {
+        match &self.mode {
+            TransportMode::Live => self.send_or_get_cached(method, &params).await,
+            TransportMode::Record(recording) => {
+                let response = self.send_or_get_cached(method, &params).await?;
+                recording.insert(&self.endpoint, method, params, response.clone());
+                Ok(response)
+            }
+            TransportMode::Replay(recording) => recording
+                .get(&self.endpoint, method, &params)
+                .ok_or_else(|| TransportError::MissingRecording {
+                    method: method.to_string(),
+                    params: params.to_string(),
+                }),
+        }
+    }
+
+    /// Answers a request from the disk cache, or sends it to the node and caches the response.
+    async fn send_or_get_cached(
+        &self,
+        method: &str,
+        params: &serde_json::Value,
+    ) -> Result<serde_json::Value, TransportError> {
+        if let Some(response) = self.get_cached(method, params) {
+            return Ok(response);
+        }
+
+        let request = json_rpc_request(0, method, params);
+        let response = self
+            .post_with_retries(method, is_idempotent(method), &request)
+            .await?;
+        if let Some(disk_cache) = &self.disk_cache {
+            disk_cache.insert(&self.endpoint, method, params, &response);
+        }
+
+        Ok(response)
+    }
+
+    fn get_cached(&self, method: &str, params: &serde_json::Value) -> Option<serde_json::Value> {
+        self.disk_cache
+            .as_ref()
+            .and_then(|disk_cache| disk_cache.get(&self.endpoint, method, params))
+    }
+
+    /// Sends several JSON-RPC requests in a single HTTP request, and returns the raw response
+    /// of each request in the order of `requests`.
+    ///
+    /// Requests are recorded one by one, so a batch can be replayed with single requests and
+    /// the other way around. Requests found in the disk cache are not sent.
+    pub async fn send_batch(
+        &self,
+        requests: Vec<(String, serde_json::Value)>,
+    ) -> Result<Vec<serde_json::Value>, TransportError> {
+        if let TransportMode::Replay(_) = &self.mode {
+            let mut responses = Vec::with_capacity(requests.len());
+            for (method, params) in requests {
+                responses.push(self.send(&method, params).await?);
+            }
+            return Ok(responses);
+        }
+
+        let cached_responses: Vec<_> = requests
+            .iter()
+            .map(|(method, params)| self.get_cached(method, params))
+            .collect();
+        let uncached_requests: Vec<_> = requests
+            .iter()
+            .zip(&cached_responses)
+            .filter(|(_, cached_response)| cached_response.is_none())
+            .map(|(request, _)| request.clone())
+            .collect();
+        let mut fetched_responses = self.post_batch(uncached_requests).await?.into_iter();
+
+        let mut responses = Vec::with_capacity(requests.len());
+        for ((method, params), cached_response) in requests.into_iter().zip(cached_responses) {
+            let response = match cached_response {
+                Some(response) => response,
+                None => fetched_responses
+                    .next()
+                    .expect("one response per uncached request"),
+            };
+            if let TransportMode::Record(recording) = &self.mode {
+                recording.insert(&self.endpoint, &method, params, response.clone());
+            }
+            responses.push(response);
+        }
+
+        Ok(responses)
+    }
+
+    /// Posts a batch request to the node and returns the responses in the order of `requests`.
+    async fn post_batch(
+        &self,
+        requests: Vec<(String, serde_json::Value)>,
+    ) -> Result<Vec<serde_json::Value>, TransportError> {
+        if requests.is_empty() {
+            return Ok(vec![]);
+        }
+
+        let description = format!("Batch of {} requests", requests.len());
+        let idempotent = requests.iter().all(|(method, _)| is_idempotent(method));
+        let batch: Vec<_> = requests
+            .iter()
+            .enumerate()
+            .map(|(id, (method, params))| json_rpc_request(id, method, params))
+            .collect();
+        let response = self
+            .post_with_retries(&description, idempotent, &serde_json::Value::Array(batch))
+            .await?;
+
+        // Responses to a batch can come in any order
+        let mut responses = vec![None; requests.len()];
+        for response in serde_json::from_value::<Vec<serde_json::Value>>(response)? {
+            let id = response["id"].as_u64().map(|id| id as usize);
+            match id.and_then(|id| responses.get_mut(id)) {
+                Some(slot) => *slot = Some(response),
+                None => return Err(TransportError::InvalidBatchResponse(response.to_string())),
+            }
+        }
+
+        let mut ordered_responses = Vec::with_capacity(requests.len());
+        for ((method, params), response) in requests.into_iter().zip(responses) {
+            let response = response.ok_or_else(|| {
+                TransportError::InvalidBatchResponse(format!("no response to {method}"))
+            })?;
+            if let Some(disk_cache) = &self.disk_cache {
+                disk_cache.insert(&self.endpoint, &method, &params, &response);
+            }
+            ordered_responses.push(response);
+        }
+
+        Ok(ordered_responses)
+    }
+
+    /// Posts a request to the node, retrying transient failures of idempotent requests
+    /// according to the retry policy.
+    ///
+    /// Once the request has been retried, a failure is returned as `RetriesExhausted` wrapping
+    /// the last error, whether or not that error is retryable.
+    async fn post_with_retries(
+        &self,
+        description: &str,
+        idempotent: bool,
+        body: &serde_json::Value,
+    ) -> Result<serde_json::Value, TransportError> {
+        let max_retries = if idempotent {
+            self.retry_policy.max_retries
+        } else {
+            0
+        };
+
+        let mut retries = 0;
+        loop {
+            if let Some(rate_limiter) = &self.rate_limiter {
+                rate_limiter.acquire().await;
+            }
+
+            let error = match self.post(body).await {
+                Ok(response) => return Ok(response),
+                Err(error) => error,
+            };
+            if !error.is_retryable() || retries == max_retries {
+                return Err(match retries {
+                    0 => error,
+                    _ => TransportError::RetriesExhausted {
+                        method: description.to_string(),
+                        retries,
+                        error: Box::new(error),
+                    },
+                });
+            }
+
+            let backoff = self.retry_policy.backoff(retries);
+            retries += 1;
+            log::warn!(
+                "{} failed: {}, retry {}/{} in {:?}",
+                description,
+                error,
+                retries,
+                max_retries,
+                backoff
+            );
+            tokio::time::sleep(backoff).await;
+        }
+    }
+
+    async fn post(&self, body: &serde_json::Value) -> Result<serde_json::Value, TransportError> {
+        let response = self.http_client.post(&self.url).json(body).send().await?;
+
+        match response.status() {
+            StatusCode::OK => {
+                let response: serde_json::Value = response.json().await?;
+                if is_rate_limit_response(&response) {
+                    return Err(TransportError::RateLimited(response.to_string()));
+                }
+                Ok(response)
+            }
+            status => {
+                let error = response.text().await?;
+                Err(TransportError::HttpError { status, error })
+            }
+        }
+    }
+}
+
+fn json_rpc_request(id: usize, method: &str, params: &serde_json::Value) -> serde_json::Value {
+    json!({
+        "jsonrpc": "2.0",
+        "id": id,
+        "method": method,
+        "params": params,
+    })
+}
+
+#[async_trait]
+impl JsonRpcTransport for RpcTransport {
+    type Error = TransportError;
+
+    async fn send_request<P, R>(
+        &self,
+        method: JsonRpcMethod,
+        params: P,
+    ) -> Result<JsonRpcResponse<R>, Self::Error>
+    where
+        P: Serialize + Send + Sync,
+        R: DeserializeOwned,
+    {
+        let method = serde_json::to_value(method)?;
+        let method = method.as_str().unwrap_or_default().to_string();
+        let params = serde_json::to_value(params)?;
+
+        let response = self.send(&method, params).await?;
+
+        Ok(serde_json::from_value(response)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::MockNode;
+
+    fn block_number_response() -> serde_json::Value {
+        json!({"jsonrpc": "2.0", "id": 0, "result": 1234})
+    }
+
+    #[tokio::test]
+    async fn test_record_then_replay() {
+        let node = MockNode::start(vec![(200, block_number_response())]).await;
+        let recording = Arc::new(RpcRecording::new());
+        let transport = RpcTransport::new(
+            node.url(),
+            "rpc/v0_7",
+            TransportMode::Record(recording.clone()),
+        )
+        .unwrap();
+        let response = transport
+            .send("starknet_blockNumber", json!([]))
+            .await
+            .unwrap();
+        assert_eq!(response, block_number_response());
+        assert_eq!(recording.len(), 1);
+
+        let path = std::env::temp_dir().join(format!(
+            "rpc-recording-{}-{}.json",
+            std::process::id(),
+            node.url().rsplit(':').next().unwrap()
+        ));
+        recording.save(&path).unwrap();
+        let recording = RpcRecording::load(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        // Nothing listens on port 9, the response must come from the recording
+        let transport = RpcTransport::new(
+            "http://127.0.0.1:9",
+            "rpc/v0_7",
+            TransportMode::Replay(Arc::new(recording)),
+        )
+        .unwrap();
+        let replayed_response = transport
+            .send("starknet_blockNumber", json!([]))
+            .await
+            .unwrap();
+        assert_eq!(replayed_response, block_number_response());
+        assert_eq!(node.requests().len(), 1);
+    }
+
+    fn retrying_transport(node: &MockNode) -> RpcTransport {
+        let config = TransportConfig {
+            retry_policy: RetryPolicy {
+                max_retries: 2,
+                initial_backoff: Duration::from_millis(1),
+                max_backoff: Duration::from_millis(1),
+                jitter: false,
+            },
+            ..TransportConfig::default()
+        };
+        RpcTransport::with_config(node.url(), "rpc/v0_7", TransportMode::Live, config).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_retry_transient_error() {
+        let node = MockNode::start(vec![
+            (503, json!("unavailable")),
+            (200, block_number_response()),
+        ])
+        .await;
+        let transport = retrying_transport(&node);
+
+        let response = transport
+            .send("starknet_blockNumber", json!([]))
+            .await
+            .unwrap();
+        assert_eq!(response, block_number_response());
+        assert_eq!(node.requests().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_retry_connection_reset() {
+        let node =
+            MockNode::start_with_dropped_responses(1, vec![(200, block_number_response())]).await;
+        let transport = retrying_transport(&node);
+
+        let response = transport
+            .send("starknet_blockNumber", json!([]))
+            .await
+            .unwrap();
+        assert_eq!(response, block_number_response());
+        assert_eq!(node.requests().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_connection_reset_not_retried_for_non_idempotent_request() {
+        let node =
+            MockNode::start_with_dropped_responses(1, vec![(200, block_number_response())]).await;
+        let transport = retrying_transport(&node);
+
+        let error = transport
+            .send("starknet_addInvokeTransaction", json!([]))
+            .await
+            .unwrap_err();
+        assert!(matches!(error, TransportError::ReqwestError(_)));
+        assert_eq!(node.requests().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_retry_rate_limit_error() {
+        let rate_limited = json!({"jsonrpc": "2.0", "id": 0, "error": {"code": 429, "message": "Too many requests"}});
+        let node = MockNode::start(vec![(200, rate_limited), (200, block_number_response())]).await;
+        let transport = retrying_transport(&node);
+
+        let response = transport
+            .send("starknet_blockNumber", json!([]))
+            .await
+            .unwrap();
+        assert_eq!(response, block_number_response());
+        assert_eq!(node.requests().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_retries_exhausted() {
+        let node = MockNode::start(vec![(503, json!("unavailable"))]).await;
+        let transport = retrying_transport(&node);
+
+        let error = transport
+            .send("starknet_blockNumber", json!([]))
+            .await
+            .unwrap_err();
+        assert!(matches!(
+            error,
+            TransportError::RetriesExhausted { retries: 2, .. }
+        ));
+        assert_eq!(node.requests().len(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_non_retryable_error_after_retries() {
+        let node = MockNode::start(vec![
+            (503, json!("unavailable")),
+            (400, json!("bad request")),
+        ])
+        .await;
+        let transport = retrying_transport(&node);
+
+        let error = transport
+            .send("starknet_blockNumber", json!([]))
+            .await
+            .unwrap_err();
+        let TransportError::RetriesExhausted { retries, error, .. } = error else {
+            panic!("expected RetriesExhausted, got {error}");
+        };
+        assert_eq!(retries, 1);
+        assert!(matches!(
+            *error,
+            TransportError::HttpError {
+                status: StatusCode::BAD_REQUEST,
+                ..
+            }
+        ));
+        assert_eq!(node.requests().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_non_idempotent_request_not_retried() {
+        let node = MockNode::start(vec![(503, json!("unavailable"))]).await;
+        let transport = retrying_transport(&node);
+
+        let error = transport
+            .send("starknet_addInvokeTransaction", json!([]))
+            .await
+            .unwrap_err();
+        assert!(matches!(error, TransportError::HttpError { .. }));
+        assert_eq!(node.requests().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_replay_missing_recording() {
+        let transport = RpcTransport::new(
+            "http://127.0.0.1:9",
+            "rpc/v0_7",
+            TransportMode::Replay(Arc::new(RpcRecording::new())),
+        )
+        .unwrap();
+
+        let error = transport
+            .send("starknet_blockNumber", json!([]))
+            .await
+            .unwrap_err();
+        assert!(matches!(
+            error,
+            TransportError::MissingRecording { method, params }
+                if method == "starknet_blockNumber" && params == "[]"
+        ));
+    }
+}
@@ -1,14 +1,15 @@
+use crate::network::NetworkProfile;
 use crate::utils::{felt_to_u128, FeltConversionError};
 use blockifier::blockifier::block::{BlockInfo, GasPrices};
 use blockifier::bouncer::BouncerConfig;
-use blockifier::context::{BlockContext, ChainInfo, FeeTokenAddresses};
+use blockifier::context::BlockContext;
 use blockifier::versioned_constants::{StarknetVersion, VersionedConstants};
 use starknet::core::types::{BlockWithTxs, L1DataAvailabilityMode};
 use starknet_api::block::{BlockNumber, BlockTimestamp};
 use starknet_api::contract_address;
-use starknet_api::core::ChainId;
 use starknet_types_core::felt::Felt;
 use std::num::NonZeroU128;
+use thiserror::Error;
 
 fn felt_to_gas_price(price: &Felt) -> Result<NonZeroU128, FeltConversionError> {
     if *price == Felt::ZERO {
@@ -20,10 +21,49 @@ fn felt_to_gas_price(price: &Felt) -> Result<NonZeroU128, FeltConversionError> {
     ))
 }
 
+#[derive(Error, Debug)]
+pub enum BlockContextError {
+    #[error("Felt Conversion Error: {0}")]
+    FeltConversionError(#[from] FeltConversionError),
+    #[error("Unsupported Starknet version: {0}")]
+    UnsupportedStarknetVersion(String),
+}
+
+/// Returns the blockifier constants matching the Starknet version of a block.
+///
+/// Blocks older than 0.13.0 use the oldest constants known by the blockifier.
+pub fn get_versioned_constants(
+    starknet_version: &str,
+) -> Result<&'static VersionedConstants, BlockContextError> {
+    let version = match starknet_version {
+        "0.13.0" => StarknetVersion::V0_13_0,
+        "0.13.1" => StarknetVersion::V0_13_1,
+        "0.13.1.1" => StarknetVersion::V0_13_1_1,
+        "0.13.2" => StarknetVersion::V0_13_2,
+        _ if is_before_0_13_0(starknet_version) => StarknetVersion::V0_13_0,
+        _ => {
+            return Err(BlockContextError::UnsupportedStarknetVersion(
+                starknet_version.to_string(),
+            ))
+        }
+    };
+
+    Ok(VersionedConstants::get(version))
+}
+
+/// Returns whether `starknet_version`, such as "0.12.3", is older than 0.13.0.
+fn is_before_0_13_0(starknet_version: &str) -> bool {
+    let components: Result<Vec<u64>, _> = starknet_version.split('.').map(str::parse).collect();
+    match components {
+        Ok(components) => components.as_slice() < [0, 13, 0].as_slice(),
+        Err(_) => false,
+    }
+}
+
 pub fn build_block_context(
-    chain_id: String,
+    network: &NetworkProfile,
     block: &BlockWithTxs,
-) -> Result<BlockContext, FeltConversionError> {
+) -> Result<BlockContext, BlockContextError> {
     let sequencer_address_hex = block.sequencer_address.to_hex_string();
     let sequencer_address = contract_address!(sequencer_address_hex.as_str());
     let use_kzg_da = match block.l1_da_mode {
@@ -44,25 +84,18 @@ pub fn build_block_context(
         use_kzg_da,
     };
 
-    let chain_info = ChainInfo {
-        chain_id: ChainId::Other(chain_id),
-        fee_token_addresses: FeeTokenAddresses {
-            strk_fee_token_address: contract_address!(
-                "0x04718f5a0fc34cc1af16a1cdee98ffb20c31f5cd61d6ab07201858f4287c938d"
-            ),
-            eth_fee_token_address: contract_address!(
-                "0x049d36570d4e46f48e99674bd3fcc84644ddd6b96f7c741b1562b82f9e004dc7"
-            ),
-        },
-    };
+    let chain_info = network.chain_info();
 
-    let versioned_constants = VersionedConstants::get(StarknetVersion::V0_13_1);
+    let versioned_constants = match &network.versioned_constants {
+        Some(custom_constants) => custom_constants.constants().clone(),
+        None => get_versioned_constants(&block.starknet_version)?.clone(),
+    };
     let bouncer_config = BouncerConfig::max();
 
     Ok(BlockContext::new(
         block_info,
         chain_info,
-        versioned_constants.clone(),
+        versioned_constants,
         bouncer_config,
     ))
 }
@@ -1,4 +1,5 @@
 pub mod block_context;
+pub mod network;
 pub mod rpc_state_reader;
 pub mod transaction;
 pub mod utils;
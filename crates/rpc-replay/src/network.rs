@@ -0,0 +1,156 @@
+use blockifier::context::{ChainInfo, FeeTokenAddresses};
+use blockifier::versioned_constants::VersionedConstants;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use starknet_api::contract_address;
+use starknet_api::core::{ChainId, ContractAddress};
+use std::fs::File;
+use std::io::BufReader;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use thiserror::Error;
+
+const STRK_FEE_TOKEN_ADDRESS: &str =
+    "0x04718f5a0fc34cc1af16a1cdee98ffb20c31f5cd61d6ab07201858f4287c938d";
+const ETH_FEE_TOKEN_ADDRESS: &str =
+    "0x049d36570d4e46f48e99674bd3fcc84644ddd6b96f7c741b1562b82f9e004dc7";
+const SEQUENCER_ADDRESS: &str =
+    "0x01176a1bd84444c89232ec27754698e5d2e7e1a7f1539f12027f28b23ec9f3d8";
+
+#[derive(Error, Debug)]
+pub enum NetworkProfileError {
+    #[error("IO Error: {0}")]
+    IoError(#[from] std::io::Error),
+    #[error("JSON Error: {0}")]
+    JsonError(#[from] serde_json::Error),
+    #[error("YAML Error: {0}")]
+    YamlError(#[from] serde_yaml::Error),
+}
+
+/// Chain-specific constants required to re-execute and prove blocks of a network.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct NetworkProfile {
+    pub name: String,
+    pub chain_id: ChainId,
+    pub strk_fee_token_address: ContractAddress,
+    pub eth_fee_token_address: ContractAddress,
+    /// Sequencer address used in the general config of the OS. Blocks specify their own.
+    pub sequencer_address: ContractAddress,
+    /// Versioned constants used for every block instead of the constants matching the
+    /// Starknet version of the block, for chains with custom constants.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub versioned_constants: Option<CustomVersionedConstants>,
+}
+
+/// Versioned constants loaded once with their contents, so that they can be embedded in a
+/// block witness.
+///
+/// Deserializes from the path of a versioned constants file, as written in network profiles,
+/// or from the contents of the file, as embedded in witnesses. Always serializes the contents.
+#[derive(Debug, Clone)]
+pub struct CustomVersionedConstants {
+    contents: serde_json::Value,
+    constants: Arc<VersionedConstants>,
+}
+
+impl CustomVersionedConstants {
+    pub fn from_contents(contents: serde_json::Value) -> Result<Self, NetworkProfileError> {
+        let constants = serde_json::from_value(contents.clone())?;
+        Ok(Self {
+            contents,
+            constants: Arc::new(constants),
+        })
+    }
+
+    pub fn from_file(path: &Path) -> Result<Self, NetworkProfileError> {
+        let contents = serde_json::from_reader(BufReader::new(File::open(path)?))?;
+        Self::from_contents(contents)
+    }
+
+    pub fn constants(&self) -> &VersionedConstants {
+        &self.constants
+    }
+}
+
+impl PartialEq for CustomVersionedConstants {
+    fn eq(&self, other: &Self) -> bool {
+        self.contents == other.contents
+    }
+}
+
+impl Serialize for CustomVersionedConstants {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.contents.serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for CustomVersionedConstants {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let custom_constants = match serde_json::Value::deserialize(deserializer)? {
+            serde_json::Value::String(path) => Self::from_file(&PathBuf::from(path)),
+            contents => Self::from_contents(contents),
+        };
+        custom_constants.map_err(serde::de::Error::custom)
+    }
+}
+
+impl NetworkProfile {
+    pub fn mainnet() -> Self {
+        Self {
+            name: "mainnet".to_string(),
+            chain_id: ChainId::Mainnet,
+            strk_fee_token_address: contract_address!(STRK_FEE_TOKEN_ADDRESS),
+            eth_fee_token_address: contract_address!(ETH_FEE_TOKEN_ADDRESS),
+            sequencer_address: contract_address!(SEQUENCER_ADDRESS),
+            versioned_constants: None,
+        }
+    }
+
+    pub fn sepolia() -> Self {
+        Self {
+            name: "sepolia".to_string(),
+            chain_id: ChainId::Sepolia,
+            strk_fee_token_address: contract_address!(STRK_FEE_TOKEN_ADDRESS),
+            eth_fee_token_address: contract_address!(ETH_FEE_TOKEN_ADDRESS),
+            sequencer_address: contract_address!(SEQUENCER_ADDRESS),
+            versioned_constants: None,
+        }
+    }
+
+    /// Returns the built-in profile with this name, if any.
+    pub fn builtin(name: &str) -> Option<Self> {
+        match name {
+            "mainnet" => Some(Self::mainnet()),
+            "sepolia" => Some(Self::sepolia()),
+            _ => None,
+        }
+    }
+
+    /// Loads a custom profile. `.json` files are parsed as JSON, anything else as YAML.
+    pub fn from_file(path: &Path) -> Result<Self, NetworkProfileError> {
+        let reader = BufReader::new(File::open(path)?);
+        let profile = match path.extension().and_then(|extension| extension.to_str()) {
+            Some("json") => serde_json::from_reader(reader)?,
+            _ => serde_yaml::from_reader(reader)?,
+        };
+
+        Ok(profile)
+    }
+
+    /// Returns the built-in profile named `name_or_path`, or loads it as a file.
+    pub fn from_name_or_file(name_or_path: &str) -> Result<Self, NetworkProfileError> {
+        match Self::builtin(name_or_path) {
+            Some(profile) => Ok(profile),
+            None => Self::from_file(Path::new(name_or_path)),
+        }
+    }
+
+    pub fn chain_info(&self) -> ChainInfo {
+        ChainInfo {
+            chain_id: self.chain_id.clone(),
+            fee_token_addresses: FeeTokenAddresses {
+                strk_fee_token_address: self.strk_fee_token_address,
+                eth_fee_token_address: self.eth_fee_token_address,
+            },
+        }
+    }
+}
@@ -3,14 +3,40 @@ use blockifier::execution::contract_class::ContractClass;
 use blockifier::state::errors::StateError;
 use blockifier::state::state_api::{StateReader, StateResult};
 use rpc_client::client::RpcClient;
+use rpc_client::transport::TransportError;
 use starknet::core::types::BlockId;
 use starknet_api::core::{ClassHash, CompiledClassHash, ContractAddress, Nonce};
 use starknet_api::state::StorageKey;
 use starknet_types_core::felt::Felt;
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, Mutex};
 
+/// Compiled classes by class hash, shared by the readers of several blocks.
+///
+/// Classes are immutable once declared, so a class compiled for a block is valid for every
+/// later block.
+pub type ClassCache = Arc<Mutex<HashMap<ClassHash, ContractClass>>>;
+
+/// Values read at the block of the reader.
+#[derive(Default)]
+struct StateCache {
+    storage: HashMap<(ContractAddress, StorageKey), Felt>,
+    nonces: HashMap<ContractAddress, Nonce>,
+    class_hashes: HashMap<ContractAddress, ClassHash>,
+    compiled_class_hashes: HashMap<ClassHash, CompiledClassHash>,
+}
+
+/// Serves the state at a given block from the RPC node.
+///
+/// Every value is fetched once and remembered, blockifier reads the same storage slots,
+/// nonces and classes many times over a block. Values known to be accessed can be fetched
+/// in batches beforehand with [`AsyncRpcStateReader::prefetch`].
 pub struct AsyncRpcStateReader {
     rpc_client: RpcClient,
     block_id: BlockId,
+    state_cache: RefCell<StateCache>,
+    class_cache: ClassCache,
 }
 
 impl AsyncRpcStateReader {
@@ -18,8 +44,95 @@ impl AsyncRpcStateReader {
         Self {
             rpc_client,
             block_id,
+            state_cache: Default::default(),
+            class_cache: Default::default(),
         }
     }
+
+    /// Shares the compiled classes with other readers, so that each class is compiled once
+    /// when re-executing several blocks.
+    pub fn with_class_cache(mut self, class_cache: ClassCache) -> Self {
+        self.class_cache = class_cache;
+        self
+    }
+
+    /// Fetches storage values, nonces and class hashes in batches ahead of execution, so that
+    /// reading them later does not cost a request each.
+    ///
+    /// Values that cannot be fetched, for example because the contract does not exist yet,
+    /// are left out and read one by one when needed.
+    pub async fn prefetch(
+        &self,
+        storage_slots: &[(Felt, Felt)],
+        contract_addresses: &[Felt],
+    ) -> Result<(), TransportError> {
+        let values = self
+            .rpc_client
+            .get_storage_at_batch(self.block_id, storage_slots)
+            .await?;
+        let nonces = self
+            .rpc_client
+            .get_nonce_batch(self.block_id, contract_addresses)
+            .await?;
+        let class_hashes = self
+            .rpc_client
+            .get_class_hash_at_batch(self.block_id, contract_addresses)
+            .await?;
+
+        let mut state_cache = self.state_cache.borrow_mut();
+        for ((contract_address, key), value) in storage_slots.iter().zip(values) {
+            if let (Ok(slot), Ok(value)) = (to_storage_slot(*contract_address, *key), value) {
+                state_cache.storage.insert(slot, value);
+            }
+        }
+        for (contract_address, nonce) in contract_addresses.iter().zip(nonces) {
+            if let (Ok(contract_address), Ok(nonce)) =
+                (ContractAddress::try_from(*contract_address), nonce)
+            {
+                state_cache.nonces.insert(contract_address, Nonce(nonce));
+            }
+        }
+        for (contract_address, class_hash) in contract_addresses.iter().zip(class_hashes) {
+            if let (Ok(contract_address), Ok(class_hash)) =
+                (ContractAddress::try_from(*contract_address), class_hash)
+            {
+                state_cache
+                    .class_hashes
+                    .insert(contract_address, ClassHash(class_hash));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Prefetches a set of accessed keys by contract, as computed by `get_all_accessed_keys`
+    /// in `prove_block`, along with the nonce and class hash of each contract.
+    pub async fn prefetch_accessed_keys(
+        &self,
+        accessed_keys_by_address: &HashMap<ContractAddress, HashSet<StorageKey>>,
+    ) -> Result<(), TransportError> {
+        let storage_slots: Vec<_> = accessed_keys_by_address
+            .iter()
+            .flat_map(|(contract_address, storage_keys)| {
+                storage_keys
+                    .iter()
+                    .map(|storage_key| (*contract_address.key(), *storage_key.key()))
+            })
+            .collect();
+        let contract_addresses: Vec<_> = accessed_keys_by_address
+            .keys()
+            .map(|contract_address| *contract_address.key())
+            .collect();
+
+        self.prefetch(&storage_slots, &contract_addresses).await
+    }
+}
+
+fn to_storage_slot(
+    contract_address: Felt,
+    key: Felt,
+) -> Result<(ContractAddress, StorageKey), starknet_api::StarknetApiError> {
+    Ok((contract_address.try_into()?, key.try_into()?))
 }
 
 fn to_state_err<E: ToString>(e: E) -> StateError {
@@ -32,24 +145,85 @@ impl StateReader for AsyncRpcStateReader {
         contract_address: ContractAddress,
         key: StorageKey,
     ) -> StateResult<Felt> {
-        execute_coroutine(self.get_storage_at_async(contract_address, key)).map_err(to_state_err)?
+        let slot = (contract_address, key);
+        if let Some(value) = self.state_cache.borrow().storage.get(&slot) {
+            return Ok(*value);
+        }
+
+        let value = execute_coroutine(self.get_storage_at_async(contract_address, key))
+            .map_err(to_state_err)??;
+        self.state_cache.borrow_mut().storage.insert(slot, value);
+
+        Ok(value)
     }
 
     fn get_nonce_at(&self, contract_address: ContractAddress) -> StateResult<Nonce> {
-        execute_coroutine(self.get_nonce_at_async(contract_address)).map_err(to_state_err)?
+        if let Some(nonce) = self.state_cache.borrow().nonces.get(&contract_address) {
+            return Ok(*nonce);
+        }
+
+        let nonce = execute_coroutine(self.get_nonce_at_async(contract_address))
+            .map_err(to_state_err)??;
+        self.state_cache
+            .borrow_mut()
+            .nonces
+            .insert(contract_address, nonce);
+
+        Ok(nonce)
     }
 
     fn get_class_hash_at(&self, contract_address: ContractAddress) -> StateResult<ClassHash> {
-        execute_coroutine(self.get_class_hash_at_async(contract_address))
-            .map_err(|e| StateError::StateReadError(e.to_string()))?
+        if let Some(class_hash) = self
+            .state_cache
+            .borrow()
+            .class_hashes
+            .get(&contract_address)
+        {
+            return Ok(*class_hash);
+        }
+
+        let class_hash = execute_coroutine(self.get_class_hash_at_async(contract_address))
+            .map_err(|e| StateError::StateReadError(e.to_string()))??;
+        self.state_cache
+            .borrow_mut()
+            .class_hashes
+            .insert(contract_address, class_hash);
+
+        Ok(class_hash)
     }
 
     fn get_compiled_contract_class(&self, class_hash: ClassHash) -> StateResult<ContractClass> {
-        execute_coroutine(self.get_compiled_contract_class_async(class_hash))
-            .map_err(to_state_err)?
+        if let Some(contract_class) = self.class_cache.lock().unwrap().get(&class_hash) {
+            return Ok(contract_class.clone());
+        }
+
+        let contract_class = execute_coroutine(self.get_compiled_contract_class_async(class_hash))
+            .map_err(to_state_err)??;
+        self.class_cache
+            .lock()
+            .unwrap()
+            .insert(class_hash, contract_class.clone());
+
+        Ok(contract_class)
     }
 
     fn get_compiled_class_hash(&self, class_hash: ClassHash) -> StateResult<CompiledClassHash> {
-        execute_coroutine(self.get_compiled_class_hash_async(class_hash)).map_err(to_state_err)?
+        if let Some(compiled_class_hash) = self
+            .state_cache
+            .borrow()
+            .compiled_class_hashes
+            .get(&class_hash)
+        {
+            return Ok(*compiled_class_hash);
+        }
+
+        let compiled_class_hash = execute_coroutine(self.get_compiled_class_hash_async(class_hash))
+            .map_err(to_state_err)??;
+        self.state_cache
+            .borrow_mut()
+            .compiled_class_hashes
+            .insert(class_hash, compiled_class_hash);
+
+        Ok(compiled_class_hash)
     }
 }
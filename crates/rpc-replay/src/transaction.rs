@@ -9,10 +9,11 @@ use blockifier::transaction::account_transaction::AccountTransaction;
 use blockifier::transaction::errors::TransactionExecutionError;
 use rpc_client::client::RpcClient;
 use starknet::core::types::{
-    BlockId, DeclareTransaction, DeclareTransactionV1, DeclareTransactionV2, DeclareTransactionV3,
-    DeployAccountTransaction, DeployAccountTransactionV1, DeployAccountTransactionV3,
-    InvokeTransaction, InvokeTransactionV1, InvokeTransactionV3, L1HandlerTransaction,
-    ResourceBoundsMapping, Transaction, TransactionTrace, TransactionTraceWithHash,
+    BlockId, DeclareTransaction, DeclareTransactionV0, DeclareTransactionV1, DeclareTransactionV2,
+    DeclareTransactionV3, DeployAccountTransaction, DeployAccountTransactionV1,
+    DeployAccountTransactionV3, InvokeTransaction, InvokeTransactionV0, InvokeTransactionV1,
+    InvokeTransactionV3, L1HandlerTransaction, ResourceBoundsMapping, Transaction,
+    TransactionTrace, TransactionTraceWithHash,
 };
 use starknet::providers::{Provider, ProviderError};
 use starknet_api::core::{calculate_contract_address, ContractAddress, PatriciaKey};
@@ -39,6 +40,8 @@ pub enum ToBlockifierError {
     TransactionExecutionError(#[from] TransactionExecutionError),
     #[error("Felt Conversion Error: {0}")]
     FeltConversionError(#[from] FeltConversionError),
+    #[error("Missing declared class for transaction {0:#x}")]
+    MissingDeclaredClass(Felt),
 }
 
 pub fn resource_bounds_core_to_api(
@@ -68,29 +71,48 @@ pub async fn starknet_rs_to_blockifier(
     gas_prices: &GasPrices,
     client: &RpcClient,
     block_number: u64,
+) -> Result<blockifier::transaction::transaction_execution::Transaction, ToBlockifierError> {
+    let class_info = match declared_class_hash(sn_core_tx) {
+        Some(class_hash) => {
+            let contract_class = client
+                .starknet_rpc()
+                .get_class(BlockId::Number(block_number), class_hash)
+                .await?;
+            Some(create_class_info(contract_class)?)
+        }
+        None => None,
+    };
+
+    starknet_rs_to_blockifier_with_class_info(sn_core_tx, trace, gas_prices, class_info)
+}
+
+/// Converts a starknet-rs transaction to a blockifier one without querying the RPC.
+///
+/// Declare transactions require the `ClassInfo` of the declared class, the other
+/// transaction types ignore it.
+pub fn starknet_rs_to_blockifier_with_class_info(
+    sn_core_tx: &Transaction,
+    trace: &TransactionTraceWithHash,
+    gas_prices: &GasPrices,
+    class_info: Option<ClassInfo>,
 ) -> Result<blockifier::transaction::transaction_execution::Transaction, ToBlockifierError> {
     let blockifier_tx = match sn_core_tx {
         Transaction::Invoke(tx) => match tx {
-            InvokeTransaction::V0(_) => {
-                unimplemented!("starknet_rs_to_blockifier with InvokeTransaction::V0")
-            }
+            InvokeTransaction::V0(tx) => invoke_v0_to_blockifier(tx)?,
             InvokeTransaction::V1(tx) => invoke_v1_to_blockifier(tx)?,
             InvokeTransaction::V3(tx) => invoke_v3_to_blockifier(tx)?,
         },
-        Transaction::Declare(tx) => match tx {
-            DeclareTransaction::V0(_) => {
-                unimplemented!("starknet_rs_to_blockifier with DeclareTransaction::V0")
+        Transaction::Declare(tx) => {
+            let class_info = class_info.ok_or(ToBlockifierError::MissingDeclaredClass(
+                *sn_core_tx.transaction_hash(),
+            ))?;
+            match tx {
+                DeclareTransaction::V0(tx) => declare_v0_to_blockifier(tx, class_info)?,
+                DeclareTransaction::V1(tx) => declare_v1_to_blockifier(tx, class_info)?,
+                DeclareTransaction::V2(tx) => declare_v2_to_blockifier(tx, class_info)?,
+                DeclareTransaction::V3(tx) => declare_v3_to_blockifier(tx, class_info)?,
             }
-            DeclareTransaction::V1(tx) => {
-                declare_v1_to_blockifier(tx, client, block_number).await?
-            }
-            DeclareTransaction::V2(tx) => {
-                declare_v2_to_blockifier(tx, client, block_number).await?
-            }
-            DeclareTransaction::V3(tx) => {
-                declare_v3_to_blockifier(tx, client, block_number).await?
-            }
-        },
+        }
         Transaction::L1Handler(tx) => l1_handler_to_blockifier(tx, trace, gas_prices)?,
         Transaction::DeployAccount(tx) => match tx {
             DeployAccountTransaction::V1(tx) => deploy_account_v1_to_blockifier(tx)?,
@@ -105,18 +127,45 @@ pub async fn starknet_rs_to_blockifier(
     Ok(blockifier_tx)
 }
 
-async fn create_class_info(
-    class_hash: Felt,
-    client: &RpcClient,
-    block_number: u64,
+/// Returns the hash of the class declared by the transaction, if it is a declare transaction.
+pub fn declared_class_hash(sn_core_tx: &Transaction) -> Option<Felt> {
+    match sn_core_tx {
+        Transaction::Declare(tx) => Some(match tx {
+            DeclareTransaction::V0(tx) => tx.class_hash,
+            DeclareTransaction::V1(tx) => tx.class_hash,
+            DeclareTransaction::V2(tx) => tx.class_hash,
+            DeclareTransaction::V3(tx) => tx.class_hash,
+        }),
+        _ => None,
+    }
+}
+
+pub fn create_class_info(
+    contract_class: starknet::core::types::ContractClass,
 ) -> Result<ClassInfo, ToBlockifierError> {
-    // TODO: improve this to avoid retrieving this twice. Already done in lib.rs from prove-block
-    let starknet_contract_class: starknet::core::types::ContractClass = client
-        .starknet_rpc()
-        .get_class(BlockId::Number(block_number), class_hash)
-        .await?;
+    let (blockifier_contract_class, program_length, abi_length) =
+        compile_contract_class(contract_class)?;
 
-    let (blockifier_contract_class, program_length, abi_length) = match starknet_contract_class {
+    Ok(ClassInfo::new(
+        &blockifier_contract_class,
+        program_length,
+        abi_length,
+    )?)
+}
+
+/// Returns the compiled class along with the Sierra program and ABI lengths
+/// (both zero for legacy classes).
+fn compile_contract_class(
+    contract_class: starknet::core::types::ContractClass,
+) -> Result<
+    (
+        blockifier::execution::contract_class::ContractClass,
+        usize,
+        usize,
+    ),
+    ToBlockifierError,
+> {
+    let compiled_class = match contract_class {
         starknet::core::types::ContractClass::Sierra(sierra) => {
             let generic_sierra = GenericSierraContractClass::from(sierra);
             let flattened_sierra = generic_sierra.clone().to_starknet_core_contract_class()?;
@@ -141,11 +190,7 @@ async fn create_class_info(
         }
     };
 
-    Ok(ClassInfo::new(
-        &blockifier_contract_class,
-        program_length,
-        abi_length,
-    )?)
+    Ok(compiled_class)
 }
 
 fn da_mode_core_to_api(
@@ -161,6 +206,32 @@ fn da_mode_core_to_api(
     }
 }
 
+fn invoke_v0_to_blockifier(
+    tx: &InvokeTransactionV0,
+) -> Result<blockifier::transaction::transaction_execution::Transaction, ToBlockifierError> {
+    let tx_hash = TransactionHash(tx.transaction_hash);
+    let api_tx = starknet_api::transaction::InvokeTransaction::V0(
+        starknet_api::transaction::InvokeTransactionV0 {
+            max_fee: Fee(felt_to_u128(&tx.max_fee)?),
+            signature: starknet_api::transaction::TransactionSignature(tx.signature.to_vec()),
+            contract_address: ContractAddress(PatriciaKey::try_from(tx.contract_address)?),
+            entry_point_selector: starknet_api::core::EntryPointSelector(tx.entry_point_selector),
+            calldata: starknet_api::transaction::Calldata(Arc::new(tx.calldata.to_vec())),
+        },
+    );
+
+    let invoke = blockifier::transaction::transactions::InvokeTransaction {
+        tx: api_tx,
+        tx_hash,
+        only_query: false,
+    };
+    Ok(
+        blockifier::transaction::transaction_execution::Transaction::AccountTransaction(
+            AccountTransaction::Invoke(invoke),
+        ),
+    )
+}
+
 fn invoke_v1_to_blockifier(
     tx: &InvokeTransactionV1,
 ) -> Result<blockifier::transaction::transaction_execution::Transaction, ToBlockifierError> {
@@ -220,10 +291,35 @@ fn invoke_v3_to_blockifier(
     )
 }
 
-async fn declare_v1_to_blockifier(
+fn declare_v0_to_blockifier(
+    tx: &DeclareTransactionV0,
+    class_info: ClassInfo,
+) -> Result<blockifier::transaction::transaction_execution::Transaction, ToBlockifierError> {
+    let tx_hash = TransactionHash(tx.transaction_hash);
+    let api_tx = starknet_api::transaction::DeclareTransaction::V0(
+        starknet_api::transaction::DeclareTransactionV0V1 {
+            max_fee: Fee(felt_to_u128(&tx.max_fee)?),
+            signature: starknet_api::transaction::TransactionSignature(tx.signature.clone()),
+            // Declare V0 transactions do not have a nonce
+            nonce: starknet_api::core::Nonce(Felt::ZERO),
+            class_hash: starknet_api::core::ClassHash(tx.class_hash),
+            sender_address: ContractAddress(PatriciaKey::try_from(tx.sender_address)?),
+        },
+    );
+    let declare = blockifier::transaction::transactions::DeclareTransaction::new(
+        api_tx, tx_hash, class_info,
+    )?;
+
+    Ok(
+        blockifier::transaction::transaction_execution::Transaction::AccountTransaction(
+            AccountTransaction::Declare(declare),
+        ),
+    )
+}
+
+fn declare_v1_to_blockifier(
     tx: &DeclareTransactionV1,
-    client: &RpcClient,
-    block_number: u64,
+    class_info: ClassInfo,
 ) -> Result<blockifier::transaction::transaction_execution::Transaction, ToBlockifierError> {
     let tx_hash = TransactionHash(tx.transaction_hash);
     let api_tx = starknet_api::transaction::DeclareTransaction::V1(
@@ -235,7 +331,6 @@ async fn declare_v1_to_blockifier(
             sender_address: ContractAddress(PatriciaKey::try_from(tx.sender_address)?),
         },
     );
-    let class_info = create_class_info(tx.class_hash, client, block_number).await?;
     let declare = blockifier::transaction::transactions::DeclareTransaction::new(
         api_tx, tx_hash, class_info,
     )?;
@@ -247,10 +342,9 @@ async fn declare_v1_to_blockifier(
     )
 }
 
-async fn declare_v2_to_blockifier(
+fn declare_v2_to_blockifier(
     tx: &DeclareTransactionV2,
-    client: &RpcClient,
-    block_number: u64,
+    class_info: ClassInfo,
 ) -> Result<blockifier::transaction::transaction_execution::Transaction, ToBlockifierError> {
     let tx_hash = TransactionHash(tx.transaction_hash);
     let api_tx = starknet_api::transaction::DeclareTransaction::V2(
@@ -263,7 +357,6 @@ async fn declare_v2_to_blockifier(
             sender_address: ContractAddress(PatriciaKey::try_from(tx.sender_address)?),
         },
     );
-    let class_info = create_class_info(tx.class_hash, client, block_number).await?;
     let declare = blockifier::transaction::transactions::DeclareTransaction::new(
         api_tx, tx_hash, class_info,
     )?;
@@ -275,10 +368,9 @@ async fn declare_v2_to_blockifier(
     )
 }
 
-async fn declare_v3_to_blockifier(
+fn declare_v3_to_blockifier(
     tx: &DeclareTransactionV3,
-    client: &RpcClient,
-    block_number: u64,
+    class_info: ClassInfo,
 ) -> Result<blockifier::transaction::transaction_execution::Transaction, ToBlockifierError> {
     let tx_hash = TransactionHash(tx.transaction_hash);
     let api_tx = starknet_api::transaction::DeclareTransaction::V3(
@@ -298,7 +390,6 @@ async fn declare_v3_to_blockifier(
             ),
         },
     );
-    let class_info = create_class_info(tx.class_hash, client, block_number).await?;
     let declare = blockifier::transaction::transactions::DeclareTransaction::new(
         api_tx, tx_hash, class_info,
     )?;